@@ -0,0 +1,32 @@
+use advent_of_code_rs::registry;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// Benchmark both parts of each day on its real input, in a group per day so that a
+/// single day can be selected with e.g. `cargo bench -- day18`.
+fn bench_days(c: &mut Criterion) {
+    let solutions = registry();
+    for day in 1..=25 {
+        let solution = match solutions.get(&day) {
+            Some(solution) => solution,
+            None => continue,
+        };
+
+        let input_path = format!("{}/src/day{:02}_input.txt", env!("CARGO_MANIFEST_DIR"), day);
+        let input = match std::fs::read_to_string(&input_path) {
+            Ok(input) => input,
+            Err(_) => {
+                eprintln!("Skipping day {}: No input at {}", day, input_path);
+                continue;
+            }
+        };
+
+        let mut group = c.benchmark_group(format!("day{:02}", day));
+        group.sample_size(10);
+        group.bench_function("part1", |b| b.iter(|| solution.part1(&input)));
+        group.bench_function("part2", |b| b.iter(|| solution.part2(&input)));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_days);
+criterion_main!(benches);
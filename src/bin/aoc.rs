@@ -0,0 +1,89 @@
+use advent_of_code_rs::{registered_days, solve};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: aoc <day> <part> [--input <file>] [--time]
+       aoc --list
+    where: day is between 1 and 25 and part is 1 or 2,
+           with the input read from stdin unless --input is given,
+           and --time printing the time taken to stderr,
+           and --list printing the available days and their inputs";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    exit(1);
+}
+
+/// The input of a day bundled with the source tree.
+fn bundled_input_path(day: u8) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join(format!("day{:02}_input.txt", day))
+}
+
+fn list_days() {
+    for day in registered_days() {
+        let path = bundled_input_path(day);
+        if path.is_file() {
+            println!("Day {:2}: input {}", day, path.display());
+        } else {
+            println!("Day {:2}: no input", day);
+        }
+    }
+}
+
+fn main() {
+    let mut positional_args = Vec::new();
+    let mut input_path = None;
+    let mut print_time = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input_path = Some(args.next().unwrap_or_else(|| fail(USAGE))),
+            "--time" => print_time = true,
+            "--list" => {
+                list_days();
+                return;
+            }
+            _ => positional_args.push(arg),
+        }
+    }
+
+    let (day, part) = match positional_args.as_slice() {
+        [day, part] => match (day.parse::<u8>(), part.parse::<u8>()) {
+            (Ok(day), Ok(part)) => (day, part),
+            _ => fail(USAGE),
+        },
+        _ => fail(USAGE),
+    };
+
+    let input = match input_path {
+        Some(path) => std::fs::read_to_string(&path)
+            .unwrap_or_else(|error| fail(&format!("Error reading {}: {}", path, error))),
+        None => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .unwrap_or_else(|error| fail(&format!("Error reading stdin: {}", error)));
+            input
+        }
+    };
+
+    let start = Instant::now();
+    match solve(day, part, &input) {
+        Some(answer) => {
+            let elapsed = start.elapsed();
+            println!("{}", answer);
+            if print_time {
+                eprintln!("Solved in {:.3} ms", elapsed.as_secs_f64() * 1000.);
+            }
+        }
+        None => fail(&format!(
+            "No solution for day {} part {}\n{}",
+            day, part, USAGE
+        )),
+    }
+}
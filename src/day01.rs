@@ -1,25 +1,30 @@
-fn sum_required_fuel(input_string: &str, fuel_calculator: fn(u32) -> u32) -> String {
+fn sum_required_fuel(input_string: &str, fuel_calculator: fn(u64) -> u64) -> String {
     input_string
         .lines()
-        .map(|line| line.parse::<u32>().unwrap())
+        .map(|line| line.parse::<u64>().unwrap())
         .map(fuel_calculator)
-        .sum::<u32>()
+        .sum::<u64>()
         .to_string()
 }
 
+/// The fuel required to launch the given mass, where masses too small to need fuel need zero.
+fn fuel_for_mass(mass: u64) -> u64 {
+    (mass / 3).saturating_sub(2)
+}
+
+/// The fuel required for a module of the given mass, followed by the fuel required for that
+/// fuel and so on, ending before the first zero requirement.
+pub fn fuel_iter(mass: u64) -> impl Iterator<Item = u64> {
+    std::iter::successors(Some(fuel_for_mass(mass)), |&fuel| Some(fuel_for_mass(fuel)))
+        .take_while(|&fuel| fuel > 0)
+}
+
 pub fn part1(input_string: &str) -> String {
-    sum_required_fuel(input_string, |mass| mass / 3 - 2)
+    sum_required_fuel(input_string, fuel_for_mass)
 }
 
 pub fn part2(input_string: &str) -> String {
-    fn required_fuel(mass: u32) -> u32 {
-        match (mass / 3).checked_sub(2) {
-            Some(fuel) => fuel + required_fuel(fuel),
-            None => 0,
-        }
-    }
-
-    sum_required_fuel(input_string, required_fuel)
+    sum_required_fuel(input_string, |mass| fuel_iter(mass).sum())
 }
 
 #[test]
@@ -40,3 +45,16 @@ fn tests_part2() {
 
     assert_eq!("4890696", part2(include_str!("day01_input.txt")));
 }
+
+#[test]
+fn tests_fuel_iter() {
+    assert_eq!(
+        fuel_iter(1969).collect::<Vec<_>>(),
+        vec![654, 216, 70, 21, 5]
+    );
+    assert_eq!(fuel_iter(1969).sum::<u64>(), 966);
+    assert_eq!(fuel_iter(100_756).sum::<u64>(), 50346);
+    assert_eq!(fuel_iter(2).count(), 0);
+    assert_eq!(fuel_iter(0).count(), 0);
+    assert_eq!(part1("2\n14"), "2");
+}
@@ -1,38 +1,25 @@
-use std::cmp;
+use crate::direction::Direction;
+use crate::point::Point;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::collections::HashSet;
-use std::ops;
 
-#[derive(PartialEq, Eq, Hash, Copy, Clone)]
-struct Point {
-    x: i32,
-    y: i32,
+/// A point where the two wires cross.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossing {
+    pub point: Point,
+    /// The Manhattan distance from the central port.
+    pub distance: u32,
+    /// The sum of the steps along both wires to first reach the point.
+    pub steps: u32,
 }
 
-impl Point {
-    fn new(x: i32, y: i32) -> Point {
-        Point { x, y }
-    }
-
-    fn direction(specifier: char) -> Point {
-        match specifier {
-            'U' => Point::new(0, 1),
-            'R' => Point::new(1, 0),
-            'D' => Point::new(0, -1),
-            'L' => Point::new(-1, 0),
-            _ => panic!("Invalid direction specifier: {}", specifier),
-        }
-    }
-
-    fn distance_from_origin(self) -> u32 {
-        self.x.abs() as u32 + self.y.abs() as u32
-    }
-}
-
-impl ops::AddAssign<Point> for Point {
-    fn add_assign(&mut self, rhs: Point) {
-        self.x += rhs.x;
-        self.y += rhs.y;
+fn direction(specifier: char) -> Direction {
+    match specifier {
+        'U' => Direction::Up,
+        'R' => Direction::Right,
+        'D' => Direction::Down,
+        'L' => Direction::Left,
+        _ => panic!("Invalid direction specifier: {}", specifier),
     }
 }
 
@@ -40,11 +27,11 @@ fn parse_wire_points<F>(string: &str, mut on_visit: F)
 where
     F: FnMut(Point, u32),
 {
-    let mut current_position = Point::new(0, 0);
+    let mut current_position = Point::ORIGIN;
     let mut current_step: u32 = 0;
 
     for word in string.split(',') {
-        let direction = Point::direction(word.chars().next().unwrap());
+        let direction = direction(word.chars().next().unwrap()).as_offset();
         let steps = word[1..].parse::<i32>().unwrap();
 
         for _ in 0..steps {
@@ -55,40 +42,50 @@ where
     }
 }
 
-pub fn part1(input_string: &str) -> String {
-    let mut lines = input_string.lines();
-    let mut first_wire_points = HashSet::new();
-
-    parse_wire_points(lines.next().unwrap(), |point, _| {
-        first_wire_points.insert(point);
-    });
-
-    let mut closest_distance = std::u32::MAX;
-    parse_wire_points(lines.next().unwrap(), |point, _| {
-        if first_wire_points.contains(&point) {
-            closest_distance = cmp::min(closest_distance, point.distance_from_origin());
-        }
-    });
-
-    closest_distance.to_string()
-}
-
-pub fn part2(input_string: &str) -> String {
+/// The points where the two wires cross, in the order reached along the second wire.
+pub fn intersections(input_string: &str) -> Vec<Crossing> {
     let mut lines = input_string.lines();
+    // From point to the step it was first reached on each wire:
     let mut first_wire_points = HashMap::new();
+    let mut second_wire_points = HashMap::new();
 
     parse_wire_points(lines.next().unwrap(), |point, step| {
         first_wire_points.entry(point).or_insert(step);
     });
 
-    let mut fewest_steps = std::u32::MAX;
+    let mut crossings = Vec::new();
     parse_wire_points(lines.next().unwrap(), |point, step| {
-        if let Some(&value) = first_wire_points.get(&point) {
-            fewest_steps = cmp::min(fewest_steps, step + value);
+        if let Entry::Vacant(entry) = second_wire_points.entry(point) {
+            entry.insert(step);
+            if let Some(&first_wire_step) = first_wire_points.get(&point) {
+                crossings.push(Crossing {
+                    point,
+                    distance: point.manhattan_distance(Point::ORIGIN),
+                    steps: first_wire_step + step,
+                });
+            }
         }
     });
 
-    fewest_steps.to_string()
+    crossings
+}
+
+pub fn part1(input_string: &str) -> String {
+    intersections(input_string)
+        .iter()
+        .map(|crossing| crossing.distance)
+        .min()
+        .unwrap()
+        .to_string()
+}
+
+pub fn part2(input_string: &str) -> String {
+    intersections(input_string)
+        .iter()
+        .map(|crossing| crossing.steps)
+        .min()
+        .unwrap()
+        .to_string()
 }
 
 #[test]
@@ -120,3 +117,44 @@ fn tests_part2() {
 
     assert_eq!(part2(include_str!("day03_input.txt")), "14746");
 }
+
+#[test]
+fn tests_intersections() {
+    assert_eq!(
+        intersections("R8,U5,L5,D3\nU7,R6,D4,L4"),
+        vec![
+            Crossing {
+                point: Point::new(6, 5),
+                distance: 11,
+                steps: 30,
+            },
+            Crossing {
+                point: Point::new(3, 3),
+                distance: 6,
+                steps: 40,
+            }
+        ]
+    );
+
+    let crossings =
+        intersections("R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83");
+    assert_eq!(crossings.iter().map(|c| c.distance).min(), Some(159));
+    assert_eq!(crossings.iter().map(|c| c.steps).min(), Some(610));
+
+    // A wire crossing itself is not an intersection, and a point is only reported once:
+    assert_eq!(
+        intersections("R2,U1,L1,D2\nU1,R1,D1,U1"),
+        vec![
+            Crossing {
+                point: Point::new(1, 1),
+                distance: 2,
+                steps: 6,
+            },
+            Crossing {
+                point: Point::new(1, 0),
+                distance: 1,
+                steps: 4,
+            }
+        ]
+    );
+}
@@ -1,82 +1,77 @@
-use std::cmp::Ordering;
+use crate::parse;
+
+/// If a six digit password has never decreasing digits and at least two equal adjacent digits.
+/// With `strict_group`, two equal adjacent digits must not be part of a larger group of
+/// equal digits.
+pub fn is_valid(password: u32, strict_group: bool) -> bool {
+    let digits = parse::digits_of(u64::from(password));
+    if digits.windows(2).any(|pair| pair[0] > pair[1]) {
+        return false;
+    }
 
-pub fn part1(input_string: &str) -> String {
-    let mut parts = input_string.trim().split('-');
-    let from = parts.next().unwrap().parse::<i32>().unwrap();
-    let to = parts.next().unwrap().parse::<i32>().unwrap();
-
-    let mut meeting_criteria_count = 0;
-    'outer: for i in from..=to {
-        let mut divider = 1;
-        let mut last_digit = 10;
-        let mut two_digits_adjacent = false;
-
-        while divider <= 100_000 {
-            let digit = (i / divider) % 10;
-            match digit.cmp(&last_digit) {
-                Ordering::Greater => {
-                    continue 'outer;
-                }
-                Ordering::Equal => {
-                    two_digits_adjacent = true;
-                }
-                _ => {}
-            }
-
-            last_digit = digit;
-            divider *= 10;
-        }
-        if two_digits_adjacent {
-            meeting_criteria_count += 1;
-        }
+    // Since digits never decrease, equal digits are always in a single group:
+    let mut group_lengths = [0; 10];
+    for &digit in digits.iter() {
+        group_lengths[digit as usize] += 1;
     }
+    group_lengths
+        .iter()
+        .any(|&length| length == 2 || (length > 2 && !strict_group))
+}
+
+/// The valid passwords in the range `lo..=hi`, in increasing order.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn valid_passwords(lo: u32, hi: u32, strict_group: bool) -> Vec<u32> {
+    use rayon::prelude::*;
 
-    meeting_criteria_count.to_string()
+    (lo..=hi)
+        .into_par_iter()
+        .filter(|&password| is_valid(password, strict_group))
+        .collect()
 }
 
-pub fn part2(input_string: &str) -> String {
+/// The valid passwords in the range `lo..=hi`, in increasing order.
+#[cfg(target_arch = "wasm32")]
+pub fn valid_passwords(lo: u32, hi: u32, strict_group: bool) -> Vec<u32> {
+    (lo..=hi)
+        .filter(|&password| is_valid(password, strict_group))
+        .collect()
+}
+
+/// The number of valid passwords in the range `lo..=hi`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn count_valid(lo: u32, hi: u32, strict_group: bool) -> usize {
+    use rayon::prelude::*;
+
+    (lo..=hi)
+        .into_par_iter()
+        .filter(|&password| is_valid(password, strict_group))
+        .count()
+}
+
+/// The number of valid passwords in the range `lo..=hi`.
+#[cfg(target_arch = "wasm32")]
+pub fn count_valid(lo: u32, hi: u32, strict_group: bool) -> usize {
+    (lo..=hi)
+        .filter(|&password| is_valid(password, strict_group))
+        .count()
+}
+
+fn parse_range(input_string: &str) -> (u32, u32) {
     let mut parts = input_string.trim().split('-');
-    let from = parts.next().unwrap().parse::<i32>().unwrap();
-    let to = parts.next().unwrap().parse::<i32>().unwrap();
-
-    let mut meeting_criteria_count = 0;
-    'outer: for i in from..=to {
-        let mut divider = 1;
-        let mut last_digit = 10;
-        let mut two_digits_adjacent = false;
-        let mut digits_adjacent_streak = 1;
-
-        while divider <= 100_000 {
-            let digit = (i / divider) % 10;
-            match digit.cmp(&last_digit) {
-                Ordering::Greater => {
-                    continue 'outer;
-                }
-                Ordering::Equal => {
-                    digits_adjacent_streak += 1;
-                }
-                Ordering::Less => {
-                    if digits_adjacent_streak == 2 {
-                        two_digits_adjacent = true;
-                    }
-                    digits_adjacent_streak = 1;
-                }
-            }
-
-            last_digit = digit;
-            divider *= 10;
-        }
-
-        if digits_adjacent_streak == 2 {
-            two_digits_adjacent = true;
-        }
-
-        if two_digits_adjacent {
-            meeting_criteria_count += 1;
-        }
-    }
+    let from = parts.next().unwrap().parse::<u32>().unwrap();
+    let to = parts.next().unwrap().parse::<u32>().unwrap();
+    (from, to)
+}
 
-    meeting_criteria_count.to_string()
+pub fn part1(input_string: &str) -> String {
+    let (from, to) = parse_range(input_string);
+    count_valid(from, to, false).to_string()
+}
+
+pub fn part2(input_string: &str) -> String {
+    let (from, to) = parse_range(input_string);
+    count_valid(from, to, true).to_string()
 }
 
 #[test]
@@ -95,3 +90,23 @@ fn tests_part2() {
 
     assert_eq!(part2(include_str!("day04_input.txt")), "1142");
 }
+
+#[test]
+fn tests_valid_passwords() {
+    assert!(is_valid(111_111, false));
+    assert!(!is_valid(111_111, true));
+    assert!(!is_valid(223_450, false));
+    assert!(!is_valid(123_789, false));
+    assert!(is_valid(112_233, true));
+    assert!(!is_valid(123_444, true));
+    assert!(is_valid(123_444, false));
+    assert!(is_valid(111_122, true));
+
+    assert_eq!(
+        valid_passwords(111_119, 111_130, false),
+        vec![111_119, 111_122, 111_123, 111_124, 111_125, 111_126, 111_127, 111_128, 111_129]
+    );
+    assert_eq!(valid_passwords(111_119, 111_130, true), vec![111_122]);
+    assert_eq!(count_valid(111_119, 111_130, false), 9);
+    assert_eq!(count_valid(111_119, 111_130, true), 1);
+}
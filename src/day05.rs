@@ -1,17 +1,50 @@
 use crate::int_code::Program;
+use std::sync::{Arc, Mutex};
 
-fn run_with_input(input_string: &str, input: i64) -> String {
+/// Run the diagnostic program with the given system ID, returning the final diagnostic code.
+///
+/// All outputs before the final one are test results which should be zero - if not,
+/// an error describing the failed test and the address of its output instruction is returned.
+pub fn run_diagnostics(input_string: &str, system_id: i64) -> Result<i64, String> {
     let mut program = Program::parse(input_string);
-    program.input(input);
-    program.run_for_output().last().unwrap().to_string()
+
+    let output_addresses = Arc::new(Mutex::new(Vec::new()));
+    let output_addresses_in_hook = output_addresses.clone();
+    program.set_trace_hook(move |address, opcode_and_parameter_modes| {
+        if opcode_and_parameter_modes % 100 == 4 {
+            output_addresses_in_hook.lock().unwrap().push(address);
+        }
+    });
+
+    program.input(system_id);
+    let output = program.run_to_halt();
+    let output_addresses = output_addresses.lock().unwrap();
+
+    match output.split_last() {
+        Some((&diagnostic_code, test_results)) => {
+            if let Some((index, &value)) = test_results
+                .iter()
+                .enumerate()
+                .find(|(_, &value)| value != 0)
+            {
+                Err(format!(
+                    "Diagnostic test failed with output {} from instruction at address {}",
+                    value, output_addresses[index]
+                ))
+            } else {
+                Ok(diagnostic_code)
+            }
+        }
+        None => Err("No diagnostic code output".to_string()),
+    }
 }
 
 pub fn part1(input_string: &str) -> String {
-    run_with_input(input_string, 1)
+    run_diagnostics(input_string, 1).unwrap().to_string()
 }
 
 pub fn part2(input_string: &str) -> String {
-    run_with_input(input_string, 5)
+    run_diagnostics(input_string, 5).unwrap().to_string()
 }
 
 #[test]
@@ -23,3 +56,24 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day05_input.txt")), "1558663");
 }
+
+#[test]
+fn tests_run_diagnostics() {
+    assert_eq!(
+        run_diagnostics(include_str!("day05_input.txt"), 1),
+        Ok(15_097_178)
+    );
+    assert_eq!(
+        run_diagnostics(include_str!("day05_input.txt"), 5),
+        Ok(1_558_663)
+    );
+
+    assert_eq!(
+        run_diagnostics("104,0,104,3,104,7,99", 1),
+        Err("Diagnostic test failed with output 3 from instruction at address 2".to_string())
+    );
+    assert_eq!(
+        run_diagnostics("99", 1),
+        Err("No diagnostic code output".to_string())
+    );
+}
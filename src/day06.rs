@@ -1,71 +1,101 @@
 use std::collections::HashMap;
-use std::collections::HashSet;
-use std::collections::VecDeque;
 
-pub fn part1(string: &str) -> String {
-    let mut map = HashMap::new();
+/// The objects in orbit around each other, forming a tree rooted at the universal Center of Mass.
+pub struct OrbitMap<'a> {
+    /// From an object to the object it directly orbits.
+    orbits: HashMap<&'a str, &'a str>,
+}
 
-    for line in string.lines() {
-        let mut parts = line.split(')');
-        let orbited_by = map.entry(parts.next().unwrap()).or_insert_with(Vec::new);
-        orbited_by.push(parts.next().unwrap());
+impl<'a> OrbitMap<'a> {
+    /// Parse lines of the form `A)B`, meaning that B is in orbit around A.
+    pub fn parse(input_string: &'a str) -> Result<OrbitMap<'a>, String> {
+        let mut orbits = HashMap::new();
+        for line in input_string.lines() {
+            let mut parts = line.trim().split(')');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(orbited), Some(orbiting), None) => {
+                    orbits.insert(orbiting, orbited);
+                }
+                _ => return Err(format!("Invalid orbit: '{}'", line)),
+            }
+        }
+        Ok(OrbitMap { orbits })
     }
 
-    fn checksum(map: &HashMap<&str, Vec<&str>>, name: &str, depth: u32) -> u32 {
-        depth
-            + map.get(name).map_or(0, |list| {
-                list.iter()
-                    .map(|entry| checksum(map, entry, depth + 1))
-                    .sum::<u32>()
-            })
+    fn contains(&self, object: &str) -> bool {
+        self.orbits.contains_key(object) || self.orbits.values().any(|&orbited| orbited == object)
     }
 
-    checksum(&map, "COM", 0).to_string()
-}
-
-pub fn part2(string: &str) -> String {
-    let mut map = HashMap::new();
-    let mut target: &str = "";
-
-    for line in string.lines() {
-        let mut parts = line.split(')');
-        let orbited_name = parts.next().unwrap();
-        let orbits_name = parts.next().unwrap();
-
-        let orbits = map.entry(orbits_name).or_insert_with(Vec::new);
-        orbits.push(orbited_name);
-
-        let orbited = map.entry(orbited_name).or_insert_with(Vec::new);
-        orbited.push(orbits_name);
+    /// The objects which `object` orbits directly or indirectly, nearest first.
+    pub fn ancestors(&self, object: &str) -> Result<Vec<&'a str>, String> {
+        if !self.contains(object) {
+            return Err(format!("Unknown object: {}", object));
+        }
 
-        if orbits_name == "SAN" {
-            target = orbited_name;
+        let mut ancestors = Vec::new();
+        let mut current = object;
+        while let Some(&orbited) = self.orbits.get(current) {
+            if ancestors.len() == self.orbits.len() {
+                return Err(format!("Orbits of {} form a cycle", object));
+            }
+            ancestors.push(orbited);
+            current = orbited;
         }
+        Ok(ancestors)
     }
 
-    let mut visited: HashSet<&str> = HashSet::new();
-    let mut to_visit = VecDeque::new();
+    /// The total number of direct and indirect orbits.
+    pub fn total_orbits(&self) -> Result<usize, String> {
+        let mut total = 0;
+        for object in self.orbits.keys() {
+            total += self.ancestors(object)?.len();
+        }
+        Ok(total)
+    }
 
-    visited.insert("YOU");
-    to_visit.push_back((0, "YOU"));
+    /// The number of orbital transfers needed to move from the object `from` orbits to
+    /// the object `to` orbits, passing through their nearest common ancestor.
+    pub fn transfers(&self, from: &str, to: &str) -> Result<usize, String> {
+        let from_ancestors = self.ancestors(from)?;
+        let to_ancestors = self.ancestors(to)?;
+
+        let to_distances: HashMap<&str, usize> = to_ancestors
+            .iter()
+            .enumerate()
+            .map(|(distance, &ancestor)| (ancestor, distance))
+            .collect();
+        from_ancestors
+            .iter()
+            .enumerate()
+            .find_map(|(distance, ancestor)| {
+                to_distances
+                    .get(ancestor)
+                    .map(|to_distance| distance + to_distance)
+            })
+            .ok_or_else(|| format!("{} and {} have no common ancestor", from, to))
+    }
+}
 
-    while !to_visit.is_empty() {
-        let (distance, name) = to_visit.pop_front().unwrap();
+/// The total number of orbits and the orbital transfers from YOU to SAN, parsing the orbit
+/// map only once.
+pub fn solve(string: &str) -> (usize, usize) {
+    OrbitMap::parse(string)
+        .and_then(|map| Ok((map.total_orbits()?, map.transfers("YOU", "SAN")?)))
+        .unwrap_or_else(|error| panic!("{}", error))
+}
 
-        if let Some(list) = map.get(name) {
-            for entry in list.iter() {
-                if visited.insert(entry) {
-                    if *entry == target {
-                        return distance.to_string();
-                    }
-                    let new_distance = distance + 1;
-                    to_visit.push_back((new_distance, entry));
-                }
-            }
-        }
-    }
+pub fn part1(string: &str) -> String {
+    OrbitMap::parse(string)
+        .and_then(|map| map.total_orbits())
+        .unwrap_or_else(|error| panic!("{}", error))
+        .to_string()
+}
 
-    "Unable to find path".to_string()
+pub fn part2(string: &str) -> String {
+    OrbitMap::parse(string)
+        .and_then(|map| map.transfers("YOU", "SAN"))
+        .unwrap_or_else(|error| panic!("{}", error))
+        .to_string()
 }
 
 #[test]
@@ -113,3 +143,59 @@ I)SAN"
 
     assert_eq!(part2(include_str!("day06_input.txt")), "460");
 }
+
+#[test]
+fn tests_orbit_map() {
+    let input = "COM)B
+B)C
+C)D
+D)E
+E)F
+B)G
+G)H
+D)I
+E)J
+J)K
+K)L
+K)YOU
+I)SAN";
+    let map = OrbitMap::parse(input).unwrap();
+    assert_eq!(map.ancestors("COM"), Ok(vec![]));
+    assert_eq!(map.ancestors("I"), Ok(vec!["D", "C", "B", "COM"]));
+    assert_eq!(map.total_orbits(), Ok(54));
+    assert_eq!(map.transfers("YOU", "SAN"), Ok(4));
+    assert_eq!(map.transfers("SAN", "YOU"), Ok(4));
+    assert_eq!(map.transfers("H", "F"), Ok(4));
+    assert_eq!(map.transfers("L", "J"), Ok(2));
+    assert_eq!(map.transfers("L", "YOU"), Ok(0));
+
+    assert_eq!(
+        map.transfers("YOU", "SANTA"),
+        Err("Unknown object: SANTA".to_string())
+    );
+    assert_eq!(map.ancestors("X"), Err("Unknown object: X".to_string()));
+    assert!(OrbitMap::parse("COM)B\nB-C").is_err());
+    assert_eq!(
+        OrbitMap::parse("A)B\nX)Y").unwrap().transfers("B", "Y"),
+        Err("B and Y have no common ancestor".to_string())
+    );
+}
+
+#[test]
+fn tests_solve() {
+    let input = "COM)B
+B)C
+C)D
+D)E
+E)F
+B)G
+G)H
+D)I
+E)J
+J)K
+K)L
+K)YOU
+I)SAN";
+    assert_eq!(solve(input), (54, 4));
+    assert_eq!(solve(include_str!("day06_input.txt")), (273_985, 460));
+}
@@ -1,8 +1,17 @@
-use crate::int_code::Program;
+use crate::int_code::{run_ring, Program};
 use crate::permutation::all_permutations;
-use std::cell::RefCell;
 
 pub fn part1(input_string: &str) -> String {
+    max_thruster_signal(input_string).to_string()
+}
+
+pub fn part2(input_string: &str) -> String {
+    max_thruster_signal_feedback(input_string).to_string()
+}
+
+/// The highest signal that can be sent to the thrusters by amplifiers in series
+/// using phase settings 0 to 4.
+pub fn max_thruster_signal(input_string: &str) -> i64 {
     let program = Program::parse(input_string);
     let mut phase_settings = vec![0, 1, 2, 3, 4];
     let mut strongest_signal = 0;
@@ -21,50 +30,36 @@ pub fn part1(input_string: &str) -> String {
         strongest_signal = std::cmp::max(strongest_signal, signal);
     });
 
-    strongest_signal.to_string()
+    strongest_signal
 }
 
-pub fn part2(input_string: &str) -> String {
+/// The highest signal that can be sent to the thrusters by amplifiers in a feedback
+/// loop using phase settings 5 to 9.
+pub fn max_thruster_signal_feedback(input_string: &str) -> i64 {
     let program = Program::parse(input_string);
     let mut phase_settings = vec![5, 6, 7, 8, 9];
     let mut strongest_signal = 0;
 
     all_permutations(&mut phase_settings, &mut |permutation: &Vec<i64>| {
-        let mut amplifier_programs = Vec::new();
-        for &phase in permutation.iter() {
-            let mut new_program = program.clone();
-            new_program.input(phase);
-            amplifier_programs.push(RefCell::new(new_program));
-        }
+        let mut amplifier_programs: Vec<Program> = permutation
+            .iter()
+            .map(|&phase| {
+                let mut new_program = program.clone();
+                new_program.input(phase);
+                new_program
+            })
+            .collect();
 
-        amplifier_programs[0].borrow_mut().input(0);
-
-        let mut last_signal_output = 0;
-        'outer: loop {
-            for i in 0..5 {
-                let mut current_program = amplifier_programs[i].borrow_mut();
-                let output = current_program.run_for_output();
-
-                if i == 4 {
-                    if !output.is_empty() {
-                        last_signal_output = *output.last().unwrap();
-                    }
-                    if current_program.is_halted() {
-                        break 'outer;
-                    }
-                }
-
-                let mut next_program = amplifier_programs[(i + 1) % 5].borrow_mut();
-                for &value in output.iter() {
-                    next_program.input(value);
-                }
-            }
-        }
+        amplifier_programs[0].input(0);
+
+        let last_signal_output = run_ring(&mut amplifier_programs)
+            .expect("Amplifier feedback loop failed")
+            .unwrap_or(0);
 
         strongest_signal = std::cmp::max(strongest_signal, last_signal_output);
     });
 
-    strongest_signal.to_string()
+    strongest_signal
 }
 
 #[test]
@@ -94,3 +89,17 @@ fn tests_part2() {
 
     assert_eq!(part2(include_str!("day07_input.txt")), "19539216");
 }
+
+#[test]
+fn tests_max_thruster_signal() {
+    assert_eq!(
+        max_thruster_signal("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0"),
+        43210
+    );
+    assert_eq!(
+        max_thruster_signal_feedback(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5"
+        ),
+        139_629_729
+    );
+}
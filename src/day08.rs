@@ -1,68 +1,151 @@
-use std::str;
 extern crate bytecount;
 
-const PIXELS_WIDE: u32 = 25;
-const PIXELS_TALL: u32 = 6;
-const LAYER_SIZE: usize = (PIXELS_WIDE * PIXELS_TALL) as usize;
+#[cfg(feature = "image")]
+use crate::grid::Grid2D;
+#[cfg(feature = "image")]
+use crate::render::{self, Rgb};
 
-pub fn part1(input_string: &str) -> String {
-    assert_eq!(input_string.len() % LAYER_SIZE, 0);
-
-    let (_, slice) = input_string
-        .as_bytes()
-        .chunks(LAYER_SIZE)
-        .map(|slice| {
-            let num_zeros = bytecount::count(slice, b'0');
-            (num_zeros, slice)
-        })
-        .min_by_key(|(num_zeros, _)| *num_zeros)
-        .expect("Nothing found by min_by_key()");
+const PIXELS_WIDE: usize = 25;
+const PIXELS_TALL: usize = 6;
 
-    let count_1 = bytecount::count(slice, b'1');
-    let count_2 = bytecount::count(slice, b'2');
-    let result = count_1 * count_2;
+const BLACK: u8 = 0;
+const WHITE: u8 = 1;
+const TRANSPARENT: u8 = 2;
 
-    result.to_string()
-}
+/// The number of pixels in each direction used for a single image pixel in `render_png`.
+#[cfg(feature = "image")]
+const PNG_PIXEL_SIZE: usize = 10;
 
-pub fn part2(input_string: &str) -> String {
-    part2_sized(input_string, PIXELS_WIDE, PIXELS_TALL)
+/// An image in the Space Image Format, consisting of layers of digits where 0 is black,
+/// 1 is white and 2 is transparent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    layers: Vec<Vec<u8>>,
 }
 
-pub fn part2_sized(input_string: &str, width: u32, height: u32) -> String {
-    let layer_size = (width * height) as usize;
-    let mut image = vec![b'2'; layer_size];
+impl Image {
+    pub fn decode(data: &str, width: usize, height: usize) -> Result<Image, String> {
+        let digits = data
+            .trim()
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(digit) => Ok(digit as u8),
+                None => Err(format!("Invalid pixel: '{}'", c)),
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+
+        let layer_size = width * height;
+        let layers = digits.chunks_exact(std::cmp::max(layer_size, 1));
+        if layer_size == 0 || digits.is_empty() || !layers.remainder().is_empty() {
+            return Err(format!(
+                "Image data of length {} is not made up of {}x{} layers",
+                digits.len(),
+                width,
+                height
+            ));
+        }
+
+        Ok(Image {
+            width,
+            height,
+            layers: layers.map(<[u8]>::to_vec).collect(),
+        })
+    }
+
+    /// The pixels of each layer, with the top row of a layer first.
+    pub fn layers(&self) -> &[Vec<u8>] {
+        &self.layers
+    }
+
+    /// The index of the first layer with the fewest number of `pixel` digits.
+    pub fn layer_with_fewest(&self, pixel: u8) -> usize {
+        self.layers
+            .iter()
+            .enumerate()
+            .min_by_key(|&(index, layer)| (bytecount::count(layer, pixel), index))
+            .map(|(index, _)| index)
+            .expect("An image has at least one layer")
+    }
 
-    input_string
-        .as_bytes()
-        .chunks(layer_size)
-        .for_each(|layer| {
+    /// The first layer with the fewest number of 0 digits.
+    pub fn fewest_zeros_layer(&self) -> &[u8] {
+        &self.layers[self.layer_with_fewest(BLACK)]
+    }
+
+    /// The number of 1 digits multiplied by the number of 2 digits in the layer with the
+    /// fewest 0 digits.
+    pub fn part1_checksum(&self) -> usize {
+        let layer = self.fewest_zeros_layer();
+        bytecount::count(layer, WHITE) * bytecount::count(layer, TRANSPARENT)
+    }
+
+    /// The visible pixels, where the first layer is in front, which are transparent only
+    /// if the pixel is transparent in all layers.
+    pub fn flatten(&self) -> Vec<u8> {
+        let mut image = vec![TRANSPARENT; self.width * self.height];
+        for layer in self.layers.iter() {
             image
                 .iter_mut()
                 .zip(layer.iter())
                 .for_each(|(image_pixel, &layer_pixel)| {
-                    if *image_pixel == b'2' {
+                    if *image_pixel == TRANSPARENT {
                         *image_pixel = layer_pixel;
                     }
                 });
-        });
-
-    str::from_utf8(&image)
-        .unwrap()
-        .replace('1', "█")
-        .replace('0', " ")
-        .chars()
-        .enumerate()
-        .flat_map(|(i, c)| {
-            if i != 0 && i % (width as usize) == 0 {
-                Some('\n')
-            } else {
-                None
+        }
+        image
+    }
+
+    /// Render the visible pixels using a block character for white pixels and a space for
+    /// black and transparent ones.
+    pub fn render_ascii(&self) -> String {
+        self.flatten()
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|&pixel| if pixel == WHITE { '█' } else { ' ' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Write the visible pixels as a black and white PNG, with transparent pixels shown as black.
+    #[cfg(feature = "image")]
+    pub fn render_png(&self, path: &str) -> std::io::Result<()> {
+        let visible_pixels = self.flatten();
+        let mut grid = Grid2D::new(self.width, self.height, 0);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                grid[(x, y)] = if visible_pixels[y * self.width + x] == WHITE {
+                    1
+                } else {
+                    0
+                };
             }
-            .into_iter()
-            .chain(std::iter::once(c))
-        })
-        .collect::<String>()
+        }
+        render::to_png(&grid, &[Rgb::BLACK, Rgb::WHITE], PNG_PIXEL_SIZE, path)
+    }
+}
+
+fn decode(input_string: &str, width: usize, height: usize) -> Image {
+    Image::decode(input_string, width, height).unwrap_or_else(|error| panic!("{}", error))
+}
+
+pub fn part1(input_string: &str) -> String {
+    decode(input_string, PIXELS_WIDE, PIXELS_TALL)
+        .part1_checksum()
+        .to_string()
+}
+
+pub fn part2(input_string: &str) -> String {
+    part2_sized(input_string, PIXELS_WIDE, PIXELS_TALL)
+}
+
+pub fn part2_sized(input_string: &str, width: usize, height: usize) -> String {
+    decode(input_string, width, height).render_ascii()
 }
 
 #[test]
@@ -77,3 +160,56 @@ fn tests_part2() {
     assert_eq!(part2(include_str!("day08_input.txt")),
 "███   ██  ███  ████ ███  \n█  █ █  █ █  █    █ █  █ \n███  █    █  █   █  ███  \n█  █ █    ███   █   █  █ \n█  █ █  █ █    █    █  █ \n███   ██  █    ████ ███  ");
 }
+
+#[test]
+fn tests_image() {
+    let image = Image::decode("123456789012", 3, 2).unwrap();
+    assert_eq!(
+        image.layers(),
+        &[vec![1, 2, 3, 4, 5, 6], vec![7, 8, 9, 0, 1, 2]]
+    );
+    assert_eq!(image.layer_with_fewest(0), 0);
+    assert_eq!(image.fewest_zeros_layer(), &[1, 2, 3, 4, 5, 6]);
+    assert_eq!(image.part1_checksum(), 1);
+    assert_eq!(image.layer_with_fewest(7), 0);
+    assert_eq!(image.layer_with_fewest(3), 1);
+    // Ties are broken by the first layer:
+    assert_eq!(image.layer_with_fewest(1), 0);
+
+    let image = Image::decode("001122121122", 3, 2).unwrap();
+    assert_eq!(image.layer_with_fewest(0), 1);
+    assert_eq!(image.layer_with_fewest(2), 0);
+    assert_eq!(image.part1_checksum(), 9);
+
+    let image = Image::decode("0222112222120000", 2, 2).unwrap();
+    assert_eq!(image.flatten(), vec![0, WHITE, WHITE, 0]);
+    assert_eq!(image.render_ascii(), " █\n█ ");
+
+    let image = Image::decode("222102", 3, 1).unwrap();
+    assert_eq!(image.layers().len(), 2);
+    assert_eq!(image.flatten(), vec![WHITE, 0, TRANSPARENT]);
+
+    assert!(Image::decode("12345", 3, 2).is_err());
+    assert!(Image::decode("", 3, 2).is_err());
+    assert_eq!(
+        Image::decode("12x456", 3, 2),
+        Err("Invalid pixel: 'x'".to_string())
+    );
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn tests_render_png() {
+    let image = Image::decode("0222112222120000", 2, 2).unwrap();
+    let path = std::env::temp_dir().join("advent_of_code_day08.png");
+    let path = path.to_str().unwrap();
+    image.render_png(path).unwrap();
+
+    let png = std::fs::read(path).unwrap();
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(
+        u32::from_be_bytes([png[16], png[17], png[18], png[19]]),
+        2 * PNG_PIXEL_SIZE as u32
+    );
+    std::fs::remove_file(path).unwrap();
+}
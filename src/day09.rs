@@ -1,11 +1,34 @@
 use crate::int_code::Program;
 
-fn output_from_input(input_string: &str, input: i64) -> String {
+/// The example programs from the puzzle together with their expected output, exercising
+/// relative mode and large numbers. The mode input is ignored by them.
+pub const CONFORMANCE_SUITE: [(&str, &[i64]); 3] = [
+    // A quine, outputting a copy of itself:
+    (
+        "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+        &[
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ],
+    ),
+    // Outputs a 16-digit number:
+    (
+        "1102,34915192,34915192,7,4,7,99,0",
+        &[1_219_070_632_396_864],
+    ),
+    // Outputs the large number in the middle:
+    ("104,1125899906842624,99", &[1_125_899_906_842_624]),
+];
+
+/// Run the BOOST program with the given mode input (1 for test mode, 2 for sensor boost
+/// mode), returning all output.
+pub fn run_boost(input_string: &str, mode: i64) -> Vec<i64> {
     let mut program = Program::parse(input_string);
-    program.input(input);
+    program.input(mode);
+    program.run_for_output()
+}
 
-    program
-        .run_for_output()
+fn output_from_input(input_string: &str, input: i64) -> String {
+    run_boost(input_string, input)
         .iter()
         .map(|&value| value.to_string())
         .collect::<Vec<String>>()
@@ -39,3 +62,16 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day09_input.txt")), "64236");
 }
+
+#[test]
+fn tests_conformance_suite() {
+    for &(program, expected_output) in CONFORMANCE_SUITE.iter() {
+        assert_eq!(run_boost(program, 1), expected_output, "{}", program);
+    }
+
+    assert_eq!(
+        run_boost(include_str!("day09_input.txt"), 1),
+        vec![3_601_950_151]
+    );
+    assert_eq!(run_boost(include_str!("day09_input.txt"), 2), vec![64_236]);
+}
@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use crate::point::Point;
+use std::collections::HashMap;
 
 pub fn gcd(mut a: i64, mut b: i64) -> i64 {
     while b != 0 {
@@ -9,7 +10,8 @@ pub fn gcd(mut a: i64, mut b: i64) -> i64 {
     a
 }
 
-pub fn parse_points(input_string: &str) -> Vec<(usize, usize)> {
+/// The positions of the asteroids, with x growing to the right and y growing downwards.
+pub fn parse_points(input_string: &str) -> Vec<Point> {
     input_string
         .lines()
         .enumerate()
@@ -17,106 +19,107 @@ pub fn parse_points(input_string: &str) -> Vec<(usize, usize)> {
             line.chars()
                 .enumerate()
                 .filter_map(move |(col, character)| match character {
-                    '#' => Some((col, row)),
+                    '#' => Some(Point::new(col as i32, row as i32)),
                     _ => None,
                 })
         })
         .collect()
 }
 
-/// Return (max_seen, (x, y)) of station.
-pub fn determine_station(points: &[(usize, usize)]) -> (usize, (usize, usize)) {
-    points
-        .iter()
-        .map(|&this_point| {
-            let seen_count = points
-                .iter()
-                .filter(|&&point| point != this_point)
-                .fold(HashSet::new(), |mut seen, other_point| {
-                    let mut distance_x = other_point.0 as i64 - this_point.0 as i64;
-                    let mut distance_y = other_point.1 as i64 - this_point.1 as i64;
-                    let divisor = gcd(distance_x.abs(), distance_y.abs());
-                    distance_x /= divisor;
-                    distance_y /= divisor;
-                    seen.insert((distance_x, distance_y));
-                    seen
-                })
-                .len();
-            (seen_count, this_point)
-        })
-        .max_by_key(|&(seen_count, _)| seen_count)
-        .unwrap()
+/// The direction from one point to another, reduced so that all points on the same ray
+/// from `from` share the same direction.
+fn direction_to(from: Point, to: Point) -> Point {
+    let distance = to - from;
+    let divisor = gcd(i64::from(distance.x.abs()), i64::from(distance.y.abs())) as i32;
+    Point::new(distance.x / divisor, distance.y / divisor)
 }
 
-pub fn part1(input_string: &str) -> String {
-    let points = parse_points(input_string);
-    determine_station(&points).0.to_string()
+/// The clockwise angle in radians from straight up to a direction, in the range [0, 2π).
+fn clockwise_angle(direction: Point) -> f64 {
+    // Use atan2(x, -y) instead of atan2(y, x) to start from the negative y axis (up),
+    // which also makes the angle grow clockwise since y grows downwards:
+    // https://en.wikipedia.org/wiki/Atan2#/media/File:Atan2definition.svg
+    let angle = f64::from(direction.x).atan2(f64::from(-direction.y));
+    if angle < 0. {
+        angle + 2. * std::f64::consts::PI
+    } else {
+        angle
+    }
 }
 
-pub fn part2(input_string: &str) -> String {
-    let (x, y) = part2_nth(input_string, 200);
-    (x * 100 + y).to_string()
+/// The other points grouped by their direction from `source`, where only the closest point
+/// on each ray is visible from it.
+fn rays_from(points: &[Point], source: Point) -> HashMap<Point, Vec<Point>> {
+    let mut rays: HashMap<Point, Vec<Point>> = HashMap::new();
+    for &point in points.iter().filter(|&&point| point != source) {
+        rays.entry(direction_to(source, point))
+            .or_default()
+            .push(point);
+    }
+    rays
 }
 
-pub fn part2_nth(input_string: &str, nth: u32) -> (i64, i64) {
+/// The number of other asteroids directly visible from each asteroid.
+pub fn visibility_map(input_string: &str) -> HashMap<Point, usize> {
     let points = parse_points(input_string);
-    let (_, base_location) = determine_station(&points);
-
-    let mut seen = HashMap::new();
-    for &(x, y) in points.iter().filter(|&&p| p != base_location) {
-        let distance_x = x as i64 - base_location.0 as i64;
-        let distance_y = y as i64 - base_location.1 as i64;
-        let divisor = gcd(distance_x.abs(), distance_y.abs());
-        let direction_x = distance_x / divisor;
-        let direction_y = distance_y / divisor;
-
-        seen.entry((direction_x, direction_y))
-            .or_insert_with(Vec::new)
-            .push((distance_x, distance_y));
-    }
+    points
+        .iter()
+        .map(|&point| (point, rays_from(&points, point).len()))
+        .collect()
+}
 
-    let mut points_grouped_by_direction: Vec<Vec<(i64, i64)>> = seen.values().cloned().collect();
+/// The asteroid from which the most other asteroids are visible, together with that count.
+pub fn best_station(input_string: &str) -> (Point, usize) {
+    let points = parse_points(input_string);
+    points
+        .iter()
+        .map(|&point| (point, rays_from(&points, point).len()))
+        .max_by_key(|&(_, seen_count)| seen_count)
+        .unwrap()
+}
 
-    // Sort each group so that closest points are at end of vector:
-    for points_on_line in points_grouped_by_direction.iter_mut() {
-        points_on_line.sort_by_key(|point| -((point.0 * point.0 + point.1 * point.1) as i64));
+/// The asteroids in the order vaporized by a laser at `station`, which starts pointing up and
+/// rotates clockwise, vaporizing only the closest remaining asteroid on each ray it passes.
+pub fn vaporization_order(input_string: &str, station: Point) -> Vec<Point> {
+    let mut rays: Vec<(Point, Vec<Point>)> = rays_from(&parse_points(input_string), station)
+        .into_iter()
+        .collect();
+    for (_, points_on_ray) in rays.iter_mut() {
+        points_on_ray.sort_by_key(|&point| point.manhattan_distance(station));
     }
+    rays.sort_by(|(direction1, _), (direction2, _)| {
+        clockwise_angle(*direction1)
+            .partial_cmp(&clockwise_angle(*direction2))
+            .unwrap()
+    });
 
-    // Sort between groups in clockwise order:
-    points_grouped_by_direction.sort_by(|p1, p2| {
-        // Use atan2(x, y) instead of atan2(y, x) to start from y axis,
-        // and negate value to get clockwise direction:
-        // https://en.wikipedia.org/wiki/Atan2#/media/File:Atan2definition.svg
-        let (x, y) = p1[0];
-        let a1 = -(x as f64).atan2(y as f64);
+    // Each rotation vaporizes the closest remaining asteroid on each ray:
+    let rotations = rays
+        .iter()
+        .map(|(_, points)| points.len())
+        .max()
+        .unwrap_or(0);
+    (0..rotations)
+        .flat_map(|rotation| {
+            rays.iter()
+                .filter_map(move |(_, points_on_ray)| points_on_ray.get(rotation).cloned())
+        })
+        .collect()
+}
 
-        let (x, y) = p2[0];
-        let a2 = -(x as f64).atan2(y as f64);
+pub fn part1(input_string: &str) -> String {
+    best_station(input_string).1.to_string()
+}
 
-        a1.partial_cmp(&a2).unwrap()
-    });
+pub fn part2(input_string: &str) -> String {
+    let (x, y) = part2_nth(input_string, 200);
+    (x * 100 + y).to_string()
+}
 
-    let mut destroyed_count = 0;
-    loop {
-        let mut i = 0;
-        while i != points_grouped_by_direction.len() {
-            let points = &mut points_grouped_by_direction[i];
-
-            let destroyed = points.pop().unwrap();
-            destroyed_count += 1;
-            if destroyed_count == nth {
-                let result_x = destroyed.0 + base_location.0 as i64;
-                let result_y = destroyed.1 + base_location.1 as i64;
-                return (result_x, result_y);
-            }
-
-            if points.is_empty() {
-                points_grouped_by_direction.remove(i);
-            } else {
-                i += 1;
-            }
-        }
-    }
+pub fn part2_nth(input_string: &str, nth: u32) -> (i64, i64) {
+    let (station, _) = best_station(input_string);
+    let destroyed = vaporization_order(input_string, station)[nth as usize - 1];
+    (i64::from(destroyed.x), i64::from(destroyed.y))
 }
 
 #[test]
@@ -176,3 +179,87 @@ fn tests_part2() {
 
     assert_eq!(part2(include_str!("day10_input.txt")), "517");
 }
+
+#[test]
+fn tests_visibility_map() {
+    let visibility = visibility_map(
+        ".#..#
+.....
+#####
+....#
+...##",
+    );
+    assert_eq!(visibility.len(), 10);
+    assert_eq!(visibility[&Point::new(3, 4)], 8);
+    assert_eq!(visibility[&Point::new(1, 0)], 7);
+    assert_eq!(visibility[&Point::new(0, 2)], 6);
+    assert_eq!(visibility[&Point::new(4, 2)], 5);
+    assert_eq!(visibility.get(&Point::new(0, 0)), None);
+    assert_eq!(visibility.values().max(), Some(&8));
+}
+
+#[test]
+fn tests_angles() {
+    let station = Point::new(5, 5);
+    assert_eq!(direction_to(station, Point::new(5, 1)), Point::new(0, -1));
+    assert_eq!(direction_to(station, Point::new(11, 8)), Point::new(2, 1));
+    assert_eq!(
+        direction_to(station, Point::new(7, 6)),
+        direction_to(station, Point::new(9, 7))
+    );
+    assert_ne!(
+        direction_to(station, Point::new(7, 6)),
+        direction_to(station, Point::new(3, 4))
+    );
+
+    let up = clockwise_angle(Point::new(0, -1));
+    let up_right = clockwise_angle(Point::new(1, -1));
+    let right = clockwise_angle(Point::new(1, 0));
+    let down = clockwise_angle(Point::new(0, 1));
+    let left = clockwise_angle(Point::new(-1, 0));
+    let up_left = clockwise_angle(Point::new(-1, -1));
+    assert_eq!(up, 0.);
+    assert!(up < up_right && up_right < right && right < down && down < left && left < up_left);
+    assert!(up_left < 2. * std::f64::consts::PI);
+}
+
+#[test]
+fn tests_vaporization_order() {
+    let input_string = ".#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##";
+    let (station, seen_count) = best_station(input_string);
+    assert_eq!((station, seen_count), (Point::new(11, 13), 210));
+
+    let order = vaporization_order(input_string, station);
+    assert_eq!(order.len(), 299);
+    assert_eq!(order[0], Point::new(11, 12));
+    assert_eq!(order[1], Point::new(12, 1));
+    assert_eq!(order[2], Point::new(12, 2));
+    assert_eq!(order[9], Point::new(12, 8));
+    assert_eq!(order[19], Point::new(16, 0));
+    assert_eq!(order[49], Point::new(16, 9));
+    assert_eq!(order[99], Point::new(10, 16));
+    assert_eq!(order[198], Point::new(9, 6));
+    assert_eq!(order[199], Point::new(8, 2));
+    assert_eq!(order[200], Point::new(10, 9));
+    assert_eq!(order[298], Point::new(11, 1));
+    assert_eq!(part2(input_string), "802");
+}
@@ -1,8 +1,14 @@
+use crate::direction::Direction;
+#[cfg(feature = "image")]
+use crate::grid::Grid2D;
 use crate::int_code::Program;
+use crate::point::Point;
+#[cfg(feature = "image")]
+use crate::render::{self, Rgb};
 use std::collections::HashMap;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-enum Color {
+pub enum Color {
     Black = 0,
     White = 1,
 }
@@ -17,45 +23,145 @@ impl Color {
     }
 }
 
-enum Direction {
-    Up,
-    Right,
-    Down,
-    Left,
+/// The panels of the hull painted by the robot.
+#[derive(Debug, Default, PartialEq)]
+pub struct Hull {
+    panels: HashMap<Point, Color>,
+    /// The number of times each panel has been painted.
+    paint_counts: HashMap<Point, u32>,
 }
 
-impl Direction {
-    fn turn_right(self) -> Direction {
-        match self {
-            Direction::Up => Direction::Right,
-            Direction::Right => Direction::Down,
-            Direction::Down => Direction::Left,
-            Direction::Left => Direction::Up,
-        }
+impl Hull {
+    pub fn new() -> Hull {
+        Hull::default()
+    }
+
+    /// Paint a panel, returning true if it had not been painted before.
+    pub fn paint(&mut self, x: i32, y: i32, color: Color) -> bool {
+        let position = Point::new(x, y);
+        *self.paint_counts.entry(position).or_insert(0) += 1;
+        self.panels.insert(position, color).is_none()
+    }
+
+    /// The color of a panel, where panels not yet painted are black.
+    pub fn get(&self, x: i32, y: i32) -> Color {
+        *self.panels.get(&Point::new(x, y)).unwrap_or(&Color::Black)
+    }
+
+    /// The number of panels painted at least once.
+    pub fn painted_count(&self) -> usize {
+        self.panels.len()
+    }
+
+    /// The number of panels painted at least twice.
+    pub fn painted_multiple_count(&self) -> usize {
+        self.paint_counts
+            .values()
+            .filter(|&&paint_count| paint_count > 1)
+            .count()
+    }
+
+    /// The bounding box of the painted panels as (min_x, max_x, min_y, max_y),
+    /// or None if nothing has been painted.
+    pub fn bounds(&self) -> Option<(i32, i32, i32, i32)> {
+        let xs = || self.panels.keys().map(|point| point.x);
+        let ys = || self.panels.keys().map(|point| point.y);
+        Some((xs().min()?, xs().max()?, ys().min()?, ys().max()?))
     }
 
-    fn turn_left(self) -> Direction {
-        match self {
-            Direction::Up => Direction::Left,
-            Direction::Right => Direction::Up,
-            Direction::Down => Direction::Right,
-            Direction::Left => Direction::Down,
+    /// Render the painted area with the top row first, using a block character for white
+    /// panels and a space for black ones.
+    pub fn render(&self) -> String {
+        self.render_with('█', ' ')
+    }
+
+    /// Render the painted area with the top row first, using `filled` for white panels
+    /// and `empty` for black ones.
+    pub fn render_with(&self, filled: char, empty: char) -> String {
+        let (min_x, max_x, min_y, max_y) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+
+        let mut result = String::new();
+        for y in (min_y..=max_y).rev() {
+            for x in min_x..=max_x {
+                result.push(if self.get(x, y) == Color::White {
+                    filled
+                } else {
+                    empty
+                });
+            }
+            if y != min_y {
+                result.push('\n');
+            }
         }
+        result
     }
 }
 
-fn run(input_string: &str, initial_color: Color) -> HashMap<(i32, i32), Color> {
+/// The movement of the painting robot during a run.
+#[derive(Debug, PartialEq)]
+pub struct RunStats {
+    /// The positions moved to, in order, not including the starting position.
+    pub path: Vec<Point>,
+    /// The panel painted and the color it was painted with before each move.
+    pub paints: Vec<(Point, Color)>,
+    /// The number of turns made.
+    pub turns: usize,
+    /// The number of panels painted at least once.
+    pub panels_painted: usize,
+    /// The direction the robot is facing when the program halts.
+    pub final_direction: Direction,
+}
+
+pub fn run(input_string: &str, initial_color: Color) -> Hull {
+    run_with(input_string, initial_color, None, None).unwrap()
+}
+
+/// Run the painting robot, recording its movement.
+pub fn run_stats(input_string: &str, initial_color: Color) -> RunStats {
+    let mut stats = RunStats {
+        path: Vec::new(),
+        paints: Vec::new(),
+        turns: 0,
+        panels_painted: 0,
+        final_direction: Direction::Up,
+    };
+    run_with(input_string, initial_color, None, Some(&mut stats)).unwrap();
+    stats
+}
+
+/// Run the painting robot, failing if a single panel is painted more than `max_repaints` times
+/// without the number of painted panels growing, which indicates a program stuck in a loop.
+pub fn try_run(
+    input_string: &str,
+    initial_color: Color,
+    max_repaints: Option<usize>,
+) -> Result<Hull, String> {
+    run_with(input_string, initial_color, max_repaints, None)
+}
+
+/// Like `try_run`, optionally recording the movement of the robot.
+fn run_with(
+    input_string: &str,
+    initial_color: Color,
+    max_repaints: Option<usize>,
+    mut stats: Option<&mut RunStats>,
+) -> Result<Hull, String> {
     let mut program = Program::parse(input_string);
-    let mut painted: HashMap<(i32, i32), Color> = HashMap::new();
-    let mut position = (0, 0);
+    let mut hull = Hull::new();
+    // Number of times each panel has been painted since the painted area last grew:
+    let mut repaint_counts: HashMap<Point, usize> = HashMap::new();
+    let mut position = Point::ORIGIN;
     let mut current_direction = Direction::Up;
 
     if initial_color == Color::White {
-        painted.insert(position, initial_color);
+        hull.paint(position.x, position.y, initial_color);
     }
 
     loop {
-        program.input(*painted.get(&position).unwrap_or(&Color::Black) as i64);
+        program.input(hull.get(position.x, position.y) as i64);
         let output = program.run_for_output();
 
         if program.is_halted() {
@@ -65,7 +171,18 @@ fn run(input_string: &str, initial_color: Color) -> HashMap<(i32, i32), Color> {
         let painted_color = Color::from(output[0]);
         let turn_direction = output[1];
 
-        painted.insert(position, painted_color);
+        if hull.paint(position.x, position.y, painted_color) {
+            repaint_counts.clear();
+        } else if let Some(max_repaints) = max_repaints {
+            let repaint_count = repaint_counts.entry(position).or_insert(0);
+            *repaint_count += 1;
+            if *repaint_count > max_repaints {
+                return Err(format!(
+                    "Panel at {} repainted {} times without new panels being painted",
+                    position, repaint_count
+                ));
+            }
+        }
 
         current_direction = match turn_direction {
             0 => current_direction.turn_left(),
@@ -73,49 +190,128 @@ fn run(input_string: &str, initial_color: Color) -> HashMap<(i32, i32), Color> {
             _ => panic!("Invalid direction: {}", turn_direction),
         };
 
-        match current_direction {
-            Direction::Up => position = (position.0, position.1 + 1),
-            Direction::Right => position = (position.0 + 1, position.1),
-            Direction::Down => position = (position.0, position.1 - 1),
-            Direction::Left => position = (position.0 - 1, position.1),
+        let painted_position = position;
+        position += current_direction.as_offset();
+
+        if let Some(stats) = &mut stats {
+            stats.paints.push((painted_position, painted_color));
+            stats.path.push(position);
+            stats.turns += 1;
         }
     }
 
-    painted
+    if let Some(stats) = stats {
+        stats.panels_painted = hull.painted_count();
+        stats.final_direction = current_direction;
+    }
+
+    Ok(hull)
 }
 
 pub fn part1(input_string: &str) -> String {
-    run(input_string, Color::Black).len().to_string()
+    run(input_string, Color::Black).painted_count().to_string()
+}
+
+/// The number of panels painted more than once when starting on a black panel.
+pub fn panels_painted_multiple(input_string: &str) -> usize {
+    run(input_string, Color::Black).painted_multiple_count()
 }
 
 pub fn part2(input_string: &str) -> String {
-    let painted = run(input_string, Color::White);
-    let mut min_x = std::i32::MAX;
-    let mut max_x = std::i32::MIN;
-    let mut min_y = std::i32::MAX;
-    let mut max_y = std::i32::MIN;
-    painted.keys().for_each(|&(x, y)| {
-        min_x = std::cmp::min(min_x, x);
-        max_x = std::cmp::max(max_x, x);
-        min_y = std::cmp::min(min_y, y);
-        max_y = std::cmp::max(max_y, y);
-    });
-
-    let mut result = String::new();
-    for y in (min_y..=max_y).rev() {
-        for x in min_x..=max_x {
-            result.push(if let Some(&Color::White) = painted.get(&(x, y)) {
-                '█'
-            } else {
-                ' '
-            });
+    run(input_string, Color::White).render()
+}
+
+/// The number of pixels in each direction used for a single panel in `render_png`.
+#[cfg(feature = "image")]
+const PNG_PANEL_SIZE: usize = 10;
+
+/// Write the registration identifier painted from a white start as a black and white PNG.
+#[cfg(feature = "image")]
+pub fn render_png(input_string: &str, path: &str) -> std::io::Result<()> {
+    let hull = run(input_string, Color::White);
+    let (min_x, max_x, min_y, max_y) = hull.bounds().unwrap_or((0, 0, 0, 0));
+    let width = (max_x - min_x + 1) as usize;
+    let height = (max_y - min_y + 1) as usize;
+
+    let mut grid = Grid2D::new(width, height, 0);
+    for row in 0..height {
+        // The top row has the highest y coordinate:
+        let y = max_y - row as i32;
+        for column in 0..width {
+            if hull.get(min_x + column as i32, y) == Color::White {
+                grid[(column, row)] = 1;
+            }
         }
-        if y != min_y {
-            result.push('\n');
+    }
+    render::to_png(&grid, &[Rgb::BLACK, Rgb::WHITE], PNG_PANEL_SIZE, path)
+}
+
+/// The colors of black panels, white panels and the robot in `render_gif`.
+#[cfg(feature = "image")]
+const GIF_PALETTE: [Rgb; 3] = [Rgb::BLACK, Rgb::WHITE, Rgb(0xFF, 0x40, 0x40)];
+
+/// The time each frame is shown in `render_gif`, in hundredths of a second.
+#[cfg(feature = "image")]
+const GIF_FRAME_DELAY: u16 = 5;
+
+/// Write an animated GIF of the registration identifier being painted from a white start,
+/// with a frame after each panel is painted showing the robot in red.
+#[cfg(feature = "image")]
+pub fn render_gif(input_string: &str, path: &str) -> std::io::Result<()> {
+    render_gif_sampled(input_string, path, 1).map(|_| ())
+}
+
+/// Like `render_gif`, but only with a frame every `sample_steps` steps and after the
+/// last one, returning the number of frames. Fails with `InvalidInput` if `sample_steps`
+/// is zero.
+#[cfg(feature = "image")]
+pub fn render_gif_sampled(
+    input_string: &str,
+    path: &str,
+    sample_steps: usize,
+) -> std::io::Result<usize> {
+    if sample_steps == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Frames must be sampled at least every step",
+        ));
+    }
+    let stats = run_stats(input_string, Color::White);
+
+    let positions = || {
+        std::iter::once(Point::ORIGIN)
+            .chain(stats.path.iter().copied())
+            .chain(stats.paints.iter().map(|&(position, _)| position))
+    };
+    let min_x = positions().map(|point| point.x).min().unwrap_or(0);
+    let max_x = positions().map(|point| point.x).max().unwrap_or(0);
+    let min_y = positions().map(|point| point.y).min().unwrap_or(0);
+    let max_y = positions().map(|point| point.y).max().unwrap_or(0);
+    // The top row has the highest y coordinate:
+    let cell = |point: Point| ((point.x - min_x) as usize, (max_y - point.y) as usize);
+
+    let mut grid = Grid2D::new(
+        (max_x - min_x + 1) as usize,
+        (max_y - min_y + 1) as usize,
+        Color::Black as u8,
+    );
+    grid[cell(Point::ORIGIN)] = Color::White as u8;
+
+    let mut frames = Vec::new();
+    let step_count = stats.paints.len();
+    for (step, (&(painted_position, color), &robot_position)) in
+        stats.paints.iter().zip(stats.path.iter()).enumerate()
+    {
+        grid[cell(painted_position)] = color as u8;
+        if step % sample_steps == 0 || step == step_count - 1 {
+            let mut frame = grid.clone();
+            frame[cell(robot_position)] = 2;
+            frames.push(frame);
         }
     }
 
-    result
+    render::to_gif(&frames, &GIF_PALETTE, PNG_PANEL_SIZE, GIF_FRAME_DELAY, path)?;
+    Ok(frames.len())
 }
 
 #[test]
@@ -130,3 +326,142 @@ fn tests_part2() {
         include_str!("day11_part2_output.txt").trim_end_matches('\n')
     );
 }
+
+#[cfg(feature = "image")]
+#[test]
+fn tests_render_png() {
+    let input = include_str!("day11_input.txt");
+    let path = std::env::temp_dir().join("advent_of_code_day11.png");
+    let path = path.to_str().unwrap();
+    render_png(input, path).unwrap();
+
+    let (min_x, max_x, min_y, max_y) = run(input, Color::White).bounds().unwrap();
+    let png = std::fs::read(path).unwrap();
+    let width = u32::from_be_bytes([png[16], png[17], png[18], png[19]]);
+    let height = u32::from_be_bytes([png[20], png[21], png[22], png[23]]);
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(
+        width as usize,
+        (max_x - min_x + 1) as usize * PNG_PANEL_SIZE
+    );
+    assert_eq!(
+        height as usize,
+        (max_y - min_y + 1) as usize * PNG_PANEL_SIZE
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn tests_panels_painted_multiple() {
+    // Paint white and turn right five times, ending up painting the first panel again
+    // (the output of a sixth round is ignored since the program halts after it):
+    let program = format!("{}99", "3,100,104,1,104,1,".repeat(6));
+    assert_eq!(run(&program, Color::Black).painted_count(), 4);
+    assert_eq!(panels_painted_multiple(&program), 1);
+
+    assert_eq!(
+        panels_painted_multiple(include_str!("day11_input.txt")),
+        1417
+    );
+}
+
+#[test]
+fn tests_run_stats() {
+    // Paint white, turning right twice and then left three times (the last round is
+    // ignored since the program halts after it):
+    let program = "3,100,104,1,104,1,3,100,104,1,104,1,3,100,104,1,104,0,\
+                   3,100,104,1,104,0,3,100,104,1,104,0,3,100,104,1,104,0,99";
+    let stats = run_stats(program, Color::Black);
+    assert_eq!(
+        stats.path,
+        vec![
+            Point::new(1, 0),
+            Point::new(1, -1),
+            Point::new(2, -1),
+            Point::new(2, 0),
+            Point::new(1, 0)
+        ]
+    );
+    assert_eq!(stats.turns, 5);
+    assert_eq!(stats.path.len(), stats.turns);
+    assert_eq!(stats.paints.len(), stats.turns);
+    assert_eq!(stats.paints[0], (Point::ORIGIN, Color::White));
+    assert_eq!(stats.paints[4], (Point::new(2, 0), Color::White));
+    assert_eq!(stats.panels_painted, 5);
+    assert_eq!(stats.final_direction, Direction::Left);
+}
+
+#[test]
+fn tests_render_with() {
+    let hull = run(include_str!("day11_input.txt"), Color::White);
+    assert_eq!(hull.render(), part2(include_str!("day11_input.txt")));
+    assert_eq!(
+        hull.render_with('#', ' '),
+        include_str!("day11_part2_output.txt")
+            .trim_end_matches('\n')
+            .replace('█', "#")
+    );
+}
+
+#[test]
+fn tests_try_run() {
+    // Always paint white and turn right, circling around the same four panels forever:
+    let looping_program = "3,100,104,1,104,1,1105,1,0";
+    assert_eq!(
+        try_run(looping_program, Color::Black, Some(1000)),
+        Err("Panel at (0, 0) repainted 1001 times without new panels being painted".to_string())
+    );
+
+    assert_eq!(
+        try_run(include_str!("day11_input.txt"), Color::Black, Some(1000))
+            .unwrap()
+            .painted_count(),
+        1686
+    );
+}
+
+#[test]
+fn tests_hull_bounds() {
+    let mut hull = Hull::new();
+    assert_eq!(hull.bounds(), None);
+    assert_eq!(hull.render_with('#', '.'), "");
+
+    assert!(hull.paint(0, 0, Color::White));
+    assert!(hull.paint(2, -1, Color::Black));
+    assert!(hull.paint(-1, 1, Color::White));
+    assert!(!hull.paint(0, 0, Color::White));
+    assert_eq!(hull.bounds(), Some((-1, 2, -1, 1)));
+    assert_eq!(hull.painted_count(), 3);
+    assert_eq!(hull.get(-1, 1), Color::White);
+    assert_eq!(hull.get(2, -1), Color::Black);
+    assert_eq!(hull.get(5, 5), Color::Black);
+    assert_eq!(hull.render_with('#', '.'), "#...\n.#..\n....");
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn tests_render_gif() {
+    let input = include_str!("day11_input.txt");
+    let steps = run_stats(input, Color::White).turns;
+    let path = std::env::temp_dir().join("advent_of_code_day11.gif");
+    let path = path.to_str().unwrap();
+
+    for &sample_steps in [25, steps].iter() {
+        let frame_count = render_gif_sampled(input, path, sample_steps).unwrap();
+        // Every sampled step as well as the last one:
+        let sampled_steps: std::collections::HashSet<usize> = (0..steps)
+            .step_by(sample_steps)
+            .chain(std::iter::once(steps - 1))
+            .collect();
+        let expected_frame_count = sampled_steps.len();
+        assert_eq!(frame_count, expected_frame_count);
+
+        let gif = std::fs::read(path).unwrap();
+        assert_eq!(crate::gif::frame_count(&gif), Some(frame_count));
+    }
+    std::fs::remove_file(path).unwrap();
+
+    let error = render_gif_sampled(input, path, 0).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(!std::path::Path::new(path).exists());
+}
@@ -1,10 +1,12 @@
+/// The moons of Jupiter, with their positions and velocities changing one time step at a time.
 #[derive(Debug, Clone)]
-struct Moons {
-    positions: [[i32; 3]; 4],
-    velocities: [[i32; 3]; 4],
+pub struct Simulation {
+    positions: Vec<[i32; 3]>,
+    velocities: Vec<[i32; 3]>,
+    time: u64,
 }
 
-pub fn gcd(mut a: usize, mut b: usize) -> usize {
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
     while b != 0 {
         let tmp = a;
         a = b;
@@ -13,60 +15,75 @@ pub fn gcd(mut a: usize, mut b: usize) -> usize {
     a
 }
 
-pub fn lcd(a: usize, b: usize) -> usize {
+pub fn lcd(a: u64, b: u64) -> u64 {
     a * b / gcd(a, b)
 }
 
-pub fn lcd3(a: usize, b: usize, c: usize) -> usize {
-    lcd(a, lcd(b, c))
-}
-
-impl Moons {
-    fn parse(input: &str) -> Moons {
-        let mut positions = [[0; 3]; 4];
-        input.lines().enumerate().for_each(|(i, line)| {
-            let parts: Vec<&str> = line
-                .split(|c| c == '=' || c == ' ' || c == '>' || c == ',')
-                .collect();
-
-            positions[i][0] = parts[1].trim().parse::<i32>().unwrap();
-            positions[i][1] = parts[4].trim().parse::<i32>().unwrap();
-            positions[i][2] = parts[7].trim().parse::<i32>().unwrap();
-        });
-
-        Moons {
+impl Simulation {
+    /// Parse the initial positions of the moons, which start out still.
+    pub fn parse(input: &str) -> Simulation {
+        let positions: Vec<[i32; 3]> = input
+            .lines()
+            .map(|line| {
+                let parts: Vec<&str> = line.split(&['=', ' ', '>', ','][..]).collect();
+                [
+                    parts[1].trim().parse::<i32>().unwrap(),
+                    parts[4].trim().parse::<i32>().unwrap(),
+                    parts[7].trim().parse::<i32>().unwrap(),
+                ]
+            })
+            .collect();
+
+        Simulation {
+            velocities: vec![[0; 3]; positions.len()],
             positions,
-            velocities: [[0; 3]; 4],
+            time: 0,
         }
     }
 
-    fn total_energy(&self) -> u64 {
-        let mut total_energy = 0 as u64;
-        for i in 0..4 {
-            let potential_energy = self.positions[i].iter().map(|&x| x.abs()).sum::<i32>() as u64;
-            let kinetic_energy = self.velocities[i].iter().map(|&x| x.abs()).sum::<i32>() as u64;
-            total_energy += potential_energy * kinetic_energy;
-        }
-        total_energy
+    /// The number of steps simulated so far.
+    pub fn time(&self) -> u64 {
+        self.time
     }
 
-    fn step(&mut self) {
-        for i in 0..4 {
-            for j in 0..4 {
-                if i != j {
-                    for k in 0..3 {
-                        self.velocities[i][k] +=
-                            signum(self.positions[j][k] - self.positions[i][k]);
-                    }
-                }
-            }
-        }
+    /// The sum over all moons of their potential energy multiplied by their kinetic energy.
+    pub fn total_energy(&self) -> u64 {
+        self.positions
+            .iter()
+            .zip(self.velocities.iter())
+            .map(|(position, velocity)| {
+                let potential_energy = position.iter().map(|&x| x.abs()).sum::<i32>() as u64;
+                let kinetic_energy = velocity.iter().map(|&x| x.abs()).sum::<i32>() as u64;
+                potential_energy * kinetic_energy
+            })
+            .sum()
+    }
 
-        for (i, position) in self.positions.iter_mut().enumerate() {
-            for (k, p) in position.iter_mut().enumerate() {
-                *p += self.velocities[i][k];
+    /// Advance the simulation by one time step.
+    pub fn step(&mut self) {
+        for axis in 0..3 {
+            let (mut positions, mut velocities) = self.axis(axis);
+            step_axis(&mut positions, &mut velocities);
+            for moon in 0..self.positions.len() {
+                self.positions[moon][axis] = positions[moon];
+                self.velocities[moon][axis] = velocities[moon];
             }
         }
+        self.time += 1;
+    }
+
+    /// The positions and velocities of all moons along a single axis.
+    fn axis(&self, axis: usize) -> (Vec<i32>, Vec<i32>) {
+        (
+            self.positions
+                .iter()
+                .map(|position| position[axis])
+                .collect(),
+            self.velocities
+                .iter()
+                .map(|velocity| velocity[axis])
+                .collect(),
+        )
     }
 }
 
@@ -78,12 +95,59 @@ fn signum(value: i32) -> i32 {
     }
 }
 
-pub fn part1_nth(input_string: &str, n: usize) -> String {
-    let mut moons = Moons::parse(input_string);
-    for _ in 0..n {
-        moons.step();
+/// Apply gravity and then velocity along a single axis, which is independent of the others.
+fn step_axis(positions: &mut [i32], velocities: &mut [i32]) {
+    for i in 0..positions.len() {
+        for j in 0..positions.len() {
+            velocities[i] += signum(positions[j] - positions[i]);
+        }
     }
-    moons.total_energy().to_string()
+
+    for (position, velocity) in positions.iter_mut().zip(velocities.iter()) {
+        *position += velocity;
+    }
+}
+
+/// The number of steps until the moons return to the given positions and velocities along
+/// a single axis.
+///
+/// Since each step can be reversed, the first repeated state is always the initial one.
+pub fn cycle_length(positions: &[i32], velocities: &[i32]) -> u64 {
+    let mut current_positions = positions.to_vec();
+    let mut current_velocities = velocities.to_vec();
+    let mut steps = 0;
+    loop {
+        step_axis(&mut current_positions, &mut current_velocities);
+        steps += 1;
+        if current_positions == positions && current_velocities == velocities {
+            return steps;
+        }
+    }
+}
+
+/// The total energy in the system after simulating the given number of steps.
+pub fn total_energy(input_string: &str, steps: usize) -> u64 {
+    let mut simulation = Simulation::parse(input_string);
+    for _ in 0..steps {
+        simulation.step();
+    }
+    simulation.total_energy()
+}
+
+/// The number of steps until all moons are back at a previous state, which is the least
+/// common multiple of the cycle lengths of the independent axes.
+pub fn steps_to_repeat(input_string: &str) -> u64 {
+    let simulation = Simulation::parse(input_string);
+    (0..3)
+        .map(|axis| {
+            let (positions, velocities) = simulation.axis(axis);
+            cycle_length(&positions, &velocities)
+        })
+        .fold(1, lcd)
+}
+
+pub fn part1_nth(input_string: &str, n: usize) -> String {
+    total_energy(input_string, n).to_string()
 }
 
 pub fn part1(input_string: &str) -> String {
@@ -91,33 +155,7 @@ pub fn part1(input_string: &str) -> String {
 }
 
 pub fn part2(input_string: &str) -> String {
-    let mut moons = Moons::parse(input_string);
-    let initial_moons = moons.clone();
-    let mut cycles: [Option<usize>; 3] = [None; 3];
-
-    let mut step = 0;
-    while cycles.iter().any(|x| x.is_none()) {
-        moons.step();
-        step += 1;
-
-        for (i, cycle) in cycles.iter_mut().enumerate() {
-            if cycle.is_none() {
-                let mut same = true;
-                for moon in 0..4 {
-                    if initial_moons.positions[moon][i] != moons.positions[moon][i]
-                        || initial_moons.velocities[moon][i] != moons.velocities[moon][i]
-                    {
-                        same = false;
-                    }
-                }
-                if same {
-                    cycle.replace(step);
-                }
-            }
-        }
-    }
-
-    lcd3(cycles[0].unwrap(), cycles[1].unwrap(), cycles[2].unwrap()).to_string()
+    steps_to_repeat(input_string).to_string()
 }
 
 #[test]
@@ -140,3 +178,47 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day12_input.txt")), "548525804273976");
 }
+
+#[test]
+fn tests_steps_to_repeat() {
+    let first_example = "<x=-1, y=0, z=2>
+<x=2, y=-10, z=-7>
+<x=4, y=-8, z=8>
+<x=3, y=5, z=-1>";
+    let second_example = "<x=-8, y=-10, z=0>
+<x=5, y=5, z=10>
+<x=2, y=-7, z=3>
+<x=9, y=-8, z=-3>";
+
+    assert_eq!(total_energy(first_example, 10), 179);
+    assert_eq!(total_energy(second_example, 100), 1940);
+    assert_eq!(steps_to_repeat(first_example), 2772);
+    assert_eq!(steps_to_repeat(second_example), 4_686_774_924);
+
+    // Two moons pulling each other together and apart again:
+    assert_eq!(cycle_length(&[0, 3], &[0, 0]), 8);
+    assert_eq!(cycle_length(&[5], &[0]), 1);
+}
+
+#[test]
+fn tests_simulation() {
+    let mut simulation = Simulation::parse(
+        "<x=-1, y=0, z=2>
+<x=2, y=-10, z=-7>
+<x=4, y=-8, z=8>
+<x=3, y=5, z=-1>",
+    );
+    assert_eq!((simulation.time(), simulation.total_energy()), (0, 0));
+
+    simulation.step();
+    assert_eq!(simulation.time(), 1);
+    assert_eq!(simulation.positions[0], [2, -1, 1]);
+    assert_eq!(simulation.velocities[0], [3, -1, -1]);
+    assert_eq!(simulation.total_energy(), 229);
+
+    for _ in 1..10 {
+        simulation.step();
+    }
+    assert_eq!(simulation.time(), 10);
+    assert_eq!(simulation.total_energy(), 179);
+}
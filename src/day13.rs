@@ -1,33 +1,39 @@
+#[cfg(feature = "image")]
+use crate::grid::Grid2D;
 use crate::int_code::Program;
+#[cfg(feature = "image")]
+use crate::render::{self, Rgb};
 use std::collections::HashMap;
 use std::env;
 
+/// The tile id of a block.
+const BLOCK_TILE: i64 = 2;
+
+/// The number of block tiles drawn when running the game without inserting quarters.
+pub fn count_blocks(input_string: &str) -> usize {
+    let mut program = Program::parse(input_string);
+    let mut block_count = 0;
+    while let [_x, _y, tile_id] = program.run_for_n_outputs(3)[..] {
+        if tile_id == BLOCK_TILE {
+            block_count += 1;
+        }
+    }
+    block_count
+}
+
 pub fn part1(input_string: &str) -> String {
-    Program::parse(input_string)
-        .run_for_output()
-        .iter()
-        .skip(2)
-        .step_by(3)
-        .filter(|&&t| t == 2)
-        .count()
-        .to_string()
+    count_blocks(input_string).to_string()
 }
 
-fn render(current_score: i64, tiles: &HashMap<(i64, i64), i64>) {
-    let mut output = String::new();
-    output.push_str("\x1b[2J\x1b[H");
-    let mut min_x = std::i64::MAX;
-    let mut max_x = std::i64::MIN;
-    let mut min_y = std::i64::MAX;
-    let mut max_y = std::i64::MIN;
-    for &(x, y) in tiles.keys() {
-        min_x = std::cmp::min(min_x, x);
-        max_x = std::cmp::max(max_x, x);
-        min_y = std::cmp::min(min_y, y);
-        max_y = std::cmp::max(max_y, y);
-    }
+/// The screen as text, with the score on the first line followed by the tiles
+/// within the bounding box of all drawn tiles.
+pub fn render_frame(current_score: i64, tiles: &HashMap<(i64, i64), i64>) -> String {
+    let mut output = format!("Score: {}\n", current_score);
+    let min_x = tiles.keys().map(|&(x, _)| x).min().unwrap_or(0);
+    let max_x = tiles.keys().map(|&(x, _)| x).max().unwrap_or(-1);
+    let min_y = tiles.keys().map(|&(_, y)| y).min().unwrap_or(0);
+    let max_y = tiles.keys().map(|&(_, y)| y).max().unwrap_or(-1);
 
-    output.push_str(format!("Score: {}\n", current_score).as_str());
     for y in min_y..=max_y {
         for x in min_x..=max_x {
             let character = match tiles.get(&(x, y)) {
@@ -41,58 +47,189 @@ fn render(current_score: i64, tiles: &HashMap<(i64, i64), i64>) {
         }
         output.push('\n');
     }
-    println!("{}", output);
+    output
+}
+
+fn render(current_score: i64, tiles: &HashMap<(i64, i64), i64>) {
+    println!("\x1b[2J\x1b[H{}", render_frame(current_score, tiles));
     std::thread::sleep(std::time::Duration::from_millis(10));
 }
 
-pub fn part2(input_string: &str) -> String {
+/// The colors of the empty, wall, block, paddle and ball tiles in `render_png`.
+#[cfg(feature = "image")]
+const TILE_PALETTE: [Rgb; 5] = [
+    Rgb::BLACK,
+    Rgb(0x80, 0x80, 0x80),
+    Rgb(0x40, 0x80, 0xFF),
+    Rgb::WHITE,
+    Rgb(0xFF, 0xC0, 0x00),
+];
+
+/// The number of pixels in each direction used for a single tile in `render_png`.
+#[cfg(feature = "image")]
+const PNG_TILE_SIZE: usize = 10;
+
+/// The number of pixels in each direction used for a single tile in `render_gif`.
+#[cfg(feature = "image")]
+const GIF_TILE_SIZE: usize = 4;
+
+/// The time each frame is shown in `render_gif`, in hundredths of a second.
+#[cfg(feature = "image")]
+const GIF_FRAME_DELAY: u16 = 2;
+
+/// The tiles on the screen, which must have been stored when updating it.
+#[cfg(feature = "image")]
+fn screen_grid(screen: &Screen) -> Grid2D<u8> {
+    let width = screen.tiles.keys().map(|&(x, _)| x + 1).max().unwrap_or(0);
+    let height = screen.tiles.keys().map(|&(_, y)| y + 1).max().unwrap_or(0);
+    let mut grid = Grid2D::new(width as usize, height as usize, 0);
+    for (&(x, y), &tile_id) in screen.tiles.iter() {
+        grid[(x as usize, y as usize)] = tile_id as u8;
+    }
+    grid
+}
+
+/// Write the screen drawn when running the game without inserting quarters as a PNG.
+#[cfg(feature = "image")]
+pub fn render_png(input_string: &str, path: &str) -> std::io::Result<()> {
     let mut program = Program::parse(input_string);
+    let mut screen = Screen::default();
+    screen.update(&program.run_for_output(), true);
+    render::to_png(&screen_grid(&screen), &TILE_PALETTE, PNG_TILE_SIZE, path)
+}
 
-    // "Memory address 0 represents the number of quarters that
-    // have been inserted; set it to 2 to play for free."
-    program.write_memory(0, 2);
+/// Write an animated GIF of the game being played by `play`, with a frame for each move.
+#[cfg(feature = "image")]
+pub fn render_gif(input_string: &str, path: &str) -> std::io::Result<()> {
+    render_gif_frames(input_string, path, usize::MAX)
+}
 
-    let mut tiles = HashMap::new();
-    let mut current_score = 0;
-    let mut ball_x = -1;
-    let mut paddle_x = -1;
-    let debug = env::var("ADVENT_DEBUG").is_ok();
+/// Like `render_gif`, but only with the first `max_frames` moves.
+#[cfg(feature = "image")]
+fn render_gif_frames(input_string: &str, path: &str, max_frames: usize) -> std::io::Result<()> {
+    let mut frames = Vec::new();
+    run_game(
+        input_string,
+        Some(&mut |screen: &Screen| {
+            if frames.len() < max_frames {
+                frames.push(screen_grid(screen));
+            }
+        }),
+    );
+    render::to_gif(&frames, &TILE_PALETTE, GIF_TILE_SIZE, GIF_FRAME_DELAY, path)
+}
 
-    loop {
-        program.run_for_output().chunks(3).for_each(|chunk| {
+/// The state of the arcade screen as built up from (x, y, tile) output triples.
+#[derive(Default)]
+struct Screen {
+    tiles: HashMap<(i64, i64), i64>,
+    score: i64,
+    ball_x: i64,
+    paddle_x: i64,
+}
+
+impl Screen {
+    fn update(&mut self, output: &[i64], store_tiles: bool) {
+        output.chunks(3).for_each(|chunk| {
             let x = chunk[0];
             let y = chunk[1];
             let third = chunk[2];
             if x == -1 && y == 0 {
-                current_score = third;
+                self.score = third;
             } else {
-                if debug {
-                    tiles.insert((x, y), third);
+                if store_tiles {
+                    self.tiles.insert((x, y), third);
                 }
                 if third == 3 {
-                    paddle_x = x;
+                    self.paddle_x = x;
                 } else if third == 4 {
-                    ball_x = x;
+                    self.ball_x = x;
                 };
             }
         });
+    }
+
+    fn joystick_input(&self) -> i64 {
+        match self.ball_x {
+            _ if self.ball_x > self.paddle_x => 1,
+            _ if self.ball_x < self.paddle_x => -1,
+            _ => 0,
+        }
+    }
+}
+
+/// Play the game for free by keeping the paddle under the ball, returning the final score.
+pub fn play(input_string: &str) -> i64 {
+    run_game(input_string, None)
+}
+
+/// Same as `play`, but invoking `on_move` with the screen (including its tiles) before each
+/// move and after the game is over.
+fn run_game(input_string: &str, mut on_move: Option<&mut dyn FnMut(&Screen)>) -> i64 {
+    let mut program = Program::parse(input_string);
+
+    // "Memory address 0 represents the number of quarters that
+    // have been inserted; set it to 2 to play for free."
+    program.write_memory(0, 2);
+
+    let mut screen = Screen::default();
+    let debug = env::var("ADVENT_DEBUG").is_ok();
+
+    loop {
+        screen.update(&program.run_for_output(), debug || on_move.is_some());
 
         if debug {
-            render(current_score, &tiles);
+            render(screen.score, &screen.tiles);
+        }
+        if let Some(on_move) = &mut on_move {
+            on_move(&screen);
         }
 
         if program.is_halted() {
             break;
         }
 
-        program.input(match ball_x {
-            _ if ball_x > paddle_x => 1,
-            _ if ball_x < paddle_x => -1,
+        program.input(screen.joystick_input());
+    }
+
+    screen.score
+}
+
+/// Play the game by hand, reading a joystick move per line from stdin: `a` for left,
+/// `d` for right and anything else (such as a space) to stay. Returns the final score.
+pub fn play_interactive(input_string: &str) -> i64 {
+    let mut program = Program::parse(input_string);
+    program.write_memory(0, 2);
+
+    let mut screen = Screen::default();
+    let stdin = std::io::stdin();
+    let mut line = String::new();
+
+    loop {
+        screen.update(&program.run_for_output(), true);
+        print!("\x1b[2J\x1b[H{}", render_frame(screen.score, &screen.tiles));
+
+        if program.is_halted() {
+            break;
+        }
+
+        println!("Move (a = left, d = right, space = stay):");
+        line.clear();
+        if stdin.read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        program.input(match line.chars().next() {
+            Some('a') => -1,
+            Some('d') => 1,
             _ => 0,
         });
     }
 
-    current_score.to_string()
+    screen.score
+}
+
+pub fn part2(input_string: &str) -> String {
+    play(input_string).to_string()
 }
 
 #[test]
@@ -100,7 +237,67 @@ pub fn tests_part1() {
     assert_eq!(part1(include_str!("day13_input.txt")), "462");
 }
 
+#[test]
+fn tests_count_blocks() {
+    assert_eq!(count_blocks(include_str!("day13_input.txt")), 462);
+
+    // Draw a wall, two blocks and a paddle, and then the first block again:
+    assert_eq!(
+        count_blocks("104,0,104,0,104,1,104,1,104,0,104,2,104,2,104,0,104,2,104,3,104,0,104,3,104,1,104,0,104,2,99"),
+        3
+    );
+}
+
 #[test]
 fn tests_part2() {
     assert_eq!(part2(include_str!("day13_input.txt")), "23981");
 }
+
+#[test]
+fn tests_play() {
+    assert_eq!(play(include_str!("day13_input.txt")), 23981);
+}
+
+#[test]
+fn tests_render_frame() {
+    let mut tiles = HashMap::new();
+    for x in 0..5 {
+        tiles.insert((x, 0), 1);
+    }
+    tiles.insert((0, 1), 1);
+    tiles.insert((1, 1), 2);
+    tiles.insert((2, 1), 4);
+    tiles.insert((4, 1), 1);
+    tiles.insert((0, 2), 1);
+    tiles.insert((3, 2), 3);
+    tiles.insert((4, 2), 1);
+    assert_eq!(render_frame(42, &tiles), "Score: 42\n█████\n█▬○ █\n█  ▢█\n");
+    assert_eq!(render_frame(0, &HashMap::new()), "Score: 0\n");
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn tests_render_png() {
+    let path = std::env::temp_dir().join("advent_of_code_day13.png");
+    let path = path.to_str().unwrap();
+    render_png(include_str!("day13_input.txt"), path).unwrap();
+
+    let png = std::fs::read(path).unwrap();
+    assert_eq!(&png[12..16], b"IHDR");
+    let width = u32::from_be_bytes([png[16], png[17], png[18], png[19]]);
+    assert_eq!(width as usize % PNG_TILE_SIZE, 0);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn tests_render_gif() {
+    let path = std::env::temp_dir().join("advent_of_code_day13.gif");
+    let path = path.to_str().unwrap();
+    render_gif_frames(include_str!("day13_input.txt"), path, 20).unwrap();
+
+    let gif = std::fs::read(path).unwrap();
+    assert_eq!(&gif[0..6], b"GIF89a");
+    assert!(gif.len() > 20 * 4 * 4);
+    std::fs::remove_file(path).unwrap();
+}
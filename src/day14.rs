@@ -20,7 +20,8 @@ impl ChemicalIdAssigner {
     }
 }
 
-struct Reactions {
+/// The reactions of a nanofactory, each producing a single chemical.
+pub struct ReactionGraph {
     id_assigner: ChemicalIdAssigner,
     // Indexed by chemical id that is produced, contains amount produced and required.
     produced_by: Vec<(ChemicalAmount, Vec<ChemicalAmount>)>,
@@ -28,8 +29,8 @@ struct Reactions {
     ore_id: ChemicalId,
 }
 
-impl Reactions {
-    fn parse(input_string: &str) -> Reactions {
+impl ReactionGraph {
+    pub fn parse(input_string: &str) -> ReactionGraph {
         let mut id_assigner = ChemicalIdAssigner::new();
 
         // Indexed by chemical id that is produced, to amount produced and required.
@@ -64,19 +65,72 @@ impl Reactions {
         let fuel_id = *id_assigner.id_map.get("FUEL").unwrap();
         let ore_id = *id_assigner.id_map.get("ORE").unwrap();
 
-        Reactions {
+        ReactionGraph {
             id_assigner,
             produced_by: reactions,
             fuel_id,
             ore_id,
         }
     }
+
+    /// The chemical names indexed by chemical id.
+    fn chemical_names(&self) -> Vec<&str> {
+        let mut names = vec![""; self.id_assigner.id_map.len()];
+        for (name, &id) in self.id_assigner.id_map.iter() {
+            names[id] = name;
+        }
+        names
+    }
+
+    /// A Graphviz DOT description of the reactions, with an edge from each required chemical
+    /// to the chemical it is used to produce, labeled with the amount required. Chemicals
+    /// produced by a reaction are labeled with the amount a single reaction produces.
+    pub fn to_dot(&self) -> String {
+        let names = self.chemical_names();
+
+        let mut dot = String::from("digraph reactions {\n");
+        for (id, name) in names.iter().enumerate() {
+            match self.produced_by.get(id) {
+                Some(&(produced_amount, _)) if produced_amount > 0 => {
+                    dot.push_str(&format!(
+                        "  \"{}\" [label=\"{} {}\"];\n",
+                        name, produced_amount, name
+                    ));
+                }
+                _ => dot.push_str(&format!("  \"{}\";\n", name)),
+            }
+        }
+        for (produced_id, (_, required)) in self.produced_by.iter().enumerate() {
+            for (required_id, &required_amount) in required.iter().enumerate() {
+                if required_amount > 0 {
+                    dot.push_str(&format!(
+                        "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                        names[required_id], names[produced_id], required_amount
+                    ));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The ore needed to produce the given amount of fuel, where chemicals left over from one
+/// reaction are used by later ones.
+pub fn ore_for_fuel(reactions: &ReactionGraph, fuel: u64) -> u64 {
+    ore_for_fuel_with_leftovers(reactions, fuel).0
 }
 
-fn required_ore(reactions: &Reactions, fuel_to_produce: ChemicalAmount) -> ChemicalAmount {
+/// Like `ore_for_fuel`, but also returning the amounts of chemicals left over after producing
+/// the fuel, by chemical name, for the chemicals with anything left over.
+pub fn ore_for_fuel_with_leftovers(
+    reactions: &ReactionGraph,
+    fuel: u64,
+) -> (u64, HashMap<String, u64>) {
     let mut needed: Vec<ChemicalAmount> = vec![0; reactions.id_assigner.id_map.len()];
-    needed[reactions.fuel_id] = fuel_to_produce;
+    needed[reactions.fuel_id] = fuel as ChemicalAmount;
 
+    // A negative amount needed is left over from previous reactions:
     while let Some((needed_id, &needed_amount)) = needed
         .iter()
         .enumerate()
@@ -98,30 +152,36 @@ fn required_ore(reactions: &Reactions, fuel_to_produce: ChemicalAmount) -> Chemi
         }
     }
 
-    needed[reactions.ore_id]
-}
-
-pub fn part1(input_string: &str) -> String {
-    let reactions = Reactions::parse(input_string);
-    required_ore(&reactions, 1).to_string()
+    let leftovers = reactions
+        .chemical_names()
+        .into_iter()
+        .zip(needed.iter())
+        .filter(|&(_, &amount)| amount < 0)
+        .map(|(name, &amount)| (name.to_string(), (-amount) as u64))
+        .collect();
+    (needed[reactions.ore_id] as u64, leftovers)
 }
 
-pub fn part2(input_string: &str) -> String {
-    let reactions = Reactions::parse(input_string);
+/// The maximum amount of fuel which can be produced from the given amount of ore.
+pub fn max_fuel(reactions: &ReactionGraph, ore: u64) -> u64 {
+    let ore_for_single_fuel = ore_for_fuel(reactions, 1);
+    if ore_for_single_fuel > ore {
+        return 0;
+    }
 
-    const AVAILABLE_ORE: i64 = 1_000_000_000_000;
+    // Producing fuel in bulk costs at most as much per fuel as producing a single one,
+    // so start from that and double until the ore runs out:
+    let mut min_produced_fuel = ore / ore_for_single_fuel;
+    let mut max_produced_fuel = min_produced_fuel * 2;
+    while ore_for_fuel(reactions, max_produced_fuel) <= ore {
+        min_produced_fuel = max_produced_fuel;
+        max_produced_fuel *= 2;
+    }
 
-    let mut min_produced_fuel = 1;
-    let mut max_produced_fuel = AVAILABLE_ORE;
-    loop {
+    // Producing min_produced_fuel is within the ore budget, while max_produced_fuel is not:
+    while max_produced_fuel - min_produced_fuel > 1 {
         let fuel_to_produce = (max_produced_fuel + min_produced_fuel) / 2;
-        if fuel_to_produce == min_produced_fuel {
-            return min_produced_fuel.to_string();
-        }
-
-        let ore_amount = required_ore(&reactions, fuel_to_produce);
-
-        if ore_amount > AVAILABLE_ORE {
+        if ore_for_fuel(reactions, fuel_to_produce) > ore {
             // Uses too much ore, try less ambitious fuel production.
             max_produced_fuel = fuel_to_produce;
         } else {
@@ -129,6 +189,19 @@ pub fn part2(input_string: &str) -> String {
             min_produced_fuel = fuel_to_produce;
         }
     }
+    min_produced_fuel
+}
+
+pub fn part1(input_string: &str) -> String {
+    let reactions = ReactionGraph::parse(input_string);
+    ore_for_fuel(&reactions, 1).to_string()
+}
+
+pub fn part2(input_string: &str) -> String {
+    const AVAILABLE_ORE: u64 = 1_000_000_000_000;
+
+    let reactions = ReactionGraph::parse(input_string);
+    max_fuel(&reactions, AVAILABLE_ORE).to_string()
 }
 
 #[test]
@@ -183,3 +256,90 @@ fn tests_part2() {
 
     assert_eq!(part2(include_str!("day14_input.txt")), "1184209");
 }
+
+#[test]
+fn tests_max_fuel() {
+    let reactions = ReactionGraph::parse(
+        "171 ORE => 8 CNZTR
+7 ZLQW, 3 BMBT, 9 XCVML, 26 XMNCP, 1 WPTQ, 2 MZWV, 1 RJRHP => 4 PLWSL
+114 ORE => 4 BHXH
+14 VRPVC => 6 BMBT
+6 BHXH, 18 KTJDG, 12 WPTQ, 7 PLWSL, 31 FHTLT, 37 ZDVW => 1 FUEL
+6 WPTQ, 2 BMBT, 8 ZLQW, 18 KTJDG, 1 XMNCP, 6 MZWV, 1 RJRHP => 6 FHTLT
+15 XDBXC, 2 LTCX, 1 VRPVC => 6 ZLQW
+13 WPTQ, 10 LTCX, 3 RJRHP, 14 XMNCP, 2 MZWV, 1 ZLQW => 1 ZDVW
+5 BMBT => 4 WPTQ
+189 ORE => 9 KTJDG
+1 MZWV, 17 XDBXC, 3 XCVML => 2 XMNCP
+12 VRPVC, 27 CNZTR => 2 XDBXC
+15 KTJDG, 12 BHXH => 5 XCVML
+3 BHXH, 2 VRPVC => 7 MZWV
+121 ORE => 7 VRPVC
+7 XCVML => 6 RJRHP
+5 BHXH, 4 VRPVC => 5 LTCX",
+    );
+    assert_eq!(ore_for_fuel(&reactions, 1), 2_210_736);
+    assert_eq!(max_fuel(&reactions, 1_000_000_000_000), 460_664);
+
+    let reactions = ReactionGraph::parse("10 ORE => 10 A\n1 ORE => 1 B\n7 A, 1 B => 1 FUEL");
+    assert_eq!(ore_for_fuel(&reactions, 1), 11);
+    assert_eq!(ore_for_fuel(&reactions, 10), 80);
+    assert_eq!(max_fuel(&reactions, 10), 0);
+    assert_eq!(max_fuel(&reactions, 11), 1);
+    assert_eq!(max_fuel(&reactions, 80), 10);
+    assert_eq!(max_fuel(&reactions, 79), 9);
+}
+
+#[test]
+fn tests_ore_for_fuel_with_leftovers() {
+    let reactions = ReactionGraph::parse("10 ORE => 10 A\n1 ORE => 1 B\n7 A, 1 B => 1 FUEL");
+    let (ore, leftovers) = ore_for_fuel_with_leftovers(&reactions, 1);
+    assert_eq!(ore, 11);
+    assert_eq!(leftovers.len(), 1);
+    assert_eq!(leftovers["A"], 3);
+
+    let (ore, leftovers) = ore_for_fuel_with_leftovers(&reactions, 10);
+    assert_eq!(ore, 80);
+    assert!(leftovers.is_empty());
+
+    let reactions = ReactionGraph::parse(
+        "9 ORE => 2 A
+8 ORE => 3 B
+7 ORE => 5 C
+3 A, 4 B => 1 AB
+5 B, 7 C => 1 BC
+4 C, 1 A => 1 CA
+2 AB, 3 BC, 4 CA => 1 FUEL",
+    );
+    let (ore, leftovers) = ore_for_fuel_with_leftovers(&reactions, 1);
+    assert_eq!(ore, 165);
+    assert_eq!(leftovers.get("B"), Some(&1));
+    assert_eq!(leftovers.get("C"), Some(&3));
+    assert_eq!(leftovers.get("FUEL"), None);
+}
+
+#[test]
+fn tests_to_dot() {
+    let reactions = ReactionGraph::parse(
+        "10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL",
+    );
+    let dot = reactions.to_dot();
+    assert!(dot.starts_with("digraph reactions {\n"));
+    assert!(dot.ends_with("}\n"));
+
+    let lines: Vec<&str> = dot.lines().collect();
+    let edge_count = lines.iter().filter(|line| line.contains(" -> ")).count();
+    let node_count = lines.len() - 2 - edge_count;
+    assert_eq!((node_count, edge_count), (7, 10));
+
+    assert!(lines.contains(&"  \"ORE\";"));
+    assert!(lines.contains(&"  \"A\" [label=\"10 A\"];"));
+    assert!(lines.contains(&"  \"ORE\" -> \"A\" [label=\"10\"];"));
+    assert!(lines.contains(&"  \"E\" -> \"FUEL\" [label=\"1\"];"));
+    assert!(lines.contains(&"  \"A\" -> \"FUEL\" [label=\"7\"];"));
+}
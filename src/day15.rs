@@ -1,104 +1,205 @@
+use crate::direction::Direction;
 use crate::int_code::Program;
-use std::collections::{HashSet, VecDeque};
-
-const DIRECTIONS: &[(i32, i32); 4] = &[(0, 1), (0, -1), (-1, 0), (1, 0)];
-
-/// The intcode instruction for moving the robot in the specified direction.
-fn instruction_for_direction(direction: (i32, i32)) -> i64 {
-    match direction {
-        (0, 1) => 1,
-        (0, -1) => 2,
-        (-1, 0) => 3,
-        (1, 0) => 4,
-        _ => panic!("Invalid direction ({},{})", direction.0, direction.1),
+use crate::pathfinding::flood_fill_distances;
+use std::collections::HashMap;
+
+/// The directions the droid can move in, in the order of their intcode movement commands.
+fn directions() -> impl Iterator<Item = Direction> {
+    (1..=4).filter_map(Direction::from_intcode)
+}
+
+/// The position one step away from the given one in the specified direction.
+fn step(position: (i32, i32), direction: Direction) -> (i32, i32) {
+    let offset = direction.as_offset();
+    (position.0 + offset.x, position.1 + offset.y)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Tile {
+    Wall,
+    Open,
+    Oxygen,
+}
+
+/// The explored area of the space ship.
+pub struct Maze {
+    pub tiles: HashMap<(i32, i32), Tile>,
+}
+
+impl Maze {
+    fn oxygen_position(&self) -> Option<(i32, i32)> {
+        self.tiles
+            .iter()
+            .find(|(_, &tile)| tile == Tile::Oxygen)
+            .map(|(&position, _)| position)
+    }
+
+    /// The distance from the given position to all reachable positions.
+    fn distances_from(&self, start: (i32, i32)) -> HashMap<(i32, i32), usize> {
+        flood_fill_distances(start, |&position: &(i32, i32)| {
+            directions()
+                .map(move |direction| step(position, direction))
+                .filter(|new_position| {
+                    matches!(
+                        self.tiles.get(new_position),
+                        Some(Tile::Open) | Some(Tile::Oxygen)
+                    )
+                })
+        })
     }
 }
 
-/// Search the space ship using the given intcode program.
-/// The on_visit is called with ((pos_x, pos_y), is_oxygen, distance).
-fn search_space_ship<F>(input_string: &str, mut on_visit: F)
-where
-    F: FnMut((i32, i32), bool, i32),
-{
-    let initial_program = Program::parse(input_string);
-    let initial_position = (0, 0);
-
-    // Contains (pos_x, pos_y):
-    let mut visited: HashSet<(i32, i32)> = HashSet::new();
-    // Contains ((pos_x, pos_y), distance, program):
-    let mut to_visit = VecDeque::new();
-
-    visited.insert(initial_position);
-    to_visit.push_back(((initial_position), 0, initial_program));
-    on_visit(initial_position, false, 0);
-
-    while let Some((position, distance, program)) = to_visit.pop_front() {
-        for &direction in DIRECTIONS.iter() {
-            let new_position = (position.0 + direction.0, position.1 + direction.1);
-            if !visited.insert(new_position) {
-                continue;
-            }
-            let new_distance = distance + 1;
+/// Move the droid one step in the given direction, returning the tile it ended up facing.
+fn move_droid(program: &mut Program, direction: Direction) -> Tile {
+    program.input(direction.as_intcode());
+    match program.run_for_n_outputs(1)[..] {
+        // 0: The repair droid hit a wall. Its position has not changed.
+        [0] => Tile::Wall,
+        // 1: The repair droid has moved one step in the requested direction.
+        [1] => Tile::Open,
+        // 2: The repair droid has moved one step in the requested direction;
+        // its new position is the location of the oxygen system.
+        [2] => Tile::Oxygen,
+        ref other => panic!("Invalid output: {:?}", other),
+    }
+}
 
-            let mut updated_program = program.clone();
-            updated_program.input(instruction_for_direction(direction));
+/// Explore the space ship using the given intcode program, recording the tile at every
+/// position reachable by the repair droid (as well as the walls surrounding them).
+///
+/// A single droid is driven depth first through the whole ship, backtracking the way
+/// it came once every neighbor of its current position has been visited.
+pub fn explore(input_string: &str) -> Maze {
+    explore_with(input_string, None)
+}
 
-            match updated_program.run_for_output()[0] {
-                // 0: The repair droid hit a wall. Its position has not changed.
-                0 => {
-                    // Do nothing.
-                }
-                // 1: The repair droid has moved one step in the requested direction.
-                // 2: The repair droid has moved one step in the requested direction;
-                // its new position is the location of the oxygen system.
-                val @ 1..=2 => {
-                    on_visit(new_position, val == 2, new_distance);
-                    to_visit.push_back((new_position, new_distance, updated_program.clone()));
+/// Same as `explore`, but invoking `on_move` with the tiles discovered so far and the
+/// position of the droid after each movement command.
+#[allow(clippy::type_complexity)]
+fn explore_with(
+    input_string: &str,
+    mut on_move: Option<&mut dyn FnMut(&HashMap<(i32, i32), Tile>, (i32, i32))>,
+) -> Maze {
+    let mut program = Program::parse(input_string);
+    let mut position = (0, 0);
+
+    let mut tiles = HashMap::new();
+    // The directions moved in to reach the current position, for backtracking:
+    let mut path: Vec<Direction> = Vec::new();
+
+    tiles.insert(position, Tile::Open);
+
+    loop {
+        let unexplored =
+            directions().find(|&direction| !tiles.contains_key(&step(position, direction)));
+
+        match unexplored {
+            Some(direction) => {
+                let new_position = step(position, direction);
+                let tile = move_droid(&mut program, direction);
+                tiles.insert(new_position, tile);
+                if tile != Tile::Wall {
+                    position = new_position;
+                    path.push(direction);
                 }
-                other => panic!("Invalid output: {}", other),
             }
+            None => match path.pop() {
+                Some(direction) => {
+                    let back = direction.opposite();
+                    move_droid(&mut program, back);
+                    position = step(position, back);
+                }
+                None => break,
+            },
         }
-    }
-}
 
-pub fn part1(input_string: &str) -> String {
-    let mut distance_to_oxygen = -1;
-    search_space_ship(input_string, |_, is_oxygen, distance| {
-        if is_oxygen {
-            distance_to_oxygen = distance;
+        if let Some(on_move) = &mut on_move {
+            on_move(&tiles, position);
         }
-    });
-    distance_to_oxygen.to_string()
+    }
+
+    Maze { tiles }
 }
 
-pub fn part2(input_string: &str) -> String {
-    // Contains (pos_x, pos_y).
-    let mut locations_without_oxygen = HashSet::new();
-    // Contains ((pos_x, pos_y), distance_from_oxygen).
-    let mut to_visit = VecDeque::new();
-
-    search_space_ship(input_string, |position, is_oxygen, _| {
-        if is_oxygen {
-            to_visit.push_back((position, 0));
-        } else {
-            locations_without_oxygen.insert(position);
-        }
-    });
-
-    let mut furthest_distance = -1;
-    while let Some((position, distance)) = to_visit.pop_front() {
-        for &direction in DIRECTIONS.iter() {
-            let new_position = (position.0 + direction.0, position.1 + direction.1);
-            if locations_without_oxygen.remove(&new_position) {
-                furthest_distance = distance + 1;
-                to_visit.push_back((new_position, furthest_distance));
-            }
+/// Render the discovered tiles with the northernmost row first, showing walls as `█`,
+/// open tiles as `.`, the oxygen system as `O`, the droid as `D` and undiscovered
+/// tiles as spaces.
+pub fn render_maze(tiles: &HashMap<(i32, i32), Tile>, droid: (i32, i32)) -> String {
+    let positions = || tiles.keys().chain(std::iter::once(&droid));
+    let min_x = positions().map(|&(x, _)| x).min().unwrap_or(0);
+    let max_x = positions().map(|&(x, _)| x).max().unwrap_or(0);
+    let min_y = positions().map(|&(_, y)| y).min().unwrap_or(0);
+    let max_y = positions().map(|&(_, y)| y).max().unwrap_or(0);
+
+    let mut result = String::new();
+    for y in (min_y..=max_y).rev() {
+        for x in min_x..=max_x {
+            result.push(if (x, y) == droid {
+                'D'
+            } else {
+                match tiles.get(&(x, y)) {
+                    Some(Tile::Wall) => '█',
+                    Some(Tile::Open) => '.',
+                    Some(Tile::Oxygen) => 'O',
+                    None => ' ',
+                }
+            });
         }
+        result.push('\n');
     }
+    result
+}
+
+/// Explore the space ship while redrawing the discovered area in the terminal after each
+/// movement command, returning the explored maze.
+///
+/// The screen is redrawn using plain ANSI escape sequences, which most terminals support,
+/// rather than through a terminal library such as crossterm.
+#[cfg(feature = "visual")]
+pub fn explore_visual(input_string: &str) -> Maze {
+    use std::io::Write;
+
+    explore_with(
+        input_string,
+        Some(&mut |tiles: &HashMap<(i32, i32), Tile>, droid| {
+            // Clear the screen and move the cursor to the top left corner:
+            print!("\x1b[2J\x1b[H{}", render_maze(tiles, droid));
+            // Stdout is line buffered, so flush to show the frame before sleeping:
+            let _ = std::io::stdout().flush();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }),
+    )
+}
 
-    furthest_distance.to_string()
+/// The fewest number of movement commands required to move the droid to the oxygen system.
+pub fn shortest_path_to_oxygen(maze: &Maze) -> Option<usize> {
+    let oxygen_position = maze.oxygen_position()?;
+    maze.distances_from((0, 0)).get(&oxygen_position).copied()
+}
+
+/// The number of minutes it takes to fill the area with oxygen from the oxygen system.
+pub fn minutes_to_fill(maze: &Maze) -> Option<usize> {
+    let oxygen_position = maze.oxygen_position()?;
+    maze.distances_from(oxygen_position).values().max().copied()
+}
+
+/// The shortest path to the oxygen system and the minutes to fill the area with oxygen,
+/// exploring the space ship only once.
+pub fn solve(input_string: &str) -> (usize, usize) {
+    let maze = explore(input_string);
+    (
+        shortest_path_to_oxygen(&maze).expect("No oxygen system found"),
+        minutes_to_fill(&maze).expect("No oxygen system found"),
+    )
+}
+
+pub fn part1(input_string: &str) -> String {
+    solve(input_string).0.to_string()
 }
 
+pub fn part2(input_string: &str) -> String {
+    solve(input_string).1.to_string()
+}
 #[test]
 pub fn tests_part1() {
     assert_eq!(part1(include_str!("day15_input.txt")), "208");
@@ -108,3 +209,55 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day15_input.txt")), "306");
 }
+
+#[test]
+fn tests_explore() {
+    let maze = explore(include_str!("day15_input.txt"));
+    assert_eq!(maze.tiles.get(&(0, 0)), Some(&Tile::Open));
+    assert_eq!(
+        maze.tiles
+            .values()
+            .filter(|&&tile| tile == Tile::Oxygen)
+            .count(),
+        1
+    );
+    assert_eq!(shortest_path_to_oxygen(&maze), Some(208));
+    assert_eq!(minutes_to_fill(&maze), Some(306));
+}
+
+#[test]
+fn tests_solve() {
+    assert_eq!(solve(include_str!("day15_input.txt")), (208, 306));
+}
+
+#[test]
+fn tests_render_maze() {
+    let mut tiles = HashMap::new();
+    for x in -1..=2 {
+        tiles.insert((x, 1), Tile::Wall);
+        tiles.insert((x, -1), Tile::Wall);
+    }
+    tiles.insert((-1, 0), Tile::Wall);
+    tiles.insert((0, 0), Tile::Open);
+    tiles.insert((1, 0), Tile::Open);
+    tiles.insert((2, 0), Tile::Oxygen);
+    assert_eq!(render_maze(&tiles, (1, 0)), "████\n█.DO\n████\n");
+
+    // Undiscovered tiles are left blank:
+    tiles.remove(&(2, 1));
+    tiles.remove(&(0, -1));
+    assert_eq!(render_maze(&tiles, (0, 0)), "███ \n█D.O\n█ ██\n");
+
+    let mut moves = 0;
+    let maze = explore_with(
+        include_str!("day15_input.txt"),
+        Some(&mut |_: &HashMap<(i32, i32), Tile>, _| moves += 1),
+    );
+    // Each tile but the start is probed once, and each open one is backtracked from once:
+    let open_tiles = maze
+        .tiles
+        .values()
+        .filter(|&&tile| tile != Tile::Wall)
+        .count();
+    assert_eq!(moves, (maze.tiles.len() - 1) + (open_tiles - 1));
+}
@@ -1,37 +1,87 @@
+use crate::parse;
 use std::iter::once;
 
-pub fn part1(input_string: &str) -> String {
-    let mut digits: Vec<i32> = input_string.bytes().map(|b| (b - 48) as i32).collect();
-    let mut new_digits = vec![0; digits.len()];
-    for _ in 0..100 {
-        for (index, digit) in new_digits.iter_mut().enumerate() {
-            *digit = digits
-                .iter()
-                .zip(
-                    once(0)
-                        .cycle()
-                        .take(index + 1)
-                        .chain(once(1).cycle().take(index + 1))
-                        .chain(once(0).cycle().take(index + 1))
-                        .chain(once(-1).cycle().take(index + 1))
-                        .cycle()
-                        .skip(1),
-                )
-                .fold(0, |acc, (&digit, mult)| acc + digit * mult)
-                .abs()
-                % 10;
-        }
+/// The signal length from which the digits of a phase are computed in parallel.
+#[cfg(not(target_arch = "wasm32"))]
+const PARALLEL_PHASE_MIN_LENGTH: usize = 500;
 
-        std::mem::swap(&mut digits, &mut new_digits);
-    }
+fn digits_to_string(digits: &[u8]) -> String {
+    digits.iter().map(|&digit| (digit + b'0') as char).collect()
+}
 
-    digits
+/// The output digit at `index` of a FFT phase, computed from all input digits.
+fn phase_digit(digits: &[u8], index: usize) -> u8 {
+    let sum = digits
         .iter()
-        .take(8)
-        .map(|b| ((b + 48) as u8) as char)
+        .zip(
+            once(0)
+                .cycle()
+                .take(index + 1)
+                .chain(once(1).cycle().take(index + 1))
+                .chain(once(0).cycle().take(index + 1))
+                .chain(once(-1).cycle().take(index + 1))
+                .cycle()
+                .skip(1),
+        )
+        .fold(0, |acc, (&digit, mult)| acc + i32::from(digit) * mult);
+    (sum.abs() % 10) as u8
+}
+
+/// Apply a single FFT phase to a signal.
+pub fn fft_phase_serial(digits: &[u8]) -> Vec<u8> {
+    (0..digits.len())
+        .map(|index| phase_digit(digits, index))
         .collect()
 }
 
+/// Apply a single FFT phase to a signal, computing the output digits in parallel.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn fft_phase_parallel(digits: &[u8]) -> Vec<u8> {
+    use rayon::prelude::*;
+
+    (0..digits.len())
+        .into_par_iter()
+        .map(|index| phase_digit(digits, index))
+        .collect()
+}
+
+fn fft_phase(digits: &[u8]) -> Vec<u8> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if digits.len() >= PARALLEL_PHASE_MIN_LENGTH {
+            return fft_phase_parallel(digits);
+        }
+    }
+    fft_phase_serial(digits)
+}
+
+/// Apply the given number of FFT phases to a signal, computing each output digit from all
+/// input digits.
+pub fn fft_phases(digits: &[u8], phases: usize) -> Vec<u8> {
+    let mut digits = digits.to_vec();
+    for _ in 0..phases {
+        digits = fft_phase(&digits);
+    }
+    digits
+}
+
+/// Apply the given number of FFT phases to the end of a signal, where the end starts in the
+/// second half of the signal. There each output digit is the sum of the input digit and all
+/// digits after it, so a phase is a running sum from the end.
+fn suffix_sum_phases(end_digits: &[u8], phases: usize) -> Vec<u8> {
+    let mut end_sequence = end_digits.to_vec();
+    for _ in 0..phases {
+        for index in (1..end_sequence.len()).rev() {
+            end_sequence[index - 1] = (end_sequence[index - 1] + end_sequence[index]) % 10;
+        }
+    }
+    end_sequence
+}
+
+pub fn part1(input_string: &str) -> String {
+    digits_to_string(&fft_phases(&parse::digits(input_string), 100)[..8])
+}
+
 /// Length of input: 650
 /// We now repeat it 10000 times, so length is 6,500,000 (six and a half millions).
 /// "The first seven digits of your initial input signal also represent the message offset."
@@ -62,31 +112,36 @@ pub fn part1(input_string: &str) -> String {
 /// The next element (second latest) is itself plus last element.
 /// The next element (third latest) is itself plus second latest and last element=itself + previous element!
 pub fn part2(input_string: &str) -> String {
+    decode_message(input_string)
+}
+
+/// The eight digit message in the real signal, which is the input repeated 10000 times, at
+/// the offset given by its first seven digits after 100 phases.
+///
+/// # Panics
+///
+/// If the offset is not in the second half of the real signal.
+pub fn decode_message(input_string: &str) -> String {
+    let input_string = input_string.trim();
     let offset = input_string[0..7].parse::<usize>().unwrap();
-    let digits: Vec<i32> = input_string.bytes().map(|b| (b - 48) as i32).collect();
+    let digits = parse::digits(input_string);
 
     let times_to_repeat = 10000;
-    let end_sequence_length = input_string.len() * times_to_repeat - offset as usize;
+    let signal_length = digits.len() * times_to_repeat;
+    assert!(
+        offset >= signal_length / 2 && offset + 8 <= signal_length,
+        "Offset {} is not in the second half of the signal",
+        offset
+    );
 
-    let mut end_sequence: Vec<i32> = digits
+    let end_sequence: Vec<u8> = digits
         .into_iter()
         .cycle()
         .skip(offset)
-        .take(end_sequence_length)
+        .take(signal_length - offset)
         .collect();
 
-    for _ in 0..100 {
-        for i in 0..(end_sequence.len() - 1) {
-            let index = end_sequence_length - i - 1;
-            end_sequence[index - 1] = (end_sequence[index - 1] + end_sequence[index]) % 10;
-        }
-    }
-
-    end_sequence
-        .iter()
-        .take(8)
-        .map(|&b| ((b + 48) as u8) as char)
-        .collect()
+    digits_to_string(&suffix_sum_phases(&end_sequence, 100)[..8])
 }
 
 #[test]
@@ -99,3 +154,65 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day16_input.txt")), "60592199");
 }
+
+#[test]
+fn tests_fft_phases() {
+    assert_eq!(
+        fft_phases(&parse::digits("12345678"), 1),
+        parse::digits("48226158")
+    );
+    assert_eq!(
+        fft_phases(&parse::digits("12345678"), 4),
+        parse::digits("01029498")
+    );
+    assert_eq!(part1("19617804207202209144916044189917"), "73745418");
+    assert_eq!(part1("69317163492948606335995924319873"), "52432133");
+
+    // Both ways of computing phases agree on the second half of a signal:
+    let digits = parse::digits("69317163492948606335995924319873");
+    for &offset in &[16, 20, 31] {
+        assert_eq!(
+            suffix_sum_phases(&digits[offset..], 7),
+            fft_phases(&digits, 7)[offset..].to_vec()
+        );
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn tests_fft_phase_parallel() {
+    let digits: Vec<u8> = parse::digits("69317163492948606335995924319873")
+        .into_iter()
+        .cycle()
+        .take(PARALLEL_PHASE_MIN_LENGTH * 2 + 3)
+        .collect();
+
+    let mut serial = digits.clone();
+    let mut parallel = digits;
+    for _ in 0..3 {
+        serial = fft_phase_serial(&serial);
+        parallel = fft_phase_parallel(&parallel);
+        assert_eq!(parallel, serial);
+    }
+    assert_eq!(fft_phase_parallel(&[]), Vec::<u8>::new());
+    assert_eq!(
+        fft_phase_parallel(&[1, 2, 3, 4, 5, 6, 7, 8]),
+        parse::digits("48226158")
+    );
+}
+
+#[test]
+fn tests_decode_message() {
+    assert_eq!(
+        decode_message("03036732577212944063491565474664"),
+        "84462026"
+    );
+    assert_eq!(
+        decode_message("02935109699940807407585447034323"),
+        "78725270"
+    );
+    assert_eq!(
+        decode_message("03081770884921959731165446850517"),
+        "53553731"
+    );
+}
@@ -1,34 +1,56 @@
+use crate::grid::Grid;
 use crate::int_code::Program;
+use crate::sequence;
+use std::fmt;
 use std::slice::Iter;
 
-pub fn part1(input_string: &str) -> String {
-    let mut program = Program::parse(input_string);
-    let map: String = program
-        .run_for_output()
+/// Parse the camera view printed by the ASCII program, ignoring anything after the first
+/// blank line (such as the movement routine prompt when the robot has been woken up).
+pub fn parse_scaffold(output: &[i64]) -> Grid<char> {
+    let text: String = output.iter().map(|&b| (b as u8) as char).collect();
+    let view = match text.find("\n\n") {
+        Some(end) => &text[..end],
+        None => &text,
+    };
+    Grid::parse(view)
+}
+
+fn is_scaffold(map: &Grid<char>, position: (i32, i32)) -> bool {
+    matches!(
+        map.get(position),
+        Some('#') | Some('^') | Some('v') | Some('<') | Some('>')
+    )
+}
+
+/// The scaffold positions with scaffold on all four sides.
+pub fn intersections(map: &Grid<char>) -> Vec<(i32, i32)> {
+    map.iter_coords()
+        .filter(|&position| {
+            is_scaffold(map, position)
+                && map
+                    .neighbors4(position)
+                    .filter(|&neighbor| is_scaffold(map, neighbor))
+                    .count()
+                    == 4
+        })
+        .collect()
+}
+
+fn alignment_sum_of_map(map: &Grid<char>) -> i64 {
+    intersections(map)
         .iter()
-        .map(|&b| (b as u8) as char)
-        .collect();
-    part1_map(&map)
+        .map(|&(x, y)| i64::from(x) * i64::from(y))
+        .sum()
 }
 
-fn part1_map(map: &str) -> String {
-    let map: Vec<&[u8]> = map.lines().map(|line| line.as_bytes()).collect();
-
-    let mut alignment_parameters_sum = 0;
-    for y in 1..(map.len() - 1) {
-        for x in 1..(map[0].len() - 1) {
-            if map[y][x] == b'#'
-                && map[y][x - 1] == b'#'
-                && map[y][x + 1] == b'#'
-                && map[y - 1][x] == b'#'
-                && map[y + 1][x] == b'#'
-            {
-                alignment_parameters_sum += x * y;
-            }
-        }
-    }
+/// The sum of the alignment parameters, row times column, of all scaffold intersections.
+pub fn alignment_sum(input_string: &str) -> i64 {
+    let mut program = Program::parse(input_string);
+    alignment_sum_of_map(&parse_scaffold(&program.run_for_output()))
+}
 
-    alignment_parameters_sum.to_string()
+pub fn part1(input_string: &str) -> String {
+    alignment_sum(input_string).to_string()
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -76,11 +98,11 @@ impl Direction {
         }
     }
 
-    fn instruction_for_turning_to(self, target: Direction) -> char {
+    fn instruction_for_turning_to(self, target: Direction) -> Move {
         if self.turn_right() == target {
-            'R'
+            Move::Right
         } else if self.turn_left() == target {
-            'L'
+            Move::Left
         } else {
             panic!("From {:?} to {:?}", self, target);
         }
@@ -97,52 +119,88 @@ impl Direction {
     }
 }
 
+/// A single movement instruction for the vacuum robot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Move {
+    Left,
+    Right,
+    Forward(u32),
+}
+
+/// A movement function, consisting of a sequence of moves.
+pub type Function = Vec<Move>;
+
+/// The main movement routine, consisting of a sequence of 'A', 'B' and 'C' function calls.
+pub type Routine = Vec<char>;
+
+/// Maximum number of characters (excluding the newline) in a routine or function.
+const MAX_INPUT_LENGTH: usize = 20;
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Move::Left => write!(f, "L"),
+            Move::Right => write!(f, "R"),
+            Move::Forward(steps) => write!(f, "{}", steps),
+        }
+    }
+}
+
+fn moves_to_ascii(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(Move::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn routine_to_ascii(routine: &[char]) -> String {
+    routine
+        .iter()
+        .map(char::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Compress a path into a main routine calling three movement functions, A, B and C,
+/// where the main routine and each function is at most 20 characters long.
+pub fn solve_routines(path: &[Move]) -> Option<(Routine, [Function; 3])> {
+    let (calls, functions) = sequence::compress(path, 3, MAX_INPUT_LENGTH)?;
+
+    let routine = calls
+        .iter()
+        .map(|&index| (b'A' + index as u8) as char)
+        .collect();
+    let function = |index: usize| functions.get(index).cloned().unwrap_or_default();
+    Some((routine, [function(0), function(1), function(2)]))
+}
+
 pub fn part2(input_string: &str) -> String {
     let mut program = Program::parse(input_string);
 
     program.write_memory(0, 2);
 
-    let map: String = program
-        .run_for_output()
-        .iter()
-        .map(|&b| (b as u8) as char)
-        .collect();
-    let map: Vec<&[u8]> = map.lines().map(|line| line.as_bytes()).collect();
-    // Strip away last two lines with blank line and "Main:" prompt:
-    let map = &map[0..(map.len() - 2)];
-
-    let mut robot_direction = Direction::Up;
-    let mut robot_position: (i32, i32) = (0, 0);
-    for y in 0..map.len() {
-        for x in 0..map[0].len() {
-            if map[y][x] == b'^' {
-                robot_direction = Direction::Up;
-                robot_position = (x as i32, y as i32);
-            } else if map[y][x] == b'v' {
-                robot_direction = Direction::Down;
-                robot_position = (x as i32, y as i32);
-            } else if map[y][x] == b'<' {
-                robot_direction = Direction::Left;
-                robot_position = (x as i32, y as i32);
-            } else if map[y][x] == b'>' {
-                robot_direction = Direction::Right;
-                robot_position = (x as i32, y as i32);
-            }
-        }
-    }
+    let map = parse_scaffold(&program.run_for_output());
+
+    let (mut robot_position, robot_tile) = map
+        .iter_coords()
+        .map(|position| (position, map[position]))
+        .find(|&(_, tile)| matches!(tile, '^' | 'v' | '<' | '>'))
+        .expect("No vacuum robot on the map");
+    let mut robot_direction = match robot_tile {
+        '^' => Direction::Up,
+        'v' => Direction::Down,
+        '<' => Direction::Left,
+        _ => Direction::Right,
+    };
 
     let mut starting = true;
     let mut moves_since_turn = 0;
-    let mut movements = String::new();
+    let mut movements = Vec::new();
 
     loop {
         let continuing_position = robot_direction.advance(robot_position);
-        if continuing_position.0 >= 0
-            && continuing_position.0 < map[0].len() as i32
-            && continuing_position.1 >= 0
-            && continuing_position.1 < map.len() as i32
-            && map[continuing_position.1 as usize][continuing_position.0 as usize] == b'#'
-        {
+        if map.get(continuing_position) == Some(&'#') {
             robot_position = continuing_position;
             moves_since_turn += 1;
             continue;
@@ -151,12 +209,7 @@ pub fn part2(input_string: &str) -> String {
         let mut possible_directions = Vec::new();
         for &direction in Direction::iterator() {
             let new_location = direction.advance(robot_position);
-            if new_location.0 >= 0
-                && new_location.0 < map[0].len() as i32
-                && new_location.1 >= 0
-                && new_location.1 < map.len() as i32
-                && map[new_location.1 as usize][new_location.0 as usize] == b'#'
-            {
+            if map.get(new_location) == Some(&'#') {
                 possible_directions.push(direction);
             }
         }
@@ -170,8 +223,7 @@ pub fn part2(input_string: &str) -> String {
             } else {
                 // Done.
                 if moves_since_turn > 0 {
-                    movements.push(',');
-                    movements.push_str(&moves_since_turn.to_string());
+                    movements.push(Move::Forward(moves_since_turn));
                 }
                 break;
             }
@@ -187,11 +239,9 @@ pub fn part2(input_string: &str) -> String {
                 moves_since_turn += 1;
             } else {
                 if moves_since_turn > 0 {
-                    movements.push(',');
-                    movements.push_str(&moves_since_turn.to_string());
+                    movements.push(Move::Forward(moves_since_turn));
                     moves_since_turn = 0;
                 }
-                movements.push(',');
                 movements.push(robot_direction.instruction_for_turning_to(new_direction));
                 robot_direction = new_direction;
             }
@@ -203,66 +253,118 @@ pub fn part2(input_string: &str) -> String {
         }
     }
 
-    movements.push(',');
-    for length_of_a in 1..20 {
-        let substring_a = &movements[0..length_of_a];
-        let remaining_string = movements.replace(substring_a, "");
-        for length_of_b in 1..20 {
-            let substring_b = &remaining_string[0..length_of_b];
-            let remaining_string = remaining_string.replace(substring_b, "");
-            for length_of_c in 1..20 {
-                let substring_c = &remaining_string[0..length_of_c];
-                let remaining_string = remaining_string.replace(substring_c, "");
-                if remaining_string.is_empty() {
-                    let function_a = &substring_a[0..substring_a.len() - 1];
-                    let function_b = &substring_b[0..substring_b.len() - 1];
-                    let function_c = &substring_c[0..substring_c.len() - 1];
-                    let main_routine = movements
-                        .replace(function_a, "A")
-                        .replace(function_b, "B")
-                        .replace(function_c, "C");
-                    let main_routine = &main_routine[0..main_routine.len() - 1];
-
-                    return vec![main_routine, function_a, function_b, function_c, "n"]
-                        .iter_mut()
-                        .map(|input| {
-                            program.input_string(input);
-                            program.input_string("\n");
-                            program.run_for_output()
-                        })
-                        .last()
-                        .unwrap()
-                        .iter()
-                        .find(|&&value| value > 255)
-                        .unwrap()
-                        .to_string();
-                }
-            }
-        }
-    }
+    let (routine, functions) = solve_routines(&movements).expect("No valid movement routine");
+    let inputs = [
+        routine_to_ascii(&routine),
+        moves_to_ascii(&functions[0]),
+        moves_to_ascii(&functions[1]),
+        moves_to_ascii(&functions[2]),
+        "n".to_string(),
+    ];
 
-    String::from("")
+    inputs
+        .iter()
+        .map(|input| {
+            program.input_string(input);
+            program.input_string("\n");
+            program.run_for_output()
+        })
+        .last()
+        .unwrap()
+        .iter()
+        .find(|&&value| value > 255)
+        .unwrap()
+        .to_string()
 }
 
-#[test]
-pub fn tests_part1() {
-    assert_eq!(
-        part1_map(
-            "..#..........
+#[cfg(test)]
+const SAMPLE_SCAFFOLD: &str = "..#..........
 ..#..........
 #######...###
 #.#...#...#.#
 #############
 ..#...#...#..
-..#####...^..",
-        ),
-        "76"
-    );
+..#####...^..";
 
+#[test]
+pub fn tests_part1() {
     assert_eq!(part1(include_str!("day17_input.txt")), "11140");
 }
 
+#[test]
+fn tests_alignment_sum() {
+    let map = Grid::parse(SAMPLE_SCAFFOLD);
+    assert_eq!(intersections(&map), vec![(2, 2), (2, 4), (6, 4), (10, 4)]);
+    assert_eq!(alignment_sum_of_map(&map), 76);
+
+    assert_eq!(alignment_sum(include_str!("day17_input.txt")), 11140);
+}
+
+#[test]
+fn tests_parse_scaffold() {
+    let output: Vec<i64> = format!("{}\n\nMain:\n", SAMPLE_SCAFFOLD)
+        .bytes()
+        .map(i64::from)
+        .collect();
+    let map = parse_scaffold(&output);
+    assert_eq!(map, Grid::parse(SAMPLE_SCAFFOLD));
+    assert_eq!((map.width(), map.height()), (13, 7));
+    assert_eq!(map[(10, 6)], '^');
+}
+
 #[test]
 fn tests_part2() {
     assert_eq!(part2(include_str!("day17_input.txt")), "1113108");
 }
+
+#[test]
+fn tests_solve_routines() {
+    use Move::*;
+
+    let path = vec![
+        Right,
+        Forward(8),
+        Right,
+        Forward(8),
+        Right,
+        Forward(4),
+        Right,
+        Forward(4),
+        Right,
+        Forward(8),
+        Left,
+        Forward(6),
+        Left,
+        Forward(2),
+        Right,
+        Forward(4),
+        Right,
+        Forward(4),
+        Right,
+        Forward(8),
+        Right,
+        Forward(8),
+        Right,
+        Forward(8),
+        Left,
+        Forward(6),
+        Left,
+        Forward(2),
+    ];
+    let (routine, functions) = solve_routines(&path).unwrap();
+    assert!(routine_to_ascii(&routine).len() <= 20);
+    assert!(functions
+        .iter()
+        .all(|function| moves_to_ascii(function).len() <= 20));
+    let expanded: Vec<Move> = routine
+        .iter()
+        .flat_map(|&call| functions[(call as u8 - b'A') as usize].clone())
+        .collect();
+    assert_eq!(expanded, path);
+
+    // Without any repetition the path cannot be compressed:
+    let path: Vec<Move> = (1..=20)
+        .flat_map(|steps| vec![Right, Forward(steps)])
+        .collect();
+    assert_eq!(solve_routines(&path), None);
+}
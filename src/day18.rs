@@ -1,70 +1,609 @@
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use crate::grid::Grid;
+use crate::parse;
+use crate::pathfinding::{dijkstra_with, DijkstraOptions};
+use crate::point::Point;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io;
 
-const DIRECTIONS: &[(i32, i32); 4] = &[(0, 1), (0, -1), (-1, 0), (1, 0)];
+/// The characters which may be used as keys, with the door for a key being its upper case
+/// version. The 26 ASCII letters come first, followed by lower case greek letters.
+const KEY_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyzαβγδεζηθικλμνξοπρστυφχψω";
 
-#[derive(Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
-struct Key {
+/// Start of the unicode private use area, used for representing entrances as keys.
+const FIRST_ENTRANCE_VALUE: u32 = 0xE000;
+
+/// A key, or an entrance where a robot starts.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+pub struct Key {
     value: char,
 }
 
-/// Keys represented as a bit mask where bit 0 is set for 'a', bit 1 is set for 'b' and so on.
-type KeyBitset = u32;
+/// Keys represented as a bit mask where bit 0 is set for 'a', bit 1 is set for 'b' and so on,
+/// following the order of keys in `KEY_ALPHABET`.
+pub type KeyBitset = u64;
 
 impl Key {
     fn new(value: char) -> Key {
         Key { value }
     }
 
-    fn bit_mask(&self) -> KeyBitset {
-        1 << (self.value as usize - 'a' as usize)
+    /// The character of the key as it appears in the map, or '@' for an entrance.
+    pub fn value(self) -> char {
+        if self.is_entrance() {
+            '@'
+        } else {
+            self.value
+        }
+    }
+
+    /// The key with the given bit index in a `KeyBitset`.
+    fn from_index(index: u32) -> Key {
+        Key::new(KEY_ALPHABET.chars().nth(index as usize).unwrap())
+    }
+
+    /// The key opened by the given door, if it is a door.
+    fn for_door(door: char) -> Option<Key> {
+        if !door.is_uppercase() {
+            return None;
+        }
+        let mut lower_case = door.to_lowercase();
+        match (lower_case.next(), lower_case.next()) {
+            (Some(key), None) if Key::index_of(key).is_some() => Some(Key::new(key)),
+            _ => None,
+        }
+    }
+
+    fn index_of(value: char) -> Option<u32> {
+        if value.is_ascii_lowercase() {
+            Some(value as u32 - 'a' as u32)
+        } else {
+            KEY_ALPHABET
+                .chars()
+                .position(|c| c == value)
+                .map(|index| index as u32)
+        }
+    }
+
+    /// The key for the entrance with the given index, in reading order.
+    fn entrance(index: usize) -> Key {
+        Key::new(std::char::from_u32(FIRST_ENTRANCE_VALUE + index as u32).unwrap())
+    }
+
+    pub fn is_entrance(self) -> bool {
+        self.value as u32 >= FIRST_ENTRANCE_VALUE && Key::index_of(self.value).is_none()
+    }
+
+    pub fn bit_mask(&self) -> KeyBitset {
+        1 << Key::index_of(self.value).expect("Not a key")
     }
 }
 
 /// Path between keys (or from starting position to a key).
-struct Edge {
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edge {
     /// The key at the other end.
-    target_key: Key,
+    pub target_key: Key,
     /// Required steps to reach the target key.
-    steps: usize,
+    pub steps: usize,
     /// The keys needed to traverse this path.
-    needed_keys: KeyBitset,
+    pub needed_keys: KeyBitset,
+}
+
+/// The keys and entrances of a maze together with the shortest paths between them.
+pub struct KeyGraph {
+    /// The entrances, in reading order.
+    pub entrances: Vec<Key>,
+    /// The position of each key and entrance.
+    pub positions: HashMap<Key, (i32, i32)>,
+    /// The paths from each key and entrance to the keys reachable from it.
+    pub edges: HashMap<Key, Vec<Edge>>,
+    /// All keys in the maze.
+    pub all_keys: KeyBitset,
+}
+
+/// A state popped during the search: (key at, keys gathered, total steps).
+pub type TraceEntry = (char, Vec<char>, usize);
+
+/// Optional behaviour and instrumentation of the search.
+#[derive(Default)]
+struct SearchOptions<'a> {
+    /// If A* should be used, with the longest distance to any key not yet gathered as
+    /// a lower bound of the remaining steps, instead of plain Dijkstra.
+    use_heuristic: bool,
+    /// Where to record popped states, together with the maximum number of entries to record.
+    trace: Option<(&'a mut Vec<TraceEntry>, usize)>,
+    /// Where to record the size of the search.
+    stats: Option<&'a mut SolveStats>,
+}
+
+/// Measurements of the size of a search.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SolveStats {
+    /// The number of vertices popped from the priority queue.
+    pub vertices_popped: usize,
+    /// The largest number of vertices in the priority queue at any time.
+    pub peak_heap_size: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MazeError {
+    /// There is no entrance ('@') in the maze.
+    NoEntrance,
+    /// There is more than one entrance in a maze where a single robot is expected.
+    MultipleEntrances { found: usize },
+    /// The rows of the maze, ignoring indentation, are not all of the same width.
+    NotRectangular {
+        row: usize,
+        width: usize,
+        expected_width: usize,
+    },
+    /// A character which is not a wall, open passage, entrance, key or door.
+    InvalidCharacter { x: usize, y: usize, character: char },
+    /// A door which can be reached from an entrance, but whose key is not in the maze.
+    MissingKey { door: char },
+    /// The entrance at the given position can not be replaced by four entrances, each
+    /// in a separate quadrant of the maze.
+    NotQuarterable { x: usize, y: usize },
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MazeError::NoEntrance => write!(f, "No entrance in maze"),
+            MazeError::MultipleEntrances { found } => {
+                write!(f, "Expected a single entrance, found {}", found)
+            }
+            MazeError::NotRectangular {
+                row,
+                width,
+                expected_width,
+            } => write!(
+                f,
+                "Row {} has width {}, expected {}",
+                row, width, expected_width
+            ),
+            MazeError::InvalidCharacter { x, y, character } => {
+                write!(f, "Invalid map entry at ({}, {}): '{}'", x, y, character)
+            }
+            MazeError::MissingKey { door } => write!(f, "No key for reachable door '{}'", door),
+            MazeError::NotQuarterable { x, y } => {
+                write!(f, "Not possible to split maze at entrance ({}, {})", x, y)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolveError {
+    /// The map is not a valid maze.
+    InvalidMap(MazeError),
+    /// Not all keys can be gathered, with the keys which are never reached.
+    Unreachable { remaining_keys: Vec<char> },
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::InvalidMap(error) => write!(f, "{}", error),
+            SolveError::Unreachable { remaining_keys } => write!(
+                f,
+                "Not possible to gather all keys - unreachable: {}",
+                remaining_keys.iter().collect::<String>()
+            ),
+        }
+    }
 }
 
 pub fn part1(input_string: &str) -> String {
-    steps_to_gather_all_keys(input_string).to_string()
+    steps_to_gather_all_keys(input_string)
+        .unwrap_or_else(|error| panic!("{}", error))
+        .to_string()
 }
 
-pub fn steps_to_gather_all_keys(input_string: &str) -> usize {
-    let mut map: HashMap<(i32, i32), char> = HashMap::new();
-    let mut found_keys = HashMap::new();
-    let mut all_keys_bitset = 0 as KeyBitset;
+pub fn steps_to_gather_all_keys(input_string: &str) -> Result<usize, SolveError> {
+    let graph = parse_maze(input_string)?;
+    steps_with_single_entrance(&graph)
+}
 
-    input_string.lines().enumerate().for_each(|(y, line)| {
-        line.chars().enumerate().for_each(|(x, c)| {
-            let current_position = (x as i32, y as i32);
-            let char_to_insert = match c {
-                '@' => {
-                    // The single entrance.
-                    found_keys.insert(Key::new('@'), current_position);
-                    '.'
+fn steps_with_single_entrance(graph: &KeyGraph) -> Result<usize, SolveError> {
+    if graph.entrances.len() != 1 {
+        return Err(SolveError::InvalidMap(MazeError::MultipleEntrances {
+            found: graph.entrances.len(),
+        }));
+    }
+    shortest_path(graph, SearchOptions::default()).map(|(steps, _)| steps)
+}
+
+/// Validate the maze and build its key graph.
+fn parse_maze(input_string: &str) -> Result<KeyGraph, SolveError> {
+    validate_maze(input_string).map_err(SolveError::InvalidMap)?;
+    build_key_graph(input_string).map_err(SolveError::InvalidMap)
+}
+
+/// Check that a maze is well formed, which `build_key_graph` does not fully check.
+pub fn validate_maze(input_string: &str) -> Result<(), MazeError> {
+    let rows: Vec<Vec<char>> = parse::normalize(input_string)
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+
+    let mut expected_width = None;
+    let mut entrances = Vec::new();
+    let mut keys = HashSet::new();
+    for (y, row) in rows.iter().enumerate() {
+        let row_width = row.len();
+        let width = *expected_width.get_or_insert(row_width);
+        if row_width != width {
+            return Err(MazeError::NotRectangular {
+                row: y,
+                width: row_width,
+                expected_width: width,
+            });
+        }
+
+        for (x, &c) in row.iter().enumerate() {
+            match c {
+                '#' | '.' => {}
+                _ if c.is_whitespace() => {}
+                '@' => entrances.push((x, y)),
+                _ if Key::index_of(c).is_some() => {
+                    keys.insert(Key::new(c));
+                }
+                _ if Key::for_door(c).is_some() => {}
+                _ => {
+                    return Err(MazeError::InvalidCharacter { x, y, character: c });
+                }
+            }
+        }
+    }
+
+    if entrances.is_empty() {
+        return Err(MazeError::NoEntrance);
+    }
+
+    // Search from the entrances, passing through all doors, for doors without a key:
+    let mut visited_positions: HashSet<(usize, usize)> = entrances.iter().cloned().collect();
+    let mut to_visit: VecDeque<(usize, usize)> = entrances.into_iter().collect();
+    while let Some((x, y)) = to_visit.pop_front() {
+        for Point { x: new_x, y: new_y } in Point::new(x as i32, y as i32).neighbors4() {
+            if new_x < 0 || new_y < 0 {
+                continue;
+            }
+            let new_position = (new_x as usize, new_y as usize);
+            match rows
+                .get(new_position.1)
+                .and_then(|row| row.get(new_position.0))
+            {
+                None | Some('#') => {
+                    continue;
                 }
-                'a'..='z' => {
-                    // A key.
-                    let found_key = Key::new(c);
-                    all_keys_bitset |= found_key.bit_mask();
-                    found_keys.insert(found_key, current_position);
-                    c
+                Some(c) if c.is_whitespace() => {
+                    continue;
                 }
-                '#' => {
-                    // Stone wall.
-                    return;
+                Some(&c) => {
+                    if let Some(needed_key) = Key::for_door(c) {
+                        if !keys.contains(&needed_key) {
+                            return Err(MazeError::MissingKey { door: c });
+                        }
+                    }
                 }
-                _ => c,
+            }
+            if visited_positions.insert(new_position) {
+                to_visit.push_back(new_position);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The steps needed to gather all keys with one robot starting at each entrance, where
+/// only one robot moves at a time and keys gathered by one robot opens doors for all.
+pub fn steps_to_gather_all_keys_multi(input_string: &str) -> Result<usize, SolveError> {
+    let graph = parse_maze(input_string)?;
+    shortest_path(&graph, SearchOptions::default()).map(|(steps, _)| steps)
+}
+
+/// Same as `steps_to_gather_all_keys_multi`, but using A* instead of Dijkstra's algorithm,
+/// which expands fewer states.
+pub fn steps_to_gather_all_keys_a_star(input_string: &str) -> Result<usize, SolveError> {
+    let graph = parse_maze(input_string)?;
+    shortest_path(
+        &graph,
+        SearchOptions {
+            use_heuristic: true,
+            ..SearchOptions::default()
+        },
+    )
+    .map(|(steps, _)| steps)
+}
+
+/// The steps needed to gather all keys together with the order in which they are gathered.
+pub fn solve_with_path(input_string: &str) -> Result<(usize, Vec<char>), SolveError> {
+    let graph = parse_maze(input_string)?;
+    shortest_path(&graph, SearchOptions::default())
+}
+
+/// The map with the cells traversed while gathering all keys marked with '*' and the
+/// gathered keys highlighted in bold using ANSI escape sequences, leaving doors and
+/// entrances as they are.
+pub fn render_solution(input_string: &str) -> Result<String, SolveError> {
+    let mut rows = map_rows(input_string);
+    for route in solution_routes(input_string)? {
+        for (x, y) in route {
+            let cell = &mut rows[y as usize][x as usize];
+            if *cell == '.' {
+                *cell = '*';
+            }
+        }
+    }
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&c| {
+                    if Key::index_of(c).is_some() {
+                        format!("\x1b[1m{}\x1b[0m", c)
+                    } else {
+                        c.to_string()
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+fn map_rows(input_string: &str) -> Vec<Vec<char>> {
+    input_string
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect()
+}
+
+/// The positions visited by each robot while gathering all keys, starting at its entrance.
+fn solution_routes(input_string: &str) -> Result<Vec<Vec<(i32, i32)>>, SolveError> {
+    let graph = parse_maze(input_string)?;
+    let (_, keys_order) = shortest_path(&graph, SearchOptions::default())?;
+
+    let rows = map_rows(input_string);
+    let mut routes: Vec<Vec<(i32, i32)>> = graph
+        .entrances
+        .iter()
+        .map(|entrance| vec![graph.positions[entrance]])
+        .collect();
+    let mut robot_keys = graph.entrances.clone();
+    let mut gathered_keys = 0 as KeyBitset;
+    for key in keys_order.into_iter().map(Key::new) {
+        // The robot moving to the key is the one with a usable path to it:
+        let robot_index = robot_keys
+            .iter()
+            .position(|robot_key| {
+                graph
+                    .edges
+                    .get(robot_key)
+                    .into_iter()
+                    .flatten()
+                    .any(|edge| {
+                        edge.target_key == key
+                            && edge.needed_keys & gathered_keys == edge.needed_keys
+                    })
+            })
+            .expect("No robot can reach key");
+
+        let from = graph.positions[&robot_keys[robot_index]];
+        routes[robot_index].extend(grid_path(&rows, from, graph.positions[&key]));
+
+        robot_keys[robot_index] = key;
+        gathered_keys |= key.bit_mask();
+    }
+
+    Ok(routes)
+}
+
+/// The width and height of a single map cell in `render_svg`.
+const SVG_CELL_SIZE: i32 = 10;
+
+/// The SVG coordinates of the center of a map cell.
+fn svg_cell_center((x, y): (i32, i32)) -> (i32, i32) {
+    (
+        x * SVG_CELL_SIZE + SVG_CELL_SIZE / 2,
+        y * SVG_CELL_SIZE + SVG_CELL_SIZE / 2,
+    )
+}
+
+/// The maze as an SVG image, with walls, keys, doors and entrances drawn as squares (the
+/// latter three labeled with their character) and the route of each robot while gathering
+/// all keys drawn as a line.
+pub fn solution_svg(input_string: &str) -> Result<String, SolveError> {
+    let routes = solution_routes(input_string)?;
+    let rows = map_rows(input_string);
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0) as i32;
+    let height = rows.len() as i32;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        width * SVG_CELL_SIZE,
+        height * SVG_CELL_SIZE
+    );
+    for (y, row) in rows.iter().enumerate() {
+        for (x, &c) in row.iter().enumerate() {
+            let color = match c {
+                '#' => "#404040",
+                '@' => "#40C040",
+                _ if c.is_lowercase() => "#FFC000",
+                _ if c.is_uppercase() => "#A06030",
+                _ => continue,
             };
-            map.insert(current_position, char_to_insert);
-        });
-    });
+            let (center_x, center_y) = svg_cell_center((x as i32, y as i32));
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                center_x - SVG_CELL_SIZE / 2,
+                center_y - SVG_CELL_SIZE / 2,
+                SVG_CELL_SIZE,
+                SVG_CELL_SIZE,
+                color
+            ));
+            if c != '#' {
+                svg.push_str(&format!(
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" \
+                     dominant-baseline=\"central\">{}</text>\n",
+                    center_x, center_y, SVG_CELL_SIZE, c
+                ));
+            }
+        }
+    }
+    for route in routes {
+        let points: Vec<String> = route
+            .into_iter()
+            .map(|cell| {
+                let (x, y) = svg_cell_center(cell);
+                format!("{},{}", x, y)
+            })
+            .collect();
+        svg.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"red\" stroke-width=\"2\"/>\n",
+            points.join(" ")
+        ));
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+/// Write the maze and the route gathering all keys as an SVG image, as in `solution_svg`.
+pub fn render_svg(input_string: &str, path: &str) -> io::Result<()> {
+    let svg = solution_svg(input_string)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+    std::fs::write(path, svg)
+}
+
+/// The positions along a shortest path through everything but walls, excluding `from`.
+fn grid_path(rows: &[Vec<char>], from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    // From position to the position it was first reached from:
+    let mut came_from = HashMap::new();
+    let mut to_visit = VecDeque::new();
+    to_visit.push_back(from);
+
+    while let Some(position) = to_visit.pop_front() {
+        if position == to {
+            break;
+        }
+        for neighbor in Point::from(position).neighbors4() {
+            let new_position: (i32, i32) = neighbor.into();
+            if new_position.0 < 0 || new_position.1 < 0 || new_position == from {
+                continue;
+            }
+            match rows
+                .get(new_position.1 as usize)
+                .and_then(|row| row.get(new_position.0 as usize))
+            {
+                None | Some('#') => {}
+                Some(c) if c.is_whitespace() => {}
+                Some(_) => {
+                    if let Entry::Vacant(entry) = came_from.entry(new_position) {
+                        entry.insert(position);
+                        to_visit.push_back(new_position);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut path = Vec::new();
+    let mut position = to;
+    while position != from {
+        path.push(position);
+        position = came_from[&position];
+    }
+    path.reverse();
+    path
+}
+
+/// The steps needed to gather all keys together with measurements of the search.
+pub fn solve_with_stats(input_string: &str) -> Result<(usize, SolveStats), SolveError> {
+    let graph = parse_maze(input_string)?;
+    let mut stats = SolveStats::default();
+    let (steps, _) = shortest_path(
+        &graph,
+        SearchOptions {
+            stats: Some(&mut stats),
+            ..SearchOptions::default()
+        },
+    )?;
+    Ok((steps, stats))
+}
+
+/// Record the states visited by the search in the order they are popped from the
+/// priority queue, recording at most `max_trace_length` entries.
+///
+/// The key recorded is the one last reached by a robot, or '@' for the initial state.
+/// The trace of a search which does not find all keys is returned as well.
+pub fn search_trace(
+    input_string: &str,
+    max_trace_length: usize,
+) -> Result<Vec<TraceEntry>, SolveError> {
+    let graph = parse_maze(input_string)?;
+    let mut trace = Vec::new();
+    let _ = shortest_path(
+        &graph,
+        SearchOptions {
+            trace: Some((&mut trace, max_trace_length)),
+            ..SearchOptions::default()
+        },
+    );
+    Ok(trace)
+}
+
+fn keys_in_bitset(bitset: KeyBitset) -> Vec<char> {
+    KEY_ALPHABET
+        .chars()
+        .enumerate()
+        .filter(|&(index, _)| bitset & (1 << index) != 0)
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Build the graph of shortest paths between the keys and entrances of a maze.
+pub fn build_key_graph(input_string: &str) -> Result<KeyGraph, MazeError> {
+    let map = Grid::parse(input_string);
+    let mut found_keys = HashMap::new();
+    let mut entrances = Vec::new();
+    let mut all_keys_bitset = 0 as KeyBitset;
+
+    for current_position in map.iter_coords() {
+        let c = map[current_position];
+        match c {
+            '@' => {
+                // An entrance.
+                let entrance = Key::entrance(entrances.len());
+                entrances.push(entrance);
+                found_keys.insert(entrance, current_position);
+            }
+            '#' | '.' => {
+                // Stone wall or open passage.
+            }
+            _ if c.is_whitespace() => {
+                // Indentation outside the walls.
+            }
+            _ if Key::index_of(c).is_some() => {
+                // A key.
+                let found_key = Key::new(c);
+                all_keys_bitset |= found_key.bit_mask();
+                found_keys.insert(found_key, current_position);
+            }
+            _ if Key::for_door(c).is_some() => {}
+            _ => {
+                return Err(MazeError::InvalidCharacter {
+                    x: current_position.0 as usize,
+                    y: current_position.1 as usize,
+                    character: c,
+                });
+            }
+        }
+    }
 
     // Mapping to (other_key, needed_keys_to_reach, steps):
     let mut adjacency_list: HashMap<Key, Vec<Edge>> = HashMap::new();
@@ -74,38 +613,36 @@ pub fn steps_to_gather_all_keys(input_string: &str) -> usize {
 
         // (position, bitset_of_needed_keys, steps):
         let mut to_visit = VecDeque::new();
-        to_visit.push_back((this_key_position, 0u32, 0u32));
+        to_visit.push_back((this_key_position, 0 as KeyBitset, 0u32));
 
         let mut visited_positions = HashSet::new();
         visited_positions.insert(this_key_position);
 
         while let Some((position, needed_keys, steps)) = to_visit.pop_front() {
-            'key_direction_loop: for direction in DIRECTIONS.iter() {
-                let new_position = (position.0 + direction.0, position.1 + direction.1);
+            'key_direction_loop: for new_position in map.neighbors4(position) {
                 let mut new_needed_keys = needed_keys;
                 let mut found_key = None;
 
-                match map.get(&new_position) {
-                    Some(&char_at_position @ 'A'..='Z') => {
-                        let needed_key = Key::new(char_at_position.to_ascii_lowercase());
-                        if found_keys.contains_key(&needed_key) {
-                            // Only consider door as necessary if key is in quadrant.
-                            // Needed by part 2, where we can wait until key is picked
-                            // up in other quadrant.
-                            new_needed_keys |= needed_key.bit_mask();
-                        }
-                    }
-                    Some(&char_at_position @ 'a'..='z') => {
-                        found_key = Some(Key::new(char_at_position));
-                    }
-                    Some('.') => {
+                match map[new_position] {
+                    '.' | '@' => {
                         // Free to enter.
                     }
-                    None => {
+                    char_at_position
+                        if char_at_position == '#' || char_at_position.is_whitespace() =>
+                    {
                         continue 'key_direction_loop;
                     }
-                    Some(c) => {
-                        panic!("Invalid map entry: {}", c);
+                    char_at_position => {
+                        if let Some(needed_key) = Key::for_door(char_at_position) {
+                            if found_keys.contains_key(&needed_key) {
+                                // Only consider door as necessary if key is in quadrant.
+                                // Needed by part 2, where we can wait until key is picked
+                                // up in other quadrant.
+                                new_needed_keys |= needed_key.bit_mask();
+                            }
+                        } else {
+                            found_key = Some(Key::new(char_at_position));
+                        }
                     }
                 }
 
@@ -129,130 +666,334 @@ pub fn steps_to_gather_all_keys(input_string: &str) -> usize {
         }
     }
 
-    shortest_path(&adjacency_list, all_keys_bitset).expect("Not possible to gather all keys")
+    Ok(KeyGraph {
+        entrances,
+        positions: found_keys,
+        edges: adjacency_list,
+        all_keys: all_keys_bitset,
+    })
 }
 
-fn shortest_path(adjacency_list: &HashMap<Key, Vec<Edge>>, all_keys: KeyBitset) -> Option<usize> {
-    #[derive(Copy, Clone, Eq, PartialEq)]
-    struct Vertex {
-        at_key: Key,
-        steps: usize,
-        gathered_keys: KeyBitset,
-    }
-
-    impl Ord for Vertex {
-        fn cmp(&self, other: &Vertex) -> Ordering {
-            other
-                .steps
-                .cmp(&self.steps)
-                .then_with(|| self.gathered_keys.cmp(&other.gathered_keys))
-                .then_with(|| self.at_key.cmp(&other.at_key))
-        }
-    }
+/// The fewest steps between each pair of keys, and from each entrance to each key, ignoring
+/// doors. Pairs where the target key can not be reached at all are left out.
+pub fn key_distances(graph: &KeyGraph) -> HashMap<(Key, Key), usize> {
+    // The edges are found by searching through doors and keys, so are shortest paths:
+    graph
+        .edges
+        .iter()
+        .flat_map(|(&from_key, edges)| {
+            edges
+                .iter()
+                .map(move |edge| ((from_key, edge.target_key), edge.steps))
+        })
+        .collect()
+}
 
-    impl PartialOrd for Vertex {
-        fn partial_cmp(&self, other: &Vertex) -> Option<Ordering> {
-            Some(self.cmp(other))
-        }
-    }
+fn shortest_path(
+    graph: &KeyGraph,
+    options: SearchOptions,
+) -> Result<(usize, Vec<char>), SolveError> {
+    // A search state: (robot_keys, gathered_keys), with the key (or entrance) where each robot is.
+    type State = (Vec<Key>, KeyBitset);
 
-    // From (key, gathered_keys) to total steps required to reach there.
-    let mut cost_for_keys: HashMap<(Key, KeyBitset), usize> = HashMap::new();
-    let mut to_visit = BinaryHeap::new();
+    let SearchOptions {
+        use_heuristic,
+        mut trace,
+        mut stats,
+    } = options;
 
-    to_visit.push(Vertex {
-        at_key: Key::new('@'),
-        steps: 0,
-        gathered_keys: 0,
-    });
+    let distances = if use_heuristic {
+        key_distances(graph)
+    } else {
+        HashMap::new()
+    };
 
-    while let Some(current) = to_visit.pop() {
-        if current.gathered_keys == all_keys {
-            return Some(current.steps);
+    // A lower bound of the remaining steps: The longest distance to a key not yet gathered,
+    // from the robot closest to it.
+    let remaining_steps_bound = |(robot_keys, gathered_keys): &State| -> usize {
+        let mut remaining_keys = graph.all_keys & !gathered_keys;
+        let mut bound = 0;
+        while remaining_keys != 0 {
+            let key = Key::from_index(remaining_keys.trailing_zeros());
+            remaining_keys &= remaining_keys - 1;
+            if let Some(distance) = robot_keys
+                .iter()
+                .filter_map(|&robot_key| distances.get(&(robot_key, key)))
+                .min()
+            {
+                bound = std::cmp::max(bound, *distance);
+            }
         }
+        bound
+    };
 
-        for edge in adjacency_list.get(&current.at_key).unwrap() {
-            let all_needed_keys_gathered =
-                edge.needed_keys & current.gathered_keys == edge.needed_keys;
-            if !all_needed_keys_gathered {
-                continue;
+    let next_states = |(robot_keys, gathered_keys): &State| {
+        let mut next_states = Vec::new();
+        for (robot_index, robot_key) in robot_keys.iter().enumerate() {
+            for edge in graph.edges.get(robot_key).into_iter().flatten() {
+                let all_needed_keys_gathered = edge.needed_keys & gathered_keys == edge.needed_keys;
+                if !all_needed_keys_gathered || gathered_keys & edge.target_key.bit_mask() != 0 {
+                    continue;
+                }
+
+                let mut next_robot_keys = robot_keys.clone();
+                next_robot_keys[robot_index] = edge.target_key;
+                let next_gathered_keys = gathered_keys | edge.target_key.bit_mask();
+                next_states.push(((next_robot_keys, next_gathered_keys), edge.steps));
             }
+        }
+        next_states
+    };
 
-            let next = Vertex {
-                steps: current.steps + edge.steps,
-                at_key: edge.target_key,
-                gathered_keys: current.gathered_keys | edge.target_key.bit_mask(),
-            };
+    // Every reachable state is popped when the search is exhausted without gathering all keys.
+    let mut reached_keys = 0;
+    let mut on_pop = |state: &State, previous: Option<&State>, steps: usize, queued: usize| {
+        reached_keys |= state.1;
 
-            let current_cost = cost_for_keys
-                .entry((edge.target_key, next.gathered_keys))
-                .or_insert(usize::max_value());
+        if let Some(stats) = &mut stats {
+            stats.vertices_popped += 1;
+            stats.peak_heap_size = std::cmp::max(stats.peak_heap_size, queued + 1);
+        }
 
-            if next.steps < *current_cost {
-                to_visit.push(next);
-                *current_cost = next.steps;
+        if let Some((trace, max_trace_length)) = &mut trace {
+            if trace.len() < *max_trace_length {
+                // The key last reached is where the robot that moved last is:
+                let at_key = match previous {
+                    Some(previous) => state
+                        .0
+                        .iter()
+                        .zip(previous.0.iter())
+                        .find(|(robot_key, previous_key)| robot_key != previous_key)
+                        .map(|(&robot_key, _)| robot_key)
+                        .unwrap_or(state.0[0]),
+                    None => state.0[0],
+                };
+                trace.push((at_key.value(), keys_in_bitset(state.1), steps));
             }
         }
-    }
+    };
+
+    let result = dijkstra_with(
+        (graph.entrances.clone(), 0),
+        next_states,
+        |state| state.1 == graph.all_keys,
+        DijkstraOptions {
+            heuristic: if use_heuristic {
+                Some(&remaining_steps_bound)
+            } else {
+                None
+            },
+            on_pop: Some(&mut on_pop),
+        },
+    );
 
-    None
+    match result {
+        Some((steps, path)) => {
+            let gathered_keys_order = path
+                .windows(2)
+                .flat_map(|states| keys_in_bitset(states[1].1 & !states[0].1))
+                .collect();
+            Ok((steps, gathered_keys_order))
+        }
+        None => Err(SolveError::Unreachable {
+            remaining_keys: keys_in_bitset(graph.all_keys & !reached_keys),
+        }),
+    }
 }
 
 pub fn part2(input_string: &str) -> String {
-    let mut map_top_left = String::new();
-    let mut map_top_right = String::new();
-    let mut map_bottom_left = String::new();
-    let mut map_bottom_right = String::new();
+    steps_in_quadrants_parallel(input_string)
+        .unwrap_or_else(|error| panic!("{}", error))
+        .to_string()
+}
+
+/// The position of the single entrance, which needs open cells around it and walls on
+/// the rest of its row and column for the maze to be split into quadrants.
+fn quadrants_center(input_string: &str) -> Result<(usize, usize), MazeError> {
+    let rows: Vec<Vec<char>> = input_string
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+    let entrances: Vec<(usize, usize)> = rows
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|&(_, &c)| c == '@')
+                .map(move |(x, _)| (x, y))
+        })
+        .collect();
+    let (center_x, center_y) = match entrances.len() {
+        0 => return Err(MazeError::NoEntrance),
+        1 => entrances[0],
+        found => return Err(MazeError::MultipleEntrances { found }),
+    };
 
-    let num_rows = input_string.lines().count();
-    let num_columns = input_string.lines().next().unwrap().len();
-    let center_y = num_rows / 2;
-    let center_x = num_columns / 2;
+    let is_wall = |c: char| c == '#' || c.is_whitespace();
+    let near_center = |a: usize, b: usize| (a as i32 - b as i32).abs() <= 1;
+    let open_around_center = center_x > 0
+        && center_y > 0
+        && (center_y - 1..=center_y + 1).all(|y| {
+            (center_x - 1..=center_x + 1).all(|x| {
+                rows.get(y)
+                    .and_then(|row| row.get(x))
+                    .is_some_and(|&c| c == '.' || c == '@')
+            })
+        });
+    let walled_row = rows[center_y]
+        .iter()
+        .enumerate()
+        .all(|(x, &c)| near_center(x, center_x) || is_wall(c));
+    let walled_column = rows
+        .iter()
+        .enumerate()
+        .all(|(y, row)| near_center(y, center_y) || row.get(center_x).is_none_or(|&c| is_wall(c)));
 
+    if open_around_center && walled_row && walled_column {
+        Ok((center_x, center_y))
+    } else {
+        Err(MazeError::NotQuarterable {
+            x: center_x,
+            y: center_y,
+        })
+    }
+}
+
+/// Replace the single entrance with four separate entrances.
+fn with_four_entrances(input_string: &str) -> Result<String, MazeError> {
+    let (center_x, center_y) = quadrants_center(input_string)?;
+
+    let mut updated_map = String::new();
     input_string.lines().enumerate().for_each(|(y, line)| {
         line.chars().enumerate().for_each(|(x, c)| {
-            let replaced_char = match (center_x as i32 - x as i32, center_y as i32 - y as i32) {
-                (0, 0) | (1, 0) | (-1, 0) | (0, 1) | (0, -1) => '#',
-                (1, 1) | (1, -1) | (-1, 1) | (-1, -1) => '@',
-                _ => c,
-            };
-
-            if y <= center_y {
-                if x <= center_x {
-                    &mut map_top_left
-                } else {
-                    &mut map_top_right
-                }
-            } else if x <= center_x {
-                &mut map_bottom_left
-            } else {
-                &mut map_bottom_right
-            }
-            .push(replaced_char);
+            updated_map.push(
+                match (center_x as i32 - x as i32, center_y as i32 - y as i32) {
+                    (0, 0) | (1, 0) | (-1, 0) | (0, 1) | (0, -1) => '#',
+                    (1, 1) | (1, -1) | (-1, 1) | (-1, -1) => '@',
+                    _ => c,
+                },
+            );
         });
-        if y <= center_y {
-            map_top_left.push('\n');
-            map_top_right.push('\n');
-        } else {
-            map_bottom_left.push('\n');
-            map_bottom_right.push('\n');
-        }
+        updated_map.push('\n');
     });
+    Ok(updated_map)
+}
+
+/// Split the map into one map for each quadrant around the entrance, where everything
+/// outside the quadrant is walled off.
+fn split_into_quadrants(input_string: &str) -> Result<Vec<String>, MazeError> {
+    let (center_x, center_y) = quadrants_center(input_string)?;
+    let updated_map = with_four_entrances(input_string)?;
+
+    Ok([(false, false), (true, false), (false, true), (true, true)]
+        .iter()
+        .map(|&(right, bottom)| {
+            let mut quadrant_map = String::new();
+            updated_map.lines().enumerate().for_each(|(y, line)| {
+                line.chars().enumerate().for_each(|(x, c)| {
+                    let in_quadrant = (x > center_x) == right
+                        && (y > center_y) == bottom
+                        && x != center_x
+                        && y != center_y;
+                    quadrant_map.push(if in_quadrant { c } else { '#' });
+                });
+                quadrant_map.push('\n');
+            });
+            quadrant_map
+        })
+        .collect())
+}
 
-    let result = steps_to_gather_all_keys(&map_top_left)
-        + steps_to_gather_all_keys(&map_top_right)
-        + steps_to_gather_all_keys(&map_bottom_left)
-        + steps_to_gather_all_keys(&map_bottom_right);
-    result.to_string()
+/// The steps needed by the four robots of part 2, solving each quadrant independently
+/// and in sequence. Doors whose keys are in other quadrants are considered open.
+pub fn steps_in_quadrants(input_string: &str) -> Result<usize, SolveError> {
+    validate_maze(input_string).map_err(SolveError::InvalidMap)?;
+    split_into_quadrants(input_string)
+        .map_err(SolveError::InvalidMap)?
+        .iter()
+        .map(|quadrant_map| steps_in_quadrant(quadrant_map))
+        .sum()
+}
+
+/// The steps needed to gather all keys in a quadrant, which is not validated by itself
+/// since keys for its doors may be in other quadrants.
+fn steps_in_quadrant(quadrant_map: &str) -> Result<usize, SolveError> {
+    let graph = build_key_graph(quadrant_map).map_err(SolveError::InvalidMap)?;
+    steps_with_single_entrance(&graph)
+}
+
+/// Same as `steps_in_quadrants`, but solving each quadrant on a separate thread.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn steps_in_quadrants_parallel(input_string: &str) -> Result<usize, SolveError> {
+    validate_maze(input_string).map_err(SolveError::InvalidMap)?;
+    split_into_quadrants(input_string)
+        .map_err(SolveError::InvalidMap)?
+        .into_iter()
+        .map(|quadrant_map| std::thread::spawn(move || steps_in_quadrant(&quadrant_map)))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|handle| handle.join().expect("Quadrant solver panicked"))
+        .sum()
+}
+
+/// Threads are not available in wasm, so solve the quadrants in sequence there.
+#[cfg(target_arch = "wasm32")]
+pub fn steps_in_quadrants_parallel(input_string: &str) -> Result<usize, SolveError> {
+    steps_in_quadrants(input_string)
+}
+
+/// Solves both parts for the same maze, building each key graph only once.
+pub struct Solver {
+    input: String,
+    key_graph: Option<KeyGraph>,
+    quadrant_graphs: Option<Vec<KeyGraph>>,
+}
+
+impl Solver {
+    pub fn new(input_string: &str) -> Solver {
+        Solver {
+            input: input_string.to_string(),
+            key_graph: None,
+            quadrant_graphs: None,
+        }
+    }
+
+    pub fn part1(&mut self) -> Result<usize, SolveError> {
+        if self.key_graph.is_none() {
+            self.key_graph = Some(parse_maze(&self.input)?);
+        }
+        steps_with_single_entrance(self.key_graph.as_ref().unwrap())
+    }
+
+    pub fn part2(&mut self) -> Result<usize, SolveError> {
+        if self.quadrant_graphs.is_none() {
+            validate_maze(&self.input).map_err(SolveError::InvalidMap)?;
+            self.quadrant_graphs = Some(
+                split_into_quadrants(&self.input)
+                    .map_err(SolveError::InvalidMap)?
+                    .iter()
+                    .map(|quadrant_map| build_key_graph(quadrant_map))
+                    .collect::<Result<Vec<KeyGraph>, MazeError>>()
+                    .map_err(SolveError::InvalidMap)?,
+            );
+        }
+        self.quadrant_graphs
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(steps_with_single_entrance)
+            .sum()
+    }
 }
 
 #[test]
 pub fn tests_part1() {
     assert_eq!(
         part1(
-            "#########
-    #b.A.@.a#
-    #########"
+            "#########\n\
+             #b.A.@.a#\n\
+             #########"
         ),
         "8"
     );
@@ -275,3 +1016,420 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day18_input.txt")), "1878");
 }
+
+#[test]
+fn tests_part2_off_center_entrance() {
+    let input = "###########
+#a.#.....b#
+#....######
+##.@.######
+#....######
+#c.#.....d#
+###########";
+    assert_eq!(part2(input), "16");
+    assert_eq!(steps_in_quadrants(input), Ok(16));
+
+    assert_eq!(
+        steps_in_quadrants("#####\n#.@.#\n#####"),
+        Err(SolveError::InvalidMap(MazeError::NotQuarterable {
+            x: 2,
+            y: 1
+        }))
+    );
+    assert_eq!(
+        steps_in_quadrants(
+            "#######
+#a....#
+#..@..#
+#...b.#
+#######"
+        ),
+        Err(SolveError::InvalidMap(MazeError::NotQuarterable {
+            x: 3,
+            y: 2
+        }))
+    );
+}
+
+#[test]
+fn tests_steps_in_quadrants_parallel() {
+    let input = include_str!("day18_input.txt");
+    assert_eq!(steps_in_quadrants_parallel(input), Ok(1878));
+    assert_eq!(
+        steps_in_quadrants_parallel(input),
+        steps_in_quadrants(input)
+    );
+    assert_eq!(
+        steps_in_quadrants(input),
+        steps_to_gather_all_keys_multi(&with_four_entrances(input).unwrap())
+    );
+}
+
+#[test]
+fn tests_solver() {
+    let input = include_str!("day18_input.txt");
+    let mut solver = Solver::new(input);
+    assert_eq!(solver.part1(), Ok(4248));
+    assert_eq!(solver.part2(), Ok(1878));
+    // Solving again uses the cached graphs:
+    assert_eq!(solver.part1(), Ok(4248));
+    assert_eq!(solver.part2(), Ok(1878));
+
+    assert_eq!(
+        Solver::new("#..a#").part1(),
+        Err(SolveError::InvalidMap(MazeError::NoEntrance))
+    );
+}
+
+#[test]
+fn tests_search_trace() {
+    let input = "#########
+#b.A.@.a#
+#########";
+    let trace = search_trace(input, 10);
+    assert_eq!(
+        trace,
+        Ok(vec![
+            ('@', vec![], 0),
+            ('a', vec!['a'], 2),
+            ('b', vec!['a', 'b'], 8)
+        ])
+    );
+
+    assert_eq!(search_trace(input, 1), Ok(vec![('@', vec![], 0)]));
+    assert!(matches!(
+        search_trace("#####\n#a.x#\n#####", 10),
+        Err(SolveError::InvalidMap(_))
+    ));
+}
+
+#[test]
+fn tests_steps_to_gather_all_keys_multi() {
+    assert_eq!(
+        steps_to_gather_all_keys_multi(
+            "###############
+#a.B.@#@.b.A.c#
+###############"
+        ),
+        Ok(10)
+    );
+
+    assert_eq!(
+        steps_to_gather_all_keys_multi(
+            "#######
+#a.#Cd#
+##@#@##
+#######
+##@#@##
+#cB#Ab#
+#######"
+        ),
+        Ok(8)
+    );
+
+    assert_eq!(
+        steps_to_gather_all_keys_multi(
+            "###############
+#d.ABC.#.....a#
+######@#@######
+###############
+######@#@######
+#b.....#.....c#
+###############"
+        ),
+        Ok(24)
+    );
+
+    assert_eq!(
+        steps_to_gather_all_keys_multi(
+            "#############
+#DcBa.#.GhKl#
+#.###@#@#I###
+#e#d#####j#k#
+###C#@#@###J#
+#fEbA.#.FgHi#
+#############"
+        ),
+        Ok(32)
+    );
+
+    assert_eq!(
+        steps_to_gather_all_keys_multi(
+            "#############
+#g#f.D#..h#l#
+#F###e#E###.#
+#dCba@#@BcIJ#
+#############
+#nK.L@#@G...#
+#M###N#H###.#
+#o#m..#i#jk.#
+#############"
+        ),
+        Ok(72)
+    );
+}
+
+#[test]
+fn tests_key_distances() {
+    let graph = build_key_graph(
+        "#########
+#b.A.@.a#
+#########",
+    )
+    .unwrap();
+    let distances = key_distances(&graph);
+    let entrance = graph.entrances[0];
+    assert_eq!(distances[&(entrance, Key::new('a'))], 2);
+    // Walking through the door, as well as past the entrance:
+    assert_eq!(distances[&(entrance, Key::new('b'))], 4);
+    assert_eq!(distances[&(Key::new('a'), Key::new('b'))], 6);
+    assert_eq!(distances[&(Key::new('b'), Key::new('a'))], 6);
+    assert_eq!(distances.get(&(Key::new('a'), Key::new('a'))), None);
+    // Entrances are only sources:
+    assert_eq!(distances.len(), 4);
+}
+
+#[test]
+fn tests_solve_with_path() {
+    let input = "########################
+#f.D.E.e.C.b.A.@.a.B.c.#
+######################.#
+#d.....................#
+########################";
+    let (steps, keys) = solve_with_path(input).unwrap();
+    assert_eq!(steps, 86);
+    assert_eq!(Ok(steps), steps_to_gather_all_keys(input));
+    assert_eq!(keys, vec!['a', 'b', 'c', 'd', 'e', 'f']);
+
+    // Replay the key order using the key graph:
+    let graph = build_key_graph(input).unwrap();
+    let mut at_key = graph.entrances[0];
+    let mut replayed_steps = 0;
+    for &key in keys.iter() {
+        let edge = graph.edges[&at_key]
+            .iter()
+            .find(|edge| edge.target_key == Key::new(key))
+            .unwrap();
+        replayed_steps += edge.steps;
+        at_key = edge.target_key;
+    }
+    assert_eq!(replayed_steps, steps);
+}
+
+#[test]
+fn tests_render_solution() {
+    let rendered = render_solution(
+        "#########
+#b.A.@.a#
+#########",
+    )
+    .unwrap();
+    assert_eq!(
+        rendered,
+        "#########
+#\x1b[1mb\x1b[0m*A*@*\x1b[1ma\x1b[0m#
+#########"
+    );
+    assert_eq!(rendered.matches('*').count(), 3);
+    assert_eq!(rendered.matches("\x1b[1m").count(), 2);
+}
+
+#[test]
+fn tests_svg_cell_center() {
+    assert_eq!(svg_cell_center((0, 0)), (5, 5));
+    assert_eq!(svg_cell_center((3, 1)), (35, 15));
+}
+
+#[test]
+fn tests_render_svg() {
+    let input = "#########
+#b.A.@.a#
+#########";
+    let svg = solution_svg(input).unwrap();
+    // 20 walls, 2 keys, 1 door and 1 entrance:
+    assert_eq!(svg.matches("<rect").count(), 24);
+    assert_eq!(svg.matches("<text").count(), 4);
+    assert!(svg.contains(">A</text>"));
+    // From the entrance to 'a' and then back past it to 'b':
+    assert!(
+        svg.contains("<polyline points=\"55,15 65,15 75,15 65,15 55,15 45,15 35,15 25,15 15,15\"")
+    );
+
+    let path = std::env::temp_dir().join("advent_of_code_day18.svg");
+    let path = path.to_str().unwrap();
+    render_svg(input, path).unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), svg);
+    std::fs::remove_file(path).unwrap();
+
+    let error = render_svg("#.#", path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn tests_solve_with_stats() {
+    let input = "########################
+#f.D.E.e.C.b.A.@.a.B.c.#
+######################.#
+#d.....................#
+########################";
+    let (steps, stats) = solve_with_stats(input).unwrap();
+    assert_eq!(Ok(steps), steps_to_gather_all_keys(input));
+    assert!(stats.vertices_popped > 0);
+    assert!(stats.peak_heap_size > 0);
+    assert_eq!(
+        stats.vertices_popped,
+        search_trace(input, usize::MAX).unwrap().len()
+    );
+}
+
+#[test]
+fn tests_a_star() {
+    let input = include_str!("day18_input.txt");
+    assert_eq!(
+        steps_to_gather_all_keys_a_star(input),
+        steps_to_gather_all_keys(input)
+    );
+
+    let graph = build_key_graph(input).unwrap();
+    let count_expanded_states = |use_heuristic| {
+        let mut trace = Vec::new();
+        let _ = shortest_path(
+            &graph,
+            SearchOptions {
+                use_heuristic,
+                trace: Some((&mut trace, usize::MAX)),
+                ..SearchOptions::default()
+            },
+        );
+        trace.len()
+    };
+    assert!(count_expanded_states(true) < count_expanded_states(false));
+}
+
+#[test]
+fn tests_extended_alphabet() {
+    // 29 keys, each behind the door opened by the previous key:
+    let input = "############################################################
+#@aAbBcCdDeEfFgGhHiIjJkKlLmMnNoOpPqQrRsStTuUvVwWxXyYzZαΑβΒγ#
+############################################################";
+    assert_eq!(steps_to_gather_all_keys(input), Ok(57));
+    assert_eq!(solve_with_path(input).unwrap().1.len(), 29);
+
+    assert_eq!(
+        build_key_graph("#@.ж#").err(),
+        Some(MazeError::InvalidCharacter {
+            x: 3,
+            y: 0,
+            character: 'ж'
+        })
+    );
+}
+
+#[test]
+fn tests_unreachable_keys() {
+    // Key b is behind door C, whose key c is behind door B:
+    let input = "##########
+#b.C@a.Bc#
+##########";
+    assert_eq!(
+        steps_to_gather_all_keys(input),
+        Err(SolveError::Unreachable {
+            remaining_keys: vec!['b', 'c']
+        })
+    );
+    assert_eq!(
+        steps_to_gather_all_keys("#@@#"),
+        Err(SolveError::InvalidMap(MazeError::MultipleEntrances {
+            found: 2
+        }))
+    );
+}
+
+#[test]
+fn tests_build_key_graph() {
+    let graph = build_key_graph(
+        "#########
+#b.A.@.a#
+#########",
+    )
+    .unwrap();
+    let entrance = graph.entrances[0];
+    let (key_a, key_b) = (Key::new('a'), Key::new('b'));
+    assert_eq!(graph.entrances.len(), 1);
+    assert_eq!(graph.all_keys, 0b11);
+    assert_eq!(graph.positions[&entrance], (5, 1));
+    assert_eq!(graph.positions[&key_a], (7, 1));
+    assert_eq!(graph.positions[&key_b], (1, 1));
+
+    let edges = |from_key: Key| {
+        let mut edges = graph.edges[&from_key].clone();
+        edges.sort_by_key(|edge| edge.target_key);
+        edges
+    };
+    let edge = |target_key: Key, steps: usize, needed_keys: KeyBitset| Edge {
+        target_key,
+        steps,
+        needed_keys,
+    };
+    assert_eq!(
+        edges(entrance),
+        vec![edge(key_a, 2, 0), edge(key_b, 4, key_a.bit_mask())]
+    );
+    assert_eq!(edges(key_a), vec![edge(key_b, 6, key_a.bit_mask())]);
+    assert_eq!(edges(key_b), vec![edge(key_a, 6, key_a.bit_mask())]);
+}
+
+#[test]
+fn tests_validate_maze() {
+    assert_eq!(validate_maze("#@.a#"), Ok(()));
+    assert_eq!(validate_maze("#..a#"), Err(MazeError::NoEntrance));
+    assert_eq!(
+        validate_maze("#####\n#@.a\n#####"),
+        Err(MazeError::NotRectangular {
+            row: 1,
+            width: 4,
+            expected_width: 5
+        })
+    );
+    assert_eq!(
+        validate_maze("#@.?#"),
+        Err(MazeError::InvalidCharacter {
+            x: 3,
+            y: 0,
+            character: '?'
+        })
+    );
+    assert_eq!(
+        validate_maze("#@.B#"),
+        Err(MazeError::MissingKey { door: 'B' })
+    );
+    // A door without a key is fine if it cannot be reached:
+    assert_eq!(validate_maze("#@.a#B#"), Ok(()));
+
+    // Trailing blank lines are ignored, while indentation is part of the rows, keeping the
+    // positions of the grid:
+    assert_eq!(validate_maze("#####\n#@.a#\n#####\n\n"), Ok(()));
+    assert_eq!(validate_maze("#####\r\n#@.a#\r\n#####\r\n"), Ok(()));
+    assert_eq!(validate_maze("  #####\n  #@.a#\n  #####"), Ok(()));
+    assert_eq!(
+        validate_maze("#@.a#\n  #####"),
+        Err(MazeError::NotRectangular {
+            row: 1,
+            width: 7,
+            expected_width: 5
+        })
+    );
+    let input = "  #####\n  #@?.#\n  #####";
+    let invalid_character = MazeError::InvalidCharacter {
+        x: 4,
+        y: 1,
+        character: '?',
+    };
+    assert_eq!(validate_maze(input), Err(invalid_character.clone()));
+    assert_eq!(build_key_graph(input).err(), Some(invalid_character));
+
+    assert_eq!(
+        steps_to_gather_all_keys("#@.B.a#"),
+        Err(SolveError::InvalidMap(MazeError::MissingKey { door: 'B' }))
+    );
+}
@@ -17,7 +17,16 @@ pub fn part1(input_string: &str) -> String {
         .to_string()
 }
 
-pub fn part2(input_string: &str) -> String {
+/// Check if the given coordinate is inside the tractor beam.
+pub fn scan(input_string: &str, x: i32, y: i32) -> bool {
+    check(&Program::parse(input_string), x, y)
+}
+
+/// Find the top left corner of the first square of the given size which fits inside the beam.
+///
+/// Instead of scanning every candidate position, this tracks the top right edge of the
+/// beam row by row until the bottom left corner of the square is inside the beam too.
+pub fn find_square(input_string: &str, size: i32) -> (i32, i32) {
     let program = Program::parse(input_string);
 
     // Find the initial start of the beam (skipping (0,0),
@@ -28,14 +37,24 @@ pub fn part2(input_string: &str) -> String {
         .unwrap();
 
     // Track the top right of the beam as long as square does not fit.
-    while !check(&program, current_x - 99, current_y + 99) {
+    while current_x < size - 1 || !check(&program, current_x - (size - 1), current_y + (size - 1)) {
         current_y += 1;
-        if check(&program, current_x + 1, current_y) {
+        while check(&program, current_x + 1, current_y) {
             current_x += 1;
         }
     }
 
-    ((current_x - 99) * 10000 + current_y).to_string()
+    (current_x - (size - 1), current_y)
+}
+
+/// The puzzle answer for a square of the given size, computed as 10000*x + y.
+pub fn square_answer(input_string: &str, size: i32) -> i32 {
+    let (x, y) = find_square(input_string, size);
+    x * 10000 + y
+}
+
+pub fn part2(input_string: &str) -> String {
+    square_answer(input_string, 100).to_string()
 }
 
 #[test]
@@ -47,3 +66,21 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day19_input.txt")), "18261982");
 }
+
+#[test]
+fn tests_find_square() {
+    let input = include_str!("day19_input.txt");
+    assert!(scan(input, 0, 0));
+    assert!(!scan(input, 1, 0));
+
+    let (x, y) = find_square(input, 100);
+    assert_eq!((x, y), (1826, 1982));
+    assert!(scan(input, x, y));
+    assert!(scan(input, x + 99, y));
+    assert!(scan(input, x, y + 99));
+    assert!(scan(input, x + 99, y + 99));
+
+    let (x, y) = find_square(input, 10);
+    assert!(scan(input, x + 9, y) && scan(input, x, y + 9));
+    assert_eq!(square_answer(input, 100), 18_261_982);
+}
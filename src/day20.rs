@@ -1,198 +1,191 @@
-use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet, VecDeque};
+use crate::grid::Grid;
+use crate::pathfinding::bfs;
+use std::collections::HashMap;
 
-const DIRECTIONS: &[(i32, i32); 4] = &[(0, 1), (0, -1), (-1, 0), (1, 0)];
+/// A portal label next to the open tile where the portal is entered and exited.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Portal {
+    pub label: String,
+    pub position: (i32, i32),
+    /// If the portal is on the outer edge of the donut rather than on the edge of the hole.
+    pub outer: bool,
+}
+
+/// Find the two letter portal labels of a maze, which are read from left to right or from
+/// top to bottom, in reading order of the open tiles next to them.
+pub fn parse_portals(input_string: &str) -> Vec<Portal> {
+    let grid = Grid::parse(input_string);
+    let is_label = |position| matches!(grid.get(position), Some(c) if c.is_ascii_uppercase());
+
+    // The bounds of the donut itself, without the surrounding labels:
+    let maze_positions: Vec<(i32, i32)> = grid
+        .iter_coords()
+        .filter(|&position| grid[position] == '#' || grid[position] == '.')
+        .collect();
+    let min_x = maze_positions.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let max_x = maze_positions.iter().map(|&(x, _)| x).max().unwrap_or(0);
+    let min_y = maze_positions.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let max_y = maze_positions.iter().map(|&(_, y)| y).max().unwrap_or(0);
+
+    let mut portals = Vec::new();
+    for &(x, y) in maze_positions
+        .iter()
+        .filter(|&&position| grid[position] == '.')
+    {
+        for &(dx, dy) in &[(0, -1), (-1, 0), (1, 0), (0, 1)] {
+            let first = (x + dx, y + dy);
+            let second = (x + 2 * dx, y + 2 * dy);
+            if is_label(first) && is_label(second) {
+                let label = if dx < 0 || dy < 0 {
+                    [grid[second], grid[first]]
+                } else {
+                    [grid[first], grid[second]]
+                };
+                portals.push(Portal {
+                    label: label.iter().collect(),
+                    position: (x, y),
+                    outer: x == min_x || x == max_x || y == min_y || y == max_y,
+                });
+            }
+        }
+    }
+    portals
+}
 
 struct Maze {
-    cols: usize,
-    array: Vec<u8>,
+    grid: Grid<char>,
+    /// From the position of a portal to the position of its pair and the change in level
+    /// when passing through it.
     portals: HashMap<(i32, i32), ((i32, i32), i32)>,
+    /// The labels of the portals, by position.
+    labels: HashMap<(i32, i32), String>,
     start_location: (i32, i32),
     end_location: (i32, i32),
 }
 
 impl Maze {
-    fn tile_at(&self, x: i32, y: i32) -> u8 {
-        if x < 0 || y < 0 {
-            return b' ';
+    fn parse(input: &str) -> Result<Maze, String> {
+        let mut portals_by_label: HashMap<String, Vec<Portal>> = HashMap::new();
+        for portal in parse_portals(input) {
+            portals_by_label
+                .entry(portal.label.clone())
+                .or_default()
+                .push(portal);
         }
-        self.array[(x as usize) + self.cols * (y as usize)]
-    }
 
-    fn set_tile(&mut self, x: usize, y: usize, tile: u8) {
-        self.array[x + self.cols * y] = tile;
-    }
-
-    fn parse(input: &str) -> Maze {
-        let rows = input.lines().filter(|&line| !line.is_empty()).count();
-        let cols = input.lines().map(|line| line.len()).max().unwrap();
-
-        let array = vec![b' '; rows * cols];
-        let mut maze = Maze {
-            cols,
-            array,
-            portals: HashMap::new(),
-            end_location: (0, 0),
-            start_location: (0, 0),
-        };
-        input.lines().enumerate().for_each(|(y, line)| {
-            line.chars().enumerate().for_each(|(x, tile)| {
-                maze.set_tile(x, y, tile as u8);
-            });
-        });
-
-        let mut current_string = String::new();
-        let mut coming_from_passage = false;
-        let mut portal_name_to_location: HashMap<String, (i32, i32)> = HashMap::new();
-
-        let mut on_tile = |x: i32, y: i32, x_direction| {
-            let tile = if x as usize == cols || y as usize == rows {
-                b' '
-            } else {
-                maze.tile_at(x, y)
-            };
-
-            if tile >= b'A' && tile <= b'Z' {
-                current_string.push(tile as char);
-            } else {
-                if current_string.len() == 2 {
-                    let portal_x = if x_direction && coming_from_passage {
-                        x - 3
-                    } else {
-                        x
-                    };
-                    let portal_y = if !x_direction && coming_from_passage {
-                        y - 3
-                    } else {
-                        y
-                    };
-
-                    let current_location: (i32, i32) = (portal_x, portal_y);
-
-                    match current_string.as_str() {
-                        "AA" => {
-                            maze.start_location = (portal_x, portal_y);
-                        }
-                        "ZZ" => {
-                            maze.end_location = (portal_x, portal_y);
-                        }
-                        _ => {}
-                    };
-
-                    match portal_name_to_location.entry(current_string.clone()) {
-                        Entry::Occupied(other_location) => {
-                            let level_difference = if current_location.0 == 2
-                                || current_location.0 == (cols - 3) as i32
-                                || current_location.1 == 2
-                                || current_location.1 == (rows - 3) as i32
-                            {
-                                -1
-                            } else {
-                                1
-                            };
-                            let other_location = *other_location.get();
-                            maze.portals
-                                .insert(current_location, (other_location, level_difference));
-                            maze.portals
-                                .insert(other_location, (current_location, -level_difference));
-                        }
-                        Entry::Vacant(vacant) => {
-                            vacant.insert(current_location);
-                        }
-                    }
-                }
-
-                coming_from_passage = tile == b'.';
-                current_string.clear();
-            }
+        let mut single_portal_at = |label: &str| match portals_by_label.remove(label) {
+            Some(ref portals) if portals.len() == 1 => Ok(portals[0].position),
+            _ => Err(format!("Expected a single {} tile", label)),
         };
+        let start_location = single_portal_at("AA")?;
+        let end_location = single_portal_at("ZZ")?;
 
-        for x in 0..=cols {
-            for y in 0..=rows {
-                on_tile(x as i32, y as i32, false);
+        let mut portals = HashMap::new();
+        let mut labels = HashMap::new();
+        for (label, pair) in portals_by_label {
+            if pair.len() != 2 {
+                return Err(format!("Portal {} does not come in a pair", label));
             }
-        }
-        for y in 0..=rows {
-            for x in 0..=cols {
-                on_tile(x as i32, y as i32, true);
+            for (from, to) in [(&pair[0], &pair[1]), (&pair[1], &pair[0])].iter() {
+                // Outer portals go up a level, inner ones go down a level:
+                let level_difference = if from.outer { -1 } else { 1 };
+                portals.insert(from.position, (to.position, level_difference));
+                labels.insert(from.position, label.clone());
             }
         }
 
-        maze
+        Ok(Maze {
+            grid: Grid::parse(input),
+            portals,
+            labels,
+            start_location,
+            end_location,
+        })
     }
 }
 
-pub fn part1(input_string: &str) -> String {
-    let maze = Maze::parse(input_string);
-
-    let mut to_visit = VecDeque::new();
-    let mut visited = HashSet::new();
-    to_visit.push_back((maze.start_location, 0));
-    visited.insert(maze.start_location);
-
-    while let Some((visiting, distance)) = to_visit.pop_front() {
-        let new_distance = distance + 1;
-
-        for new_location in DIRECTIONS
-            .iter()
-            .map(|&(dx, dy)| (visiting.0 + dx, visiting.1 + dy))
-            .chain(
-                if let Some(&(new_location, _)) = maze.portals.get(&visiting) {
-                    Some(new_location).into_iter()
-                } else {
-                    None.into_iter()
-                },
-            )
-        {
-            if maze.tile_at(new_location.0, new_location.1) == b'.' && visited.insert(new_location)
-            {
-                if new_location == maze.end_location {
-                    return new_distance.to_string();
-                }
-                to_visit.push_back((new_location, new_distance));
+/// The fewest steps from AA to ZZ, where passing a portal takes a single step.
+///
+/// In a recursive maze each portal in the hole leads to the outer edge of a copy of the maze one
+/// level down, the outer portals lead one level up and are walls at the outermost level, and
+/// ZZ must be reached at the outermost level.
+///
+/// The recursive search is limited to a depth of the number of inner portals times the number
+/// of outer portals. A path reaching a deeper level enters each level above it through an inner
+/// portal and later leaves it through an outer portal, so some (inner, outer) pair is used at
+/// two levels. The part of the path between the two levels can then be cut out, shifting the
+/// deeper part up, giving a shorter path. So a shortest path never goes deeper than the limit.
+pub fn shortest_path(input_string: &str, recursive: bool) -> Result<usize, String> {
+    shortest_path_with_route(input_string, recursive).map(|(steps, _)| steps)
+}
+
+/// Like `shortest_path`, but also returning the labels of the portals passed along the path,
+/// in the order they are passed.
+pub fn shortest_path_with_route(
+    input_string: &str,
+    recursive: bool,
+) -> Result<(usize, Vec<String>), String> {
+    let maze = Maze::parse(input_string)?;
+    let inner_portals = maze
+        .portals
+        .values()
+        .filter(|&&(_, level_difference)| level_difference > 0)
+        .count();
+    let outer_portals = maze.portals.len() - inner_portals;
+    let max_level = (inner_portals * outer_portals) as i32;
+
+    // Search over (position, level) states:
+    let neighbors = |&(position, level): &((i32, i32), i32)| {
+        let mut neighbors: Vec<((i32, i32), i32)> = maze
+            .grid
+            .neighbors4(position)
+            .filter(|&neighbor| maze.grid[neighbor] == '.')
+            .map(|neighbor| (neighbor, level))
+            .collect();
+        if let Some(&(exit, level_difference)) = maze.portals.get(&position) {
+            let new_level = if recursive {
+                level + level_difference
+            } else {
+                level
+            };
+            if new_level >= 0 && new_level <= max_level {
+                neighbors.push((exit, new_level));
             }
         }
+        neighbors
+    };
+
+    let path = bfs((maze.start_location, 0), neighbors, |&state| {
+        state == (maze.end_location, 0)
+    })
+    .ok_or_else(|| "No path found".to_string())?;
+
+    let route = path
+        .windows(2)
+        .filter_map(|states| {
+            let ((from, _), (to, _)) = (states[0], states[1]);
+            match maze.portals.get(&from) {
+                Some(&(exit, _)) if exit == to => Some(maze.labels[&from].clone()),
+                _ => None,
+            }
+        })
+        .collect();
+    Ok((path.len() - 1, route))
+}
+
+fn steps_as_string(input_string: &str, recursive: bool) -> String {
+    match shortest_path(input_string, recursive) {
+        Ok(steps) => steps.to_string(),
+        Err(error) => error,
     }
-    "No path found".to_string()
+}
+
+pub fn part1(input_string: &str) -> String {
+    steps_as_string(input_string, false)
 }
 
 pub fn part2(input_string: &str) -> String {
-    let maze = Maze::parse(input_string);
-
-    let mut to_visit = VecDeque::new();
-    let mut visited = HashSet::new();
-    // Contains ((x,y), distance, level):
-    to_visit.push_back((maze.start_location, 0, 0));
-    // Contains ((x,y), level):
-    visited.insert((maze.start_location, 0));
-
-    while let Some((visiting, distance, level)) = to_visit.pop_front() {
-        let new_distance = distance + 1;
-
-        for (new_location, level_difference) in DIRECTIONS
-            .iter()
-            .map(|&(dx, dy)| ((visiting.0 + dx, visiting.1 + dy), 0))
-            .chain(
-                if let Some(&(new_location, level_difference)) = maze.portals.get(&visiting) {
-                    Some((new_location, level_difference)).into_iter()
-                } else {
-                    None.into_iter()
-                },
-            )
-        {
-            let new_level = level + level_difference;
-            if new_level >= 0
-                && maze.tile_at(new_location.0, new_location.1) == b'.'
-                && visited.insert((new_location, new_level))
-            {
-                if new_location == maze.end_location && new_level == 0 {
-                    return new_distance.to_string();
-                }
-                to_visit.push_back((new_location, new_distance, new_level));
-            }
-        }
-    }
-    "No path found".to_string()
+    steps_as_string(input_string, true)
 }
 
 #[test]
@@ -207,3 +200,79 @@ fn tests_part2() {
     assert_eq!(part2(include_str!("day20_input.txt")), "6362");
     assert_eq!(part2(include_str!("day20_input_ray.txt")), "6492");
 }
+
+#[test]
+fn tests_parse_portals() {
+    let portals = parse_portals(include_str!("day20_example.txt"));
+    let portal = |label: &str, position, outer| Portal {
+        label: label.to_string(),
+        position,
+        outer,
+    };
+    assert_eq!(
+        portals,
+        vec![
+            portal("AA", (9, 2), true),
+            portal("BC", (9, 6), false),
+            portal("BC", (2, 8), true),
+            portal("DE", (6, 10), false),
+            portal("FG", (11, 12), false),
+            portal("DE", (2, 13), true),
+            portal("FG", (2, 15), true),
+            portal("ZZ", (13, 16), true),
+        ]
+    );
+}
+
+#[test]
+fn tests_shortest_path() {
+    assert_eq!(
+        shortest_path(include_str!("day20_example.txt"), false),
+        Ok(23)
+    );
+    assert_eq!(
+        shortest_path(include_str!("day20_example.txt"), true),
+        Ok(26)
+    );
+    assert_eq!(
+        shortest_path(include_str!("day20_example_larger.txt"), false),
+        Ok(58)
+    );
+    assert_eq!(
+        shortest_path(include_str!("day20_example_recursive.txt"), true),
+        Ok(396)
+    );
+
+    // Going deeper and deeper never leads back out to ZZ, which is detected once the depth
+    // limit is reached:
+    assert_eq!(
+        shortest_path(include_str!("day20_example_larger.txt"), true),
+        Err("No path found".to_string())
+    );
+
+    assert_eq!(
+        shortest_path("  A\n  A\n##.##\n#####", false),
+        Err("Expected a single ZZ tile".to_string())
+    );
+}
+
+#[test]
+fn tests_shortest_path_with_route() {
+    let route = |labels: &[&str]| labels.iter().map(|label| label.to_string()).collect();
+    assert_eq!(
+        shortest_path_with_route(include_str!("day20_example.txt"), false),
+        Ok((23, route(&["BC", "DE", "FG"])))
+    );
+    // Without outer portals at the outermost level the only path is the long way round:
+    assert_eq!(
+        shortest_path_with_route(include_str!("day20_example.txt"), true),
+        Ok((26, route(&[])))
+    );
+
+    let (steps, portals) =
+        shortest_path_with_route(include_str!("day20_example_recursive.txt"), true).unwrap();
+    assert_eq!(steps, 396);
+    assert_eq!(portals.len(), 32);
+    assert_eq!(portals[..5], route(&["XF", "CK", "ZH", "WB", "IC"])[..]);
+    assert_eq!(portals[29..], route(&["RE", "XQ", "FD"])[..]);
+}
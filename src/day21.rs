@@ -1,13 +1,58 @@
 use crate::int_code::Program;
 
-fn run(intcode_program_string: &str, ascii_program_string: &str) -> String {
+/// Maximum number of instructions the springdroid can remember.
+const MAX_INSTRUCTIONS: usize = 15;
+
+/// Validate springscript instructions, where `walk` determines if the instructions are
+/// for walking (with sensors A-D) or running (with sensors A-I).
+pub fn validate_springscript(instructions: &[&str], walk: bool) -> Result<(), String> {
+    if instructions.len() > MAX_INSTRUCTIONS {
+        return Err(format!(
+            "Too many instructions: {} (max {})",
+            instructions.len(),
+            MAX_INSTRUCTIONS
+        ));
+    }
+
+    let readable_registers = if walk { "ABCDTJ" } else { "ABCDEFGHITJ" };
+    for instruction in instructions {
+        let parts: Vec<&str> = instruction.split(' ').collect();
+        if parts.len() != 3 || !["AND", "OR", "NOT"].contains(&parts[0]) {
+            return Err(format!("Invalid instruction: '{}'", instruction));
+        } else if parts[1].len() != 1 || !readable_registers.contains(parts[1]) {
+            return Err(format!(
+                "Invalid first register in instruction: '{}'",
+                instruction
+            ));
+        } else if parts[2] != "T" && parts[2] != "J" {
+            return Err(format!(
+                "Invalid second register in instruction: '{}'",
+                instruction
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the given springscript instructions followed by WALK or RUN,
+/// returning the amount of hull damage reported.
+pub fn run_springscript(intcode_program_string: &str, instructions: &[&str], walk: bool) -> i64 {
+    if let Err(error) = validate_springscript(instructions, walk) {
+        panic!("Invalid springscript: {}", error);
+    }
+
     let mut intcode_program = Program::parse(intcode_program_string);
     intcode_program.run_for_output();
-    intcode_program.input_string(ascii_program_string);
+    for instruction in instructions {
+        intcode_program.input_string(instruction);
+        intcode_program.input_string("\n");
+    }
+    intcode_program.input_string(if walk { "WALK\n" } else { "RUN\n" });
 
     let program_output = intcode_program.run_for_output();
-    if let Some(value) = program_output.iter().find(|&&value| value > 255) {
-        value.to_string()
+    if let Some(&value) = program_output.iter().find(|&&value| value > 255) {
+        value
     } else {
         let output_bytes: Vec<u8> = program_output.iter().map(|&value| value as u8).collect();
         let output_string = std::str::from_utf8(&output_bytes).unwrap();
@@ -19,41 +64,39 @@ fn run(intcode_program_string: &str, ascii_program_string: &str) -> String {
 }
 
 pub fn part1(input_string: &str) -> String {
-    let mut ascii_program = String::new();
-    // Jump if there is a hole at A, B or C ...
-    ascii_program.push_str("NOT A T\nOR T J\n");
-    ascii_program.push_str("NOT B T\nOR T J\n");
-    ascii_program.push_str("NOT C T\nOR T J\n");
-    // ... AND ground at D:
-    ascii_program.push_str("AND D J\n");
-    ascii_program.push_str("WALK\n");
+    let instructions = [
+        // Jump if there is a hole at A, B or C ...
+        "NOT A T", "OR T J", "NOT B T", "OR T J", "NOT C T", "OR T J",
+        // ... AND ground at D:
+        "AND D J",
+    ];
 
-    run(input_string, &ascii_program)
+    run_springscript(input_string, &instructions, true).to_string()
 }
 
 pub fn part2(input_string: &str) -> String {
     // ABCDEFGH
     // ???_?..?
     // Do not jump to D if E and H are holes, since we cannot jump again.
-    let mut ascii_program = String::new();
-    // Jump if hole is in A ...
-    ascii_program.push_str("NOT A J\n");
-    // ... OR hole at B ...
-    ascii_program.push_str("NOT B T\nOR T J\n");
-    // ... OR hole at C:
-    ascii_program.push_str("NOT C T\nOR T J\n");
-    // ... AND ground at E:
-    ascii_program.push_str("AND E T\n");
-    // ... OR ground at H, so we can go to either E or jump to H:
-    ascii_program.push_str("OR H T\n");
-    // ... then jump ...
-    ascii_program.push_str("AND T J\n");
-    // ... if there there is ground at D:
-    ascii_program.push_str("AND D J\n");
+    #[rustfmt::skip]
+    let instructions = [
+        // Jump if hole is in A ...
+        "NOT A J",
+        // ... OR hole at B ...
+        "NOT B T", "OR T J",
+        // ... OR hole at C:
+        "NOT C T", "OR T J",
+        // ... AND ground at E:
+        "AND E T",
+        // ... OR ground at H, so we can go to either E or jump to H:
+        "OR H T",
+        // ... then jump ...
+        "AND T J",
+        // ... if there there is ground at D:
+        "AND D J",
+    ];
 
-    ascii_program.push_str("RUN\n");
-
-    run(input_string, &ascii_program)
+    run_springscript(input_string, &instructions, false).to_string()
 }
 
 #[test]
@@ -65,3 +108,33 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day21_input.txt")), "1141236756");
 }
+
+#[test]
+fn tests_run_springscript() {
+    let instructions = [
+        "NOT A T", "OR T J", "NOT B T", "OR T J", "NOT C T", "OR T J", "AND D J",
+    ];
+    assert_eq!(validate_springscript(&instructions, true), Ok(()));
+    assert_eq!(
+        run_springscript(include_str!("day21_input.txt"), &instructions, true),
+        19_358_688
+    );
+
+    assert_eq!(
+        validate_springscript(&["AND E J"], true),
+        Err("Invalid first register in instruction: 'AND E J'".to_string())
+    );
+    assert_eq!(validate_springscript(&["AND E J"], false), Ok(()));
+    assert_eq!(
+        validate_springscript(&["OR A B"], false),
+        Err("Invalid second register in instruction: 'OR A B'".to_string())
+    );
+    assert_eq!(
+        validate_springscript(&["XOR A J"], false),
+        Err("Invalid instruction: 'XOR A J'".to_string())
+    );
+    assert_eq!(
+        validate_springscript(&["NOT A J"; 16], true),
+        Err("Too many instructions: 16 (max 15)".to_string())
+    );
+}
@@ -1,105 +1,162 @@
-use mod_exp::mod_exp;
+/// A shuffle of a deck of cards as a transform from the position of a card before the shuffle
+/// to its position after it, which for all shuffle techniques is `x -> a * x + b (mod n)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shuffle {
+    a: i128,
+    b: i128,
+    deck_size: i128,
+}
 
-pub fn part1(input_string: &str) -> String {
-    let mut deck = Vec::new();
-    for i in 0..10_007 {
-        deck.push(i);
+impl Shuffle {
+    /// The shuffle leaving all cards in place.
+    pub fn identity(deck_size: u64) -> Shuffle {
+        Shuffle {
+            a: 1,
+            b: 0,
+            deck_size: i128::from(deck_size),
+        }
     }
 
-    for line in input_string.lines() {
-        if line.starts_with("deal into") {
-            deck = deck.iter().rev().copied().collect();
-        } else if line.starts_with("cut") {
-            let how_many = line
-                .split_whitespace()
-                .nth(1)
-                .unwrap()
-                .parse::<i32>()
-                .unwrap();
-            let how_many = if how_many > 0 {
-                how_many
+    /// Parse shuffle techniques, one per line, into the single shuffle performing them in order.
+    pub fn parse(input_string: &str, deck_size: u64) -> Result<Shuffle, String> {
+        let parse_number = |line: &str, prefix: &str| {
+            line[prefix.len()..]
+                .trim()
+                .parse::<i128>()
+                .map_err(|error| format!("Invalid line '{}': {}", line, error))
+        };
+
+        let mut shuffle = Shuffle::identity(deck_size);
+        for line in input_string.lines() {
+            let (a, b) = if line == "deal into new stack" {
+                (-1, -1)
+            } else if line.starts_with("cut ") {
+                (1, -parse_number(line, "cut ")?)
+            } else if line.starts_with("deal with increment ") {
+                (parse_number(line, "deal with increment ")?, 0)
             } else {
-                10_007 - how_many.abs()
-            } as usize;
-            deck = [&deck[how_many..], &deck[..how_many]].concat();
-        } else if line.starts_with("deal with") {
-            let increment = line
-                .split_whitespace()
-                .nth(3)
-                .unwrap()
-                .parse::<usize>()
-                .unwrap();
-            let old_deck = deck.clone();
-            let mut current_index = 0;
-            for &card_at_front in old_deck.iter() {
-                deck[current_index] = card_at_front;
-                current_index = (current_index + increment) % deck.len();
-            }
-        } else {
-            panic!("Invalid line: {}", line);
+                return Err(format!("Invalid line: {}", line));
+            };
+            shuffle = shuffle.then(&Shuffle {
+                a,
+                b,
+                deck_size: shuffle.deck_size,
+            });
         }
+        Ok(shuffle)
     }
 
-    deck.iter()
-        .position(|&card| card == 2019)
-        .unwrap()
-        .to_string()
-}
+    /// This shuffle followed by another one.
+    pub fn then(&self, other: &Shuffle) -> Shuffle {
+        Shuffle {
+            a: (other.a * self.a).rem_euclid(self.deck_size),
+            b: (other.a * self.b + other.b).rem_euclid(self.deck_size),
+            deck_size: self.deck_size,
+        }
+    }
 
-/// Explanation:
-/// https://www.reddit.com/r/adventofcode/comments/ee0rqi/2019_day_22_solutions/fbnkaju?utm_source=share&utm_medium=web2x
-pub fn part2(input_string: &str) -> String {
-    const NUM_CARDS: i128 = 119_315_717_514_047;
-    const MOD: i128 = NUM_CARDS;
+    /// This shuffle performed the given number of times in a row, composed by repeated squaring.
+    pub fn repeat(&self, times: u64) -> Shuffle {
+        let mut result = Shuffle::identity(self.deck_size as u64);
+        let mut square = *self;
+        let mut times = times;
+        while times > 0 {
+            if times & 1 == 1 {
+                result = result.then(&square);
+            }
+            square = square.then(&square);
+            times >>= 1;
+        }
+        result
+    }
 
-    let mut offset_diff: i128 = 0;
-    let mut increment_mul: i128 = 1;
+    /// The shuffle undoing this one.
+    ///
+    /// # Panics
+    ///
+    /// If the shuffle cannot be undone, which happens if it deals with an increment sharing a
+    /// factor with the deck size.
+    pub fn inverse(&self) -> Shuffle {
+        let a_inverse = modular_inverse(self.a, self.deck_size)
+            .expect("Shuffle with an increment sharing a factor with the deck size");
+        Shuffle {
+            a: a_inverse,
+            b: (-self.b * a_inverse).rem_euclid(self.deck_size),
+            deck_size: self.deck_size,
+        }
+    }
 
-    fn inv(n: i128) -> i128 {
-        mod_exp(n, MOD - 2, MOD)
+    /// The position of a card after the shuffle.
+    pub fn position_of(&self, card: u64) -> u64 {
+        (self.a * i128::from(card) + self.b).rem_euclid(self.deck_size) as u64
     }
 
-    for line in input_string.lines() {
-        if line.starts_with("deal into") {
-            increment_mul *= -1;
-            offset_diff += increment_mul;
-        } else if line.starts_with("cut") {
-            let n = line
-                .split_whitespace()
-                .nth(1)
-                .unwrap()
-                .parse::<i128>()
-                .unwrap();
-            offset_diff += increment_mul * n;
-        } else if line.starts_with("deal with") {
-            let n = line
-                .split_whitespace()
-                .nth(3)
-                .unwrap()
-                .parse::<i128>()
-                .unwrap();
-            increment_mul *= inv(n);
-        // The offset (first card) does not change.
-        // To get the increment, we need to calculate the second card.
-        // "The 0th card in our old list goes to the 0th card in our new list, 1st card in
-        // old goes to the nth card in new list (mod MOD), 2nd card in old goes to the 2*nth
-        // card in new list, and so on. So, the ith card in our old list goes to the i*nth
-        // card in the new list. When is i*n = 1?"
-        } else {
-            panic!("Invalid line: {}", line);
-        }
+    /// The card at a position after performing the shuffle `repeats` times.
+    pub fn card_at_position(&self, position: u64, repeats: u64) -> u64 {
+        self.repeat(repeats).inverse().position_of(position)
+    }
+}
 
-        increment_mul = increment_mul.rem_euclid(MOD);
-        offset_diff = offset_diff.rem_euclid(MOD);
+/// The inverse of `value` modulo `modulus` using the extended Euclidean algorithm, or None
+/// if they share a factor.
+fn modular_inverse(value: i128, modulus: i128) -> Option<i128> {
+    let (mut old_remainder, mut remainder) = (value.rem_euclid(modulus), modulus);
+    let (mut old_coefficient, mut coefficient) = (1, 0);
+    while remainder != 0 {
+        let quotient = old_remainder / remainder;
+        let next_remainder = old_remainder - quotient * remainder;
+        old_remainder = remainder;
+        remainder = next_remainder;
+        let next_coefficient = old_coefficient - quotient * coefficient;
+        old_coefficient = coefficient;
+        coefficient = next_coefficient;
     }
+    if old_remainder == 1 {
+        Some(old_coefficient.rem_euclid(modulus))
+    } else {
+        None
+    }
+}
+
+/// The position of a card in a deck of the given size after the shuffle, computed without
+/// materializing the deck.
+///
+/// # Panics
+///
+/// If the card is not in the deck, or if the shuffle was parsed for another deck size.
+pub fn position_of(card: u64, deck_size: u64, shuffle: &Shuffle) -> u64 {
+    assert!(
+        i128::from(deck_size) == shuffle.deck_size,
+        "Shuffle of {} cards used for a deck of {} cards",
+        shuffle.deck_size,
+        deck_size
+    );
+    assert!(
+        card < deck_size,
+        "Card {} is not in a deck of {} cards",
+        card,
+        deck_size
+    );
+    shuffle.position_of(card)
+}
 
-    const SHUFFLES: i128 = 101_741_582_076_661;
+fn parse_shuffle(input_string: &str, deck_size: u64) -> Shuffle {
+    Shuffle::parse(input_string, deck_size).unwrap_or_else(|error| panic!("{}", error))
+}
+
+pub fn part1(input_string: &str) -> String {
+    parse_shuffle(input_string, 10_007)
+        .position_of(2019)
+        .to_string()
+}
 
-    let increment = mod_exp(increment_mul, SHUFFLES, MOD);
-    let offset = (offset_diff * (1i128 - increment)).rem_euclid(MOD) * inv(1 - increment_mul);
+pub fn part2(input_string: &str) -> String {
+    const NUM_CARDS: u64 = 119_315_717_514_047;
+    const SHUFFLES: u64 = 101_741_582_076_661;
 
-    let result = (offset + increment * 2020).rem_euclid(MOD);
-    result.to_string()
+    parse_shuffle(input_string, NUM_CARDS)
+        .card_at_position(2020, SHUFFLES)
+        .to_string()
 }
 
 #[test]
@@ -111,3 +168,133 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day22_input.txt")), "79855812422607");
 }
+
+#[test]
+fn tests_shuffle() {
+    let deck_after = |techniques: &str| {
+        let shuffle = Shuffle::parse(techniques, 10).unwrap();
+        (0..10)
+            .map(|position| shuffle.card_at_position(position, 1))
+            .collect::<Vec<u64>>()
+    };
+
+    assert_eq!(
+        deck_after("deal into new stack"),
+        vec![9, 8, 7, 6, 5, 4, 3, 2, 1, 0]
+    );
+    assert_eq!(deck_after("cut 3"), vec![3, 4, 5, 6, 7, 8, 9, 0, 1, 2]);
+    assert_eq!(deck_after("cut -4"), vec![6, 7, 8, 9, 0, 1, 2, 3, 4, 5]);
+    assert_eq!(
+        deck_after("deal with increment 3"),
+        vec![0, 7, 4, 1, 8, 5, 2, 9, 6, 3]
+    );
+    assert_eq!(
+        deck_after("deal with increment 7\ndeal into new stack\ndeal into new stack"),
+        vec![0, 3, 6, 9, 2, 5, 8, 1, 4, 7]
+    );
+    assert_eq!(
+        deck_after("cut 6\ndeal with increment 7\ndeal into new stack"),
+        vec![3, 0, 7, 4, 1, 8, 5, 2, 9, 6]
+    );
+    assert_eq!(
+        deck_after("deal with increment 7\ndeal with increment 9\ncut -2"),
+        vec![6, 3, 0, 7, 4, 1, 8, 5, 2, 9]
+    );
+    assert_eq!(
+        deck_after(
+            "deal into new stack
+cut -2
+deal with increment 7
+cut 8
+cut -4
+deal with increment 7
+cut 3
+deal with increment 9
+deal with increment 3
+cut -1"
+        ),
+        vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6]
+    );
+
+    assert!(Shuffle::parse("shuffle thoroughly", 10).is_err());
+    assert!(Shuffle::parse("cut x", 10).is_err());
+}
+
+#[test]
+fn tests_shuffle_inverse() {
+    let shuffle = parse_shuffle(include_str!("day22_input.txt"), 10_007);
+    let inverse = shuffle.inverse();
+    assert_eq!(shuffle.then(&inverse), Shuffle::identity(10_007));
+    for card in (0..10_007).step_by(101) {
+        assert_eq!(inverse.position_of(shuffle.position_of(card)), card);
+    }
+
+    // Repeating is the same as composing, and a full cycle of repeats is the identity:
+    let repeated = shuffle.repeat(5);
+    assert_eq!(
+        repeated,
+        shuffle
+            .then(&shuffle)
+            .then(&shuffle)
+            .then(&shuffle)
+            .then(&shuffle)
+    );
+    assert_eq!(
+        shuffle.card_at_position(shuffle.repeat(3).position_of(42), 3),
+        42
+    );
+}
+
+#[cfg(test)]
+fn shuffle_deck(techniques: &str, deck_size: u64) -> Vec<u64> {
+    let mut deck: Vec<u64> = (0..deck_size).collect();
+    for line in techniques.lines() {
+        if line == "deal into new stack" {
+            deck.reverse();
+        } else if let Some(cut) = line.strip_prefix("cut ") {
+            let cut = cut.parse::<i64>().unwrap().rem_euclid(deck_size as i64) as usize;
+            deck.rotate_left(cut);
+        } else if let Some(increment) = line.strip_prefix("deal with increment ") {
+            let increment = increment.parse::<usize>().unwrap();
+            let mut dealt = deck.clone();
+            for (index, &card) in deck.iter().enumerate() {
+                dealt[(index * increment) % deck.len()] = card;
+            }
+            deck = dealt;
+        }
+    }
+    deck
+}
+
+#[test]
+fn tests_position_of() {
+    let techniques = "deal into new stack
+cut -2
+deal with increment 7
+cut 8
+cut -4
+deal with increment 7
+cut 3
+deal with increment 9
+deal with increment 3
+cut -1";
+    for &deck_size in &[10, 11, 101] {
+        let shuffle = Shuffle::parse(techniques, deck_size).unwrap();
+        let deck = shuffle_deck(techniques, deck_size);
+        for (position, &card) in deck.iter().enumerate() {
+            assert_eq!(position_of(card, deck_size, &shuffle), position as u64);
+            assert_eq!(shuffle.card_at_position(position as u64, 1), card);
+        }
+    }
+
+    let deck = shuffle_deck(include_str!("day22_input.txt"), 10_007);
+    let shuffle = parse_shuffle(include_str!("day22_input.txt"), 10_007);
+    assert_eq!(position_of(2019, 10_007, &shuffle), 6526);
+    assert_eq!(deck[6526], 2019);
+}
+
+#[test]
+#[should_panic(expected = "Shuffle of 10 cards used for a deck of 11 cards")]
+fn tests_position_of_other_deck_size() {
+    position_of(3, 11, &Shuffle::identity(10));
+}
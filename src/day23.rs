@@ -1,21 +1,125 @@
 use crate::int_code::Program;
 use std::collections::VecDeque;
 
-pub fn run_simulation(input_string: &str, part1: bool) -> String {
-    let mut programs = vec![Program::parse(input_string); 50];
-    let mut input_queues = vec![VecDeque::<(i64, i64)>::new(); 50];
+/// The number of computers in the network of the puzzle.
+const NUM_COMPUTERS: usize = 50;
+
+/// Statistics about the traffic in a network simulation.
+#[derive(Debug, Default, PartialEq)]
+pub struct NetworkStats {
+    /// The number of packets delivered, both between computers, to the NAT and from the NAT.
+    pub packets_routed: usize,
+    /// The number of packets received by each computer, including those from the NAT.
+    pub packets_received: Vec<usize>,
+    /// The number of packets sent to the NAT.
+    pub packets_to_nat: usize,
+    /// The number of times the NAT has sent a packet to wake up an idle network.
+    pub nat_interventions: usize,
+    /// The number of rounds where the network was idle.
+    pub idle_cycles: usize,
+    /// The number of computers whose program has halted.
+    pub halted_computers: usize,
+    /// The number of packets sent to an address without any computer or NAT, which are lost.
+    pub packets_dropped: usize,
+}
+
+/// A packet seen during a network simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PacketEvent {
+    /// A computer sent a packet to the given address, which is 255 for the NAT.
+    Sent {
+        destination: i64,
+        packet: (i64, i64),
+    },
+    /// The NAT sent a packet to address 0 since the network was idle.
+    NatWakeUp { packet: (i64, i64) },
+}
+
+/// Optional instrumentation of a network simulation.
+#[derive(Default)]
+struct NetworkOptions<'a> {
+    /// Where to record statistics about the traffic.
+    stats: Option<&'a mut NetworkStats>,
+    /// Callback invoked for each packet.
+    on_packet: Option<&'a mut dyn FnMut(PacketEvent)>,
+}
+
+/// Run the network, failing if all computers halt before the answer is found.
+pub fn run_simulation(
+    input_string: &str,
+    part1: bool,
+    num_computers: usize,
+) -> Result<String, String> {
+    run_heterogeneous(vec![Program::parse(input_string); num_computers], part1)
+}
+
+/// Same as `run_simulation`, but also returning statistics about the network traffic.
+pub fn run_simulation_stats(
+    input_string: &str,
+    part1: bool,
+    num_computers: usize,
+) -> Result<(String, NetworkStats), String> {
+    let mut stats = NetworkStats {
+        packets_received: vec![0; num_computers],
+        ..NetworkStats::default()
+    };
+    let answer = run_network(
+        vec![Program::parse(input_string); num_computers],
+        part1,
+        NetworkOptions {
+            stats: Some(&mut stats),
+            ..NetworkOptions::default()
+        },
+    )?;
+    Ok((answer, stats))
+}
+
+/// Same as `run_simulation`, but invoking `on_packet` for each packet sent.
+pub fn run_simulation_traced(
+    input_string: &str,
+    part1: bool,
+    num_computers: usize,
+    mut on_packet: impl FnMut(PacketEvent),
+) -> Result<String, String> {
+    run_network(
+        vec![Program::parse(input_string); num_computers],
+        part1,
+        NetworkOptions {
+            on_packet: Some(&mut on_packet),
+            ..NetworkOptions::default()
+        },
+    )
+}
+
+/// Run a network where each computer runs its own program, with the
+/// network address of a computer being its index in `programs`.
+pub fn run_heterogeneous(programs: Vec<Program>, part1: bool) -> Result<String, String> {
+    run_network(programs, part1, NetworkOptions::default())
+}
+
+fn run_network(
+    mut programs: Vec<Program>,
+    part1: bool,
+    mut options: NetworkOptions,
+) -> Result<String, String> {
+    let mut input_queues = vec![VecDeque::<(i64, i64)>::new(); programs.len()];
 
     // Assign network addresses:
     for (i, program) in programs.iter_mut().enumerate() {
         program.input(i as i64);
     }
 
-    let mut last_packet_to_nat = (-1, -1);
-    let mut last_emitted_packet_from_nat = (-1, -1);
+    let mut last_packet_to_nat = None;
+    let mut last_emitted_packet_from_nat: Option<(i64, i64)> = None;
+
+    let mut halted = vec![false; programs.len()];
 
     loop {
         for (program, input_queue) in programs.iter_mut().zip(input_queues.iter_mut()) {
-            if input_queue.is_empty() {
+            if program.is_halted() {
+                // Packets to a halted computer are lost:
+                input_queue.clear();
+            } else if input_queue.is_empty() {
                 program.input(-1);
             } else {
                 while let Some((x, y)) = input_queue.pop_front() {
@@ -25,41 +129,102 @@ pub fn run_simulation(input_string: &str, part1: bool) -> String {
             }
         }
 
-        let mut network_idle = true;
-        for program in programs.iter_mut() {
-            for chunk in program.run_for_output().chunks(3) {
+        let mut produced_output = false;
+        for (program, halted) in programs.iter_mut().zip(halted.iter_mut()) {
+            if *halted {
+                continue;
+            }
+            let output = program.run_for_output();
+            if program.is_halted() {
+                *halted = true;
+                if let Some(stats) = &mut options.stats {
+                    stats.halted_computers += 1;
+                }
+            }
+            produced_output |= !output.is_empty();
+            for chunk in output.chunks(3) {
                 let (destination_address, packet) = (chunk[0], (chunk[1], chunk[2]));
 
-                if destination_address == 255 {
-                    if part1 {
-                        return packet.1.to_string();
-                    } else {
-                        last_packet_to_nat = packet;
+                if let Some(on_packet) = &mut options.on_packet {
+                    on_packet(PacketEvent::Sent {
+                        destination: destination_address,
+                        packet,
+                    });
+                }
+                let destination = match destination_address {
+                    255 => None,
+                    address if address >= 0 && (address as usize) < input_queues.len() => {
+                        Some(address as usize)
+                    }
+                    _ => {
+                        // No computer has the address, so the packet is lost:
+                        if let Some(stats) = &mut options.stats {
+                            stats.packets_dropped += 1;
+                        }
+                        continue;
                     }
-                } else {
-                    network_idle = false;
-                    input_queues[destination_address as usize].push_back(packet);
+                };
+                if let Some(stats) = &mut options.stats {
+                    stats.packets_routed += 1;
+                    match destination {
+                        Some(destination) => stats.packets_received[destination] += 1,
+                        None => stats.packets_to_nat += 1,
+                    }
+                }
+
+                match destination {
+                    Some(destination) => input_queues[destination].push_back(packet),
+                    None if part1 => return Ok(packet.1.to_string()),
+                    None => last_packet_to_nat = Some(packet),
                 }
             }
         }
 
-        if network_idle {
-            if last_packet_to_nat.1 == last_emitted_packet_from_nat.1 {
-                return last_packet_to_nat.1.to_string();
+        if halted.iter().all(|&halted| halted) {
+            return Err("All computers halted before the answer was found".to_string());
+        }
+
+        if is_network_idle(&input_queues, produced_output) {
+            if let Some(stats) = &mut options.stats {
+                stats.idle_cycles += 1;
+            }
+
+            // Idle computers keep reading -1, waiting for the NAT, which is deadlocked
+            // if it has not received any packet to wake up the network with:
+            let last_packet_to_nat = last_packet_to_nat
+                .ok_or_else(|| "Network deadlocked without any packet to the NAT".to_string())?;
+            if Some(last_packet_to_nat.1) == last_emitted_packet_from_nat.map(|packet| packet.1) {
+                return Ok(last_packet_to_nat.1.to_string());
             }
 
-            last_emitted_packet_from_nat = last_packet_to_nat;
+            if let Some(on_packet) = &mut options.on_packet {
+                on_packet(PacketEvent::NatWakeUp {
+                    packet: last_packet_to_nat,
+                });
+            }
+            if let Some(stats) = &mut options.stats {
+                stats.packets_routed += 1;
+                stats.packets_received[0] += 1;
+                stats.nat_interventions += 1;
+            }
+            last_emitted_packet_from_nat = Some(last_packet_to_nat);
             input_queues[0].push_back(last_packet_to_nat);
         }
     }
 }
 
+/// The network is idle if there are no packets waiting to be received and no computer
+/// sent any packets during the last round.
+fn is_network_idle(input_queues: &[VecDeque<(i64, i64)>], produced_output: bool) -> bool {
+    !produced_output && input_queues.iter().all(VecDeque::is_empty)
+}
+
 pub fn part1(input_string: &str) -> String {
-    run_simulation(input_string, true)
+    run_simulation(input_string, true, NUM_COMPUTERS).unwrap()
 }
 
 pub fn part2(input_string: &str) -> String {
-    run_simulation(input_string, false)
+    run_simulation(input_string, false, NUM_COMPUTERS).unwrap()
 }
 
 #[test]
@@ -71,3 +236,172 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day23_input.txt")), "11462");
 }
+
+#[test]
+fn tests_heterogeneous() {
+    // Sends the packet (7, 8) to address 1 and then keeps reading input.
+    let sender = Program::parse("3,100,104,1,104,7,104,8,3,100,1105,1,8");
+    // Waits for a packet and forwards it to address 255, then keeps reading input.
+    let forwarder = Program::parse(
+        "3,100,3,101,1001,101,1,103,1006,103,2,3,102,104,255,4,101,4,102,3,100,1105,1,19",
+    );
+
+    assert_eq!(
+        run_heterogeneous(vec![sender.clone(), forwarder.clone()], true),
+        Ok("8".to_string())
+    );
+    assert_eq!(
+        run_heterogeneous(vec![sender, forwarder], false),
+        Ok("8".to_string())
+    );
+}
+
+#[test]
+fn tests_run_simulation() {
+    // Sends the packet (address, address * 2) to the NAT and then keeps reading input.
+    let program = "3,100,1002,100,2,101,104,255,4,100,4,101,3,102,1105,1,12";
+
+    assert_eq!(run_simulation(program, true, 3), Ok("0".to_string()));
+    // The NAT keeps the packet from the last computer, which is sent twice in a row:
+    assert_eq!(run_simulation(program, false, 3), Ok("4".to_string()));
+    assert_eq!(run_simulation(program, false, 5), Ok("8".to_string()));
+}
+
+#[test]
+fn tests_run_simulation_stats() {
+    let input = include_str!("day23_input.txt");
+    for &part1 in [true, false].iter() {
+        let (answer, stats) = run_simulation_stats(input, part1, NUM_COMPUTERS).unwrap();
+        assert_eq!(Ok(answer), run_simulation(input, part1, NUM_COMPUTERS));
+        assert_eq!(stats.halted_computers, 0);
+        assert_eq!(stats.packets_received.len(), NUM_COMPUTERS);
+        assert_eq!(
+            stats.packets_received.iter().sum::<usize>(),
+            stats.packets_routed - stats.packets_to_nat
+        );
+        assert!(stats.packets_to_nat > 0);
+        assert_eq!(stats.packets_dropped, 0);
+        if part1 {
+            assert_eq!(stats.nat_interventions, 0);
+        } else {
+            assert!(stats.nat_interventions > 0);
+            // Each intervention follows an idle round, and the final idle round ends the run:
+            assert_eq!(stats.idle_cycles, stats.nat_interventions + 1);
+        }
+    }
+}
+
+#[test]
+fn tests_is_network_idle() {
+    let mut input_queues = vec![VecDeque::new(); 3];
+    assert!(is_network_idle(&input_queues, false));
+    assert!(!is_network_idle(&input_queues, true));
+
+    input_queues[2].push_back((1, 2));
+    assert!(!is_network_idle(&input_queues, false));
+}
+
+#[test]
+fn tests_run_simulation_traced() {
+    // Sends the packet (address, address * 2) to the NAT and then keeps reading input.
+    let program = "3,100,1002,100,2,101,104,255,4,100,4,101,3,102,1105,1,12";
+    let mut packets = Vec::new();
+    let answer = run_simulation_traced(program, false, 3, |event| packets.push(event));
+    assert_eq!(answer, Ok("4".to_string()));
+    assert_eq!(
+        packets,
+        vec![
+            PacketEvent::Sent {
+                destination: 255,
+                packet: (0, 0)
+            },
+            PacketEvent::Sent {
+                destination: 255,
+                packet: (1, 2)
+            },
+            PacketEvent::Sent {
+                destination: 255,
+                packet: (2, 4)
+            },
+            PacketEvent::NatWakeUp { packet: (2, 4) },
+        ]
+    );
+
+    let mut packets = Vec::new();
+    run_simulation_traced(
+        include_str!("day23_input.txt"),
+        false,
+        NUM_COMPUTERS,
+        |event| packets.push(event),
+    )
+    .unwrap();
+    let nat_wake_ups = packets
+        .iter()
+        .filter(|event| matches!(event, PacketEvent::NatWakeUp { .. }))
+        .count();
+    assert!(nat_wake_ups > 0);
+}
+
+#[test]
+fn tests_halting_programs() {
+    assert_eq!(
+        run_simulation("99", false, 3),
+        Err("All computers halted before the answer was found".to_string())
+    );
+
+    // Computer 0 halts immediately, while computer 1 sends a packet to the NAT:
+    let halting = Program::parse("99");
+    let sender = Program::parse("3,100,104,255,104,7,104,8,3,100,1105,1,8");
+    assert_eq!(
+        run_heterogeneous(vec![halting.clone(), sender.clone()], true),
+        Ok("8".to_string())
+    );
+    // The packet from the NAT is lost since computer 0 has halted, so the network stays idle:
+    assert_eq!(
+        run_heterogeneous(vec![halting, sender], false),
+        Ok("8".to_string())
+    );
+}
+
+#[test]
+fn tests_deadlock() {
+    // Keeps reading input without ever sending a packet:
+    let silent = "3,100,1105,1,0";
+    for &part1 in [true, false].iter() {
+        assert_eq!(
+            run_simulation(silent, part1, 3),
+            Err("Network deadlocked without any packet to the NAT".to_string())
+        );
+    }
+}
+
+#[test]
+fn tests_small_network() {
+    // Packets to computers missing from the network are lost, until it deadlocks:
+    let input = include_str!("day23_input.txt");
+    for &part1 in [true, false].iter() {
+        let mut stats = NetworkStats {
+            packets_received: vec![0; 10],
+            ..NetworkStats::default()
+        };
+        let result = run_network(
+            vec![Program::parse(input); 10],
+            part1,
+            NetworkOptions {
+                stats: Some(&mut stats),
+                ..NetworkOptions::default()
+            },
+        );
+        assert_eq!(
+            result,
+            Err("Network deadlocked without any packet to the NAT".to_string())
+        );
+        assert!(stats.packets_dropped > 0);
+        assert_eq!(
+            stats.packets_received.iter().sum::<usize>(),
+            stats.packets_routed
+        );
+    }
+
+    assert!(run_simulation(input, false, 1).is_err());
+}
@@ -1,151 +1,184 @@
+use crate::grid::Grid2D;
 use std::collections::HashSet;
 
-#[derive(PartialEq, Eq, Hash, Copy, Clone)]
-struct Grid {
-    value: u32,
-}
+/// The size of the grids in the recursive variant.
+const SIZE: usize = 5;
+const CENTER: (usize, usize) = (2, 2);
 
-impl Grid {
-    fn zeroed() -> Grid {
-        Grid { value: 0 }
+/// Parse a rectangular grid with its size given by the input, where `#` is a bug.
+fn parse(input: &str) -> Result<Grid2D<bool>, String> {
+    let lines: Vec<&str> = input.trim().lines().map(str::trim_end).collect();
+    let width = lines.first().map_or(0, |line| line.len());
+    let height = lines.len();
+    if width == 0 {
+        return Err("Empty grid".to_string());
+    } else if width * height > 64 {
+        return Err(format!(
+            "A {}x{} grid has too many tiles for a biodiversity rating",
+            width, height
+        ));
     }
 
-    fn parse(input: &str) -> Grid {
-        Grid {
-            value: input
-                .lines()
-                .enumerate()
-                .flat_map(|(y, line)| {
-                    line.chars()
-                        .enumerate()
-                        .filter_map(move |(x, character)| match character {
-                            '#' => Some((x, y)),
-                            _ => None,
-                        })
-                })
-                .fold(0, |value, (x, y)| value | 1 << ((y * 5) + x)),
+    let mut grid = Grid2D::new(width, height, false);
+    for (y, line) in lines.iter().enumerate() {
+        if line.len() != width {
+            return Err(format!("Line {} is not {} tiles wide", y + 1, width));
         }
-    }
-
-    fn at(self, x: i32, y: i32) -> u32 {
-        if x < 0 || y < 0 || x >= 5 || y >= 5 {
-            0
-        } else {
-            let bit = 1 << ((y * 5) + x);
-            if bit & self.value == bit {
-                1
-            } else {
-                0
-            }
+        for (x, character) in line.chars().enumerate() {
+            grid[(x, y)] = match character {
+                '#' => true,
+                '.' => false,
+                _ => return Err(format!("Invalid tile: '{}'", character)),
+            };
         }
     }
+    Ok(grid)
+}
 
-    fn advance_minute(&mut self) {
-        let mut new_value = 0u32;
-
-        for y in 0..5 {
-            for x in 0..5 {
-                let adjacent_bugs =
-                    self.at(x - 1, y) + self.at(x + 1, y) + self.at(x, y - 1) + self.at(x, y + 1);
-                if adjacent_bugs == 1 || (self.at(x, y) == 0 && adjacent_bugs == 2) {
-                    let bit = 1 << ((y * 5) + x);
-                    new_value |= bit;
-                }
-            }
-        }
+fn parse_grid(input: &str) -> Grid2D<bool> {
+    parse(input).unwrap_or_else(|error| panic!("{}", error))
+}
 
-        self.value = new_value;
-    }
+/// The sum of 2^n for each tile n with a bug, counting tiles in reading order so that the
+/// tile at `(x, y)` is tile `y * width + x`.
+fn biodiversity_rating(grid: &Grid2D<bool>) -> u64 {
+    grid.iter()
+        .enumerate()
+        .filter(|&(_, &bug)| bug)
+        .map(|(index, _)| 1 << index)
+        .sum()
+}
 
-    fn advance_until_repeat(&mut self) -> u32 {
-        let mut seen = HashSet::new();
+/// If a tile has a bug after a minute, given if it has one now and its number of adjacent bugs.
+fn bug_survives(bug: bool, adjacent_bugs: usize) -> bool {
+    adjacent_bugs == 1 || (!bug && adjacent_bugs == 2)
+}
 
-        while seen.insert(self.value) {
-            self.advance_minute();
+/// Let a minute pass in a grid without recursion, writing the bugs into `next_grid`.
+fn next_generation(grid: &Grid2D<bool>, next_grid: &mut Grid2D<bool>) {
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            let adjacent_bugs = grid
+                .neighbors((x, y))
+                .filter(|&neighbor| grid[neighbor])
+                .count();
+            next_grid[(x, y)] = bug_survives(grid[(x, y)], adjacent_bugs);
         }
-
-        self.value
     }
+}
+
+/// The biodiversity rating of the first layout appearing twice, in a grid of any size with at
+/// most 64 tiles.
+pub fn biodiversity(input_string: &str) -> u64 {
+    let mut grid = parse_grid(input_string);
+    let mut next_grid = grid.clone();
+    // The biodiversity rating identifies a layout:
+    let mut seen = HashSet::new();
 
-    fn count_bugs(self) -> u32 {
-        self.value.count_ones()
+    while seen.insert(biodiversity_rating(&grid)) {
+        next_generation(&grid, &mut next_grid);
+        std::mem::swap(&mut grid, &mut next_grid);
     }
 
-    fn count_bugs_at_edge(self, coming_from: (i32, i32)) -> u32 {
-        (self.value
-            & match coming_from {
-                (2, 1) => 0b00000_00000_00000_00000_11111u32,
-                (3, 2) => 0b10000_10000_10000_10000_10000u32,
-                (2, 3) => 0b11111_00000_00000_00000_00000u32,
-                (1, 2) => 0b00001_00001_00001_00001_00001u32,
-                _ => panic!("Unsupported direction"),
-            })
-        .count_ones()
+    biodiversity_rating(&grid)
+}
+
+/// The tiles adjacent to a tile in a recursive grid as (level offset, x, y), where level offset
+/// -1 is the grid containing this one and 1 is the grid inside the center tile.
+fn recursive_neighbors(x: usize, y: usize) -> Vec<(i32, usize, usize)> {
+    let mut neighbors = Vec::new();
+    for &(dx, dy) in &[(0, 1), (0, -1), (-1, 0), (1, 0)] {
+        let (new_x, new_y) = (x as i32 + dx, y as i32 + dy);
+        if new_x < 0 || new_y < 0 || new_x >= SIZE as i32 || new_y >= SIZE as i32 {
+            // The tile next to the center of the containing grid:
+            neighbors.push((
+                -1,
+                (CENTER.0 as i32 + dx) as usize,
+                (CENTER.1 as i32 + dy) as usize,
+            ));
+        } else if (new_x as usize, new_y as usize) == CENTER {
+            // The whole edge of the inner grid facing this tile:
+            for i in 0..SIZE {
+                neighbors.push(match (dx, dy) {
+                    (1, _) => (1, 0, i),
+                    (-1, _) => (1, SIZE - 1, i),
+                    (_, 1) => (1, i, 0),
+                    _ => (1, i, SIZE - 1),
+                });
+            }
+        } else {
+            neighbors.push((0, new_x as usize, new_y as usize));
+        }
     }
+    neighbors
+}
 
-    fn advance(&mut self, inner_grid: Grid, outer_grid: Grid) -> Grid {
-        let mut new_value = 0u32;
+/// The number of bugs after the given number of minutes in the recursive grid, where the
+/// center tile of each grid holds another grid.
+///
+/// # Panics
+///
+/// If the grid is not 5x5.
+pub fn bugs_after(input_string: &str, minutes: u64) -> usize {
+    let initial_grid = parse_grid(input_string);
+    assert!(
+        initial_grid.width() == SIZE && initial_grid.height() == SIZE,
+        "Recursive grids must be {}x{}, not {}x{}",
+        SIZE,
+        SIZE,
+        initial_grid.width(),
+        initial_grid.height()
+    );
 
-        for y in 0..5 {
-            for x in 0..5 {
-                if (x, y) == (2, 2) {
-                    continue;
-                }
+    // Bugs can spread at most one level outwards and inwards each minute:
+    let num_levels = 2 * minutes as usize + 3;
+    let empty_grid = Grid2D::new(SIZE, SIZE, false);
+    let mut levels = vec![empty_grid.clone(); num_levels];
+    let mut next_levels = levels.clone();
+    levels[num_levels / 2] = initial_grid;
 
-                let mut adjacent_bugs = 0;
-
-                const DIRECTIONS: &[(i32, i32); 4] = &[(0, 1), (0, -1), (-1, 0), (1, 0)];
-                for &direction in DIRECTIONS.iter() {
-                    let new_x = x + direction.0;
-                    let new_y = y + direction.1;
-                    adjacent_bugs += if !((0..5).contains(&new_x) && (0..5).contains(&new_y)) {
-                        outer_grid.at(2 + direction.0, 2 + direction.1)
-                    } else if (new_x, new_y) == (2, 2) {
-                        inner_grid.count_bugs_at_edge((x, y))
-                    } else {
-                        self.at(new_x, new_y)
-                    };
-                }
+    let mut neighbor_table = Grid2D::new(SIZE, SIZE, Vec::new());
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            neighbor_table[(x, y)] = recursive_neighbors(x, y);
+        }
+    }
 
-                if adjacent_bugs == 1 || (self.at(x, y) == 0 && adjacent_bugs == 2) {
-                    let bit = 1 << ((y * 5) + x);
-                    new_value |= bit;
+    for _minute in 0..minutes {
+        for level in 0..num_levels {
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    if (x, y) == CENTER {
+                        continue;
+                    }
+                    let adjacent_bugs = neighbor_table[(x, y)]
+                        .iter()
+                        .filter(|&&(level_offset, neighbor_x, neighbor_y)| {
+                            let neighbor_level = level as i32 + level_offset;
+                            neighbor_level >= 0
+                                && (neighbor_level as usize) < num_levels
+                                && levels[neighbor_level as usize][(neighbor_x, neighbor_y)]
+                        })
+                        .count();
+                    next_levels[level][(x, y)] = bug_survives(levels[level][(x, y)], adjacent_bugs);
                 }
             }
         }
-
-        Grid { value: new_value }
+        std::mem::swap(&mut levels, &mut next_levels);
     }
+
+    levels
+        .iter()
+        .map(|grid| grid.iter().filter(|&&bug| bug).count())
+        .sum()
 }
 
 pub fn part1(input_string: &str) -> String {
-    let mut grid = Grid::parse(input_string);
-    grid.advance_until_repeat().to_string()
+    biodiversity(input_string).to_string()
 }
 
 pub fn part2(input_string: &str) -> String {
-    const MINUTES: usize = 200;
-    const MAX_LEVELS: usize = MINUTES * 2;
-
-    let mut current_generation = vec![Grid::zeroed(); MAX_LEVELS];
-    let mut next_generation = vec![Grid::zeroed(); MAX_LEVELS];
-
-    current_generation[MAX_LEVELS / 2] = Grid::parse(input_string);
-
-    for _minute in 0..MINUTES {
-        for i in 1..(current_generation.len() - 1) {
-            let mut this_grid = current_generation[i];
-            next_generation[i] =
-                this_grid.advance(current_generation[i - 1], current_generation[i + 1]);
-        }
-        std::mem::swap(&mut current_generation, &mut next_generation);
-    }
-
-    current_generation
-        .iter()
-        .map(|value| value.count_bugs())
-        .sum::<u32>()
-        .to_string()
+    bugs_after(input_string, 200).to_string()
 }
 
 #[test]
@@ -157,3 +190,91 @@ pub fn tests_part1() {
 fn tests_part2() {
     assert_eq!(part2(include_str!("day24_input.txt")), "1967");
 }
+
+#[test]
+fn tests_recursive_neighbors() {
+    let sorted = |x, y| {
+        let mut neighbors = recursive_neighbors(x, y);
+        neighbors.sort();
+        neighbors
+    };
+
+    // Tile 19 and G only have neighbors on the same level:
+    assert_eq!(
+        sorted(3, 3),
+        vec![(0, 2, 3), (0, 3, 2), (0, 3, 4), (0, 4, 3)]
+    );
+    assert_eq!(
+        sorted(1, 1),
+        vec![(0, 0, 1), (0, 1, 0), (0, 1, 2), (0, 2, 1)]
+    );
+    // Tile D is next to tile 8 in the containing grid:
+    assert_eq!(
+        sorted(3, 0),
+        vec![(-1, 2, 1), (0, 2, 0), (0, 3, 1), (0, 4, 0)]
+    );
+    // Tile E is next to tiles 8 and 14 in the containing grid:
+    assert_eq!(
+        sorted(4, 0),
+        vec![(-1, 2, 1), (-1, 3, 2), (0, 3, 0), (0, 4, 1)]
+    );
+    // Tile N is next to the right edge of the inner grid:
+    assert_eq!(
+        sorted(3, 2),
+        vec![
+            (0, 3, 1),
+            (0, 3, 3),
+            (0, 4, 2),
+            (1, 4, 0),
+            (1, 4, 1),
+            (1, 4, 2),
+            (1, 4, 3),
+            (1, 4, 4)
+        ]
+    );
+}
+
+#[test]
+fn tests_example() {
+    let input = "....#
+#..#.
+#..##
+..#..
+#....";
+    assert_eq!(biodiversity(input), 2_129_920);
+    assert_eq!(bugs_after(input, 10), 99);
+    assert_eq!(bugs_after(input, 0), 8);
+}
+
+#[test]
+fn tests_grid_size() {
+    let grid = parse_grid(".#.\n..#\n#..\n");
+    assert_eq!((grid.width(), grid.height()), (3, 3));
+    assert_eq!(biodiversity_rating(&grid), 2 + 32 + 64);
+
+    let mut next_grid = grid.clone();
+    next_generation(&grid, &mut next_grid);
+    assert_eq!(next_grid, parse_grid("#.#\n##.\n.##"));
+    assert_eq!(biodiversity_rating(&next_grid), 1 + 4 + 8 + 16 + 128 + 256);
+
+    // A single row, alternating between .#.#. and #.#.# after the first minute:
+    assert_eq!(biodiversity("..#.."), 2 + 8);
+    assert_eq!(
+        biodiversity(".#\n##"),
+        biodiversity_rating(&parse_grid(".#\n##"))
+    );
+
+    assert_eq!(
+        parse("#.\n#"),
+        Err("Line 2 is not 2 tiles wide".to_string())
+    );
+    assert_eq!(parse("#x"), Err("Invalid tile: 'x'".to_string()));
+    assert!(parse(&"#".repeat(65)).is_err());
+    assert!(parse("").is_err());
+}
+
+#[test]
+#[should_panic(expected = "Recursive grids must be 5x5, not 3x3")]
+fn tests_bugs_after_grid_size() {
+    bugs_after("...\n.#.\n...", 1);
+}
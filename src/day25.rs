@@ -112,9 +112,20 @@ fn parse_output(program: &mut Program) -> Room {
     }
 }
 
-pub fn part1(input_string: &str) -> String {
-    let mut program = Program::parse(input_string);
-    let initial_room = parse_output(&mut program);
+/// What has been learnt about the ship after exploring it.
+struct ShipMap {
+    /// The safe items which have been picked up while exploring.
+    carried_items: Vec<String>,
+    /// The way from the starting room to the security checkpoint.
+    directions_to_security_checkpoint: Vec<Direction>,
+    /// The direction from the security checkpoint to the pressure-sensitive floor.
+    direction_to_pressure_sensitive_floor: Direction,
+}
+
+/// Visit all rooms of the ship, picking up all items not known to be dangerous,
+/// and return to the starting room.
+fn explore_ship(program: &mut Program) -> ShipMap {
+    let initial_room = parse_output(program);
 
     let mut blacklisted_items = HashSet::new();
     blacklisted_items.insert("infinite loop".to_string());
@@ -135,11 +146,11 @@ pub fn part1(input_string: &str) -> String {
 
     while let Some((from_room, directions_to_reach_here)) = to_visit.pop_front() {
         for &direction in directions_to_reach_here.iter() {
-            execute_command(&mut program, Command::Move(direction));
+            execute_command(program, Command::Move(direction));
         }
 
         for &direction in from_room.directions.iter() {
-            let new_room = execute_command(&mut program, Command::Move(direction));
+            let new_room = execute_command(program, Command::Move(direction));
 
             if new_room.id == from_room.id {
                 // Pushed back.
@@ -160,31 +171,43 @@ pub fn part1(input_string: &str) -> String {
                             .iter()
                             .filter(|&item| !blacklisted_items.contains(item))
                             .inspect(|&item| {
-                                execute_command(&mut program, Command::Take(&item));
+                                execute_command(program, Command::Take(&item));
                             })
                             .cloned(),
                     );
                     to_visit.push_back((new_room, new_directions));
                 }
 
-                execute_command(&mut program, Command::Move(direction.reverse()));
+                execute_command(program, Command::Move(direction.reverse()));
             }
         }
 
         // Go back to starting point.
         for &direction in directions_to_reach_here.iter().rev() {
-            execute_command(&mut program, Command::Move(direction.reverse()));
+            execute_command(program, Command::Move(direction.reverse()));
         }
     }
 
+    ShipMap {
+        carried_items,
+        directions_to_security_checkpoint,
+        direction_to_pressure_sensitive_floor,
+    }
+}
+
+/// Go from the starting room to the security checkpoint and try combinations of carried
+/// items until being let through the pressure-sensitive floor, returning the password.
+fn search_item_combinations(program: &mut Program, ship_map: &ShipMap) -> Option<i32> {
+    let carried_items = &ship_map.carried_items;
+
     // Go to security checkpoint:
-    for &direction in directions_to_security_checkpoint.iter() {
-        execute_command(&mut program, Command::Move(direction));
+    for &direction in ship_map.directions_to_security_checkpoint.iter() {
+        execute_command(program, Command::Move(direction));
     }
 
     // Drop all items:
     for item in carried_items.iter() {
-        execute_command(&mut program, Command::Drop(&item));
+        execute_command(program, Command::Drop(&item));
     }
 
     // Try all combinations of items using Gray code,
@@ -196,23 +219,35 @@ pub fn part1(input_string: &str) -> String {
         for (j, item) in carried_items.iter().enumerate() {
             let bit_mask = 1 << j;
             if gray_code & bit_mask != 0 && latest_gray_code & bit_mask == 0 {
-                execute_command(&mut program, Command::Take(&item));
+                execute_command(program, Command::Take(&item));
             } else if latest_gray_code & bit_mask != 0 && gray_code & bit_mask == 0 {
-                execute_command(&mut program, Command::Drop(&item));
+                execute_command(program, Command::Drop(&item));
             }
         }
         latest_gray_code = gray_code;
 
         let new_room = execute_command(
-            &mut program,
-            Command::Move(direction_to_pressure_sensitive_floor),
+            program,
+            Command::Move(ship_map.direction_to_pressure_sensitive_floor),
         );
-        if let Some(solution) = new_room.solution {
-            return solution.to_string();
+        if new_room.solution.is_some() {
+            return new_room.solution;
         }
     }
 
-    String::from("No solution found")
+    None
+}
+
+/// Explore the ship and find the password for the main airlock, or None if no combination
+/// of the safe items gets through the pressure-sensitive floor.
+pub fn auto_solve(input_string: &str) -> Option<String> {
+    let mut program = Program::parse(input_string);
+    let ship_map = explore_ship(&mut program);
+    search_item_combinations(&mut program, &ship_map).map(|password| password.to_string())
+}
+
+pub fn part1(input_string: &str) -> String {
+    auto_solve(input_string).unwrap_or_else(|| String::from("No solution found"))
 }
 
 pub fn part2(_input_string: &str) -> String {
@@ -223,3 +258,36 @@ pub fn part2(_input_string: &str) -> String {
 pub fn tests_part1() {
     assert_eq!(part1(include_str!("day25_input.txt")), "319815680");
 }
+
+#[test]
+fn tests_explore_ship() {
+    let mut program = Program::parse(include_str!("day25_input.txt"));
+    let ship_map = explore_ship(&mut program);
+
+    let mut carried_items = ship_map.carried_items.clone();
+    carried_items.sort();
+    assert_eq!(
+        carried_items,
+        vec![
+            "antenna",
+            "boulder",
+            "cake",
+            "coin",
+            "fuel cell",
+            "mutex",
+            "pointer",
+            "tambourine"
+        ]
+    );
+
+    // Exploring ends in the starting room, from where the checkpoint can be reached:
+    let mut room = None;
+    for &direction in ship_map.directions_to_security_checkpoint.iter() {
+        room = Some(execute_command(&mut program, Command::Move(direction)));
+    }
+    assert_eq!(room.unwrap().id, "== Security Checkpoint ==");
+    assert_eq!(
+        ship_map.direction_to_pressure_sensitive_floor,
+        Direction::EAST
+    );
+}
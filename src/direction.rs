@@ -0,0 +1,118 @@
+use crate::point::Point;
+
+/// A direction of movement on a grid where up is positive y.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub fn turn_right(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    pub fn turn_left(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+        }
+    }
+
+    /// The offset of a single step in this direction.
+    pub fn as_offset(self) -> Point {
+        match self {
+            Direction::Up => Point::new(0, 1),
+            Direction::Right => Point::new(1, 0),
+            Direction::Down => Point::new(0, -1),
+            Direction::Left => Point::new(-1, 0),
+        }
+    }
+
+    /// The direction of an intcode movement command, where 1 is north, 2 south,
+    /// 3 west and 4 east.
+    pub fn from_intcode(command: i64) -> Option<Direction> {
+        match command {
+            1 => Some(Direction::Up),
+            2 => Some(Direction::Down),
+            3 => Some(Direction::Left),
+            4 => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    /// The intcode movement command for this direction, the inverse of `from_intcode`.
+    pub fn as_intcode(self) -> i64 {
+        match self {
+            Direction::Up => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+            Direction::Right => 4,
+        }
+    }
+}
+
+#[test]
+fn tests_turns() {
+    for &start in &[
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ] {
+        let mut direction = start;
+        for _ in 0..4 {
+            direction = direction.turn_left();
+        }
+        assert_eq!(direction, start);
+
+        assert_eq!(start.turn_left().turn_right(), start);
+        assert_eq!(start.turn_right().turn_right(), start.opposite());
+        assert_eq!(start.opposite().opposite(), start);
+    }
+    assert_eq!(Direction::Up.turn_left(), Direction::Left);
+    assert_eq!(Direction::Up.turn_right(), Direction::Right);
+}
+
+#[test]
+fn tests_offsets() {
+    assert_eq!(Direction::Up.as_offset(), Point::new(0, 1));
+    assert_eq!(Direction::Right.as_offset(), Point::new(1, 0));
+    assert_eq!(Direction::Down.as_offset(), Point::new(0, -1));
+    assert_eq!(Direction::Left.as_offset(), Point::new(-1, 0));
+    assert_eq!(
+        Direction::Right.as_offset() + Direction::Right.opposite().as_offset(),
+        Point::ORIGIN
+    );
+
+    assert_eq!(Direction::from_intcode(1), Some(Direction::Up));
+    assert_eq!(Direction::from_intcode(2), Some(Direction::Down));
+    assert_eq!(Direction::from_intcode(3), Some(Direction::Left));
+    assert_eq!(Direction::from_intcode(4), Some(Direction::Right));
+    assert_eq!(Direction::from_intcode(0), None);
+    assert_eq!(Direction::from_intcode(5), None);
+    for command in 1..=4 {
+        assert_eq!(
+            Direction::from_intcode(command).map(Direction::as_intcode),
+            Some(command)
+        );
+    }
+}
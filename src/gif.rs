@@ -0,0 +1,173 @@
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Write an animated GIF image looping forever, with each frame containing one palette
+/// index per pixel in row order and being shown for `delay` hundredths of a second.
+///
+/// The palette may contain at most 128 colors. The image data is not compressed: clear
+/// codes are emitted often enough that the LZW code size never grows, which keeps the
+/// encoder small at the cost of larger files.
+pub fn write_animation(
+    path: &str,
+    width: u16,
+    height: u16,
+    palette: &[[u8; 3]],
+    frames: &[Vec<u8>],
+    delay: u16,
+) -> io::Result<()> {
+    let invalid_input = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    if palette.is_empty() || palette.len() > 128 {
+        return Err(invalid_input(format!(
+            "Expected 1 to 128 palette colors, got {}",
+            palette.len()
+        )));
+    }
+    let pixel_count = usize::from(width) * usize::from(height);
+    for frame in frames {
+        if frame.len() != pixel_count {
+            return Err(invalid_input(format!(
+                "Expected {} pixels for a {}x{} frame, got {}",
+                pixel_count,
+                width,
+                height,
+                frame.len()
+            )));
+        }
+        if let Some(&index) = frame
+            .iter()
+            .find(|&&index| usize::from(index) >= palette.len())
+        {
+            return Err(invalid_input(format!("No palette color {}", index)));
+        }
+    }
+
+    // The color table has 2^color_bits entries, where at least two bits are needed
+    // for the LZW minimum code size:
+    let color_bits = (2..=7)
+        .find(|&bits| palette.len() <= 1 << bits)
+        .unwrap_or(7);
+
+    let mut file = File::create(path)?;
+    file.write_all(b"GIF89a")?;
+    file.write_all(&width.to_le_bytes())?;
+    file.write_all(&height.to_le_bytes())?;
+    // Global color table present, with its size and the color resolution:
+    file.write_all(&[0x80 | ((color_bits - 1) << 4) | (color_bits - 1), 0, 0])?;
+    for index in 0..(1 << color_bits) {
+        file.write_all(palette.get(index).unwrap_or(&[0, 0, 0]))?;
+    }
+    // Loop forever:
+    file.write_all(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+
+    for frame in frames {
+        file.write_all(&[0x21, 0xF9, 0x04, 0x00])?;
+        file.write_all(&delay.to_le_bytes())?;
+        file.write_all(&[0x00, 0x00])?;
+
+        file.write_all(&[0x2C, 0, 0, 0, 0])?;
+        file.write_all(&width.to_le_bytes())?;
+        file.write_all(&height.to_le_bytes())?;
+        file.write_all(&[0x00, color_bits])?;
+        for block in lzw_uncompressed(frame, color_bits).chunks(255) {
+            file.write_all(&[block.len() as u8])?;
+            file.write_all(block)?;
+        }
+        file.write_all(&[0x00])?;
+    }
+
+    file.write_all(&[0x3B])
+}
+
+/// Encode pixels as an LZW stream consisting of literal codes only.
+fn lzw_uncompressed(pixels: &[u8], minimum_code_size: u8) -> Vec<u8> {
+    let clear_code = 1 << minimum_code_size;
+    let end_code = clear_code + 1;
+    let code_size = u32::from(minimum_code_size) + 1;
+    // The decoder adds a table entry for each code after the first one following a clear
+    // code, so clear the table before the code size would need to grow:
+    let max_literals = clear_code as usize - 2;
+
+    let mut result = Vec::new();
+    let mut bit_buffer = 0_u32;
+    let mut bit_count = 0;
+    let mut emit = |code: u32| {
+        bit_buffer |= code << bit_count;
+        bit_count += code_size;
+        while bit_count >= 8 {
+            result.push(bit_buffer as u8);
+            bit_buffer >>= 8;
+            bit_count -= 8;
+        }
+    };
+
+    emit(clear_code);
+    for (index, &pixel) in pixels.iter().enumerate() {
+        if index > 0 && index % max_literals == 0 {
+            emit(clear_code);
+        }
+        emit(u32::from(pixel));
+    }
+    emit(end_code);
+    if bit_count > 0 {
+        result.push(bit_buffer as u8);
+    }
+    result
+}
+
+/// The number of frames in a GIF image, or None if its block structure is invalid.
+#[cfg(test)]
+pub fn frame_count(data: &[u8]) -> Option<usize> {
+    // Skip a sequence of data sub-blocks, returning the position after the terminator:
+    let skip_sub_blocks = |mut position: usize| -> Option<usize> {
+        loop {
+            let length = usize::from(*data.get(position)?);
+            position += 1 + length;
+            if length == 0 {
+                return Some(position);
+            }
+        }
+    };
+
+    let flags = *data.get(10)?;
+    let mut position = 13;
+    if flags & 0x80 != 0 {
+        position += 3 << ((flags & 0x07) + 1);
+    }
+
+    let mut frames = 0;
+    loop {
+        match data.get(position)? {
+            0x21 => position = skip_sub_blocks(position + 2)?,
+            0x2C => {
+                frames += 1;
+                position = skip_sub_blocks(position + 11)?;
+            }
+            0x3B => return Some(frames),
+            _ => return None,
+        }
+    }
+}
+
+#[test]
+fn tests_lzw_uncompressed() {
+    // With a minimum code size of 2 there are 3-bit codes, clear code 4 and end code 5,
+    // with the table cleared every second literal. The codes 4, 1, 2, 4, 3, 5 are packed
+    // starting from the least significant bit:
+    assert_eq!(
+        lzw_uncompressed(&[1, 2, 3], 2),
+        vec![0b1000_1100, 0b1011_1000, 0b0000_0010]
+    );
+    assert_eq!(lzw_uncompressed(&[], 2), vec![0b0010_1100]);
+}
+
+#[test]
+fn tests_frame_count() {
+    let path = std::env::temp_dir().join("advent_of_code_gif.gif");
+    let path = path.to_str().unwrap();
+    let frames = vec![vec![0, 1, 1, 0]; 3];
+    write_animation(path, 2, 2, &[[0, 0, 0], [0xFF, 0xFF, 0xFF]], &frames, 10).unwrap();
+    let gif = std::fs::read(path).unwrap();
+    assert_eq!(frame_count(&gif), Some(3));
+    assert_eq!(frame_count(&gif[..gif.len() - 1]), None);
+    std::fs::remove_file(path).unwrap();
+}
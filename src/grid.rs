@@ -0,0 +1,305 @@
+use crate::parse;
+
+/// Offsets to the four orthogonal neighbors of a cell.
+const NEIGHBOR_OFFSETS_4: [(i32, i32); 4] = [(0, 1), (0, -1), (-1, 0), (1, 0)];
+
+/// Offsets to the eight neighbors of a cell, including diagonal ones.
+const NEIGHBOR_OFFSETS_8: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// A rectangular grid of cells, with (0, 0) being the top left corner and y growing downwards.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl Grid<char> {
+    /// Parse a grid with one row per line, where lines shorter than the longest one
+    /// are padded with spaces and trailing whitespace of the input is ignored.
+    pub fn parse(input: &str) -> Grid<char> {
+        let rows: Vec<Vec<char>> = parse::normalize(input)
+            .lines()
+            .map(|line| line.chars().collect())
+            .collect();
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let height = rows.len();
+        let mut cells = Vec::with_capacity(width * height);
+        for row in rows {
+            let row_end = cells.len() + width;
+            cells.extend(row);
+            cells.resize(row_end, ' ');
+        }
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn in_bounds(&self, (x, y): (i32, i32)) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    /// The cell at the given position, or None if it is outside the grid.
+    pub fn get(&self, position: (i32, i32)) -> Option<&T> {
+        if self.in_bounds(position) {
+            Some(&self.cells[position.1 as usize * self.width + position.0 as usize])
+        } else {
+            None
+        }
+    }
+
+    /// The orthogonal neighbors of a position which are inside the grid.
+    pub fn neighbors4(&self, position: (i32, i32)) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.neighbors(position, &NEIGHBOR_OFFSETS_4)
+    }
+
+    /// The orthogonal and diagonal neighbors of a position which are inside the grid.
+    pub fn neighbors8(&self, position: (i32, i32)) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.neighbors(position, &NEIGHBOR_OFFSETS_8)
+    }
+
+    fn neighbors<'a>(
+        &'a self,
+        (x, y): (i32, i32),
+        offsets: &'a [(i32, i32)],
+    ) -> impl Iterator<Item = (i32, i32)> + 'a {
+        offsets
+            .iter()
+            .map(move |&(dx, dy)| (x + dx, y + dy))
+            .filter(move |&neighbor| self.in_bounds(neighbor))
+    }
+
+    /// All positions in the grid, row by row.
+    pub fn iter_coords(&self) -> impl Iterator<Item = (i32, i32)> {
+        let width = self.width as i32;
+        let height = self.height as i32;
+        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+}
+
+impl<T> std::ops::Index<(i32, i32)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, position: (i32, i32)) -> &T {
+        self.get(position)
+            .unwrap_or_else(|| panic!("Position outside grid: {:?}", position))
+    }
+}
+
+/// A fixed size grid indexed by `(x, y)`, for simulations updating every cell in each step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid2D<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid2D<T> {
+    /// A grid with all cells set to `value`.
+    pub fn new(width: usize, height: usize, value: T) -> Grid2D<T> {
+        Grid2D {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+}
+
+impl<T> Grid2D<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The cell at the given position, or None if it is outside the grid.
+    pub fn get(&self, (x, y): (usize, usize)) -> Option<&T> {
+        if x < self.width && y < self.height {
+            Some(&self.cells[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// Swap the contents of two cells.
+    pub fn swap(&mut self, a: (usize, usize), b: (usize, usize)) {
+        let (a, b) = (self.offset(a), self.offset(b));
+        self.cells.swap(a, b);
+    }
+
+    /// The orthogonal neighbors of a position which are inside the grid.
+    pub fn neighbors(&self, (x, y): (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        NEIGHBOR_OFFSETS_4
+            .iter()
+            .map(move |&(dx, dy)| (x as i32 + dx, y as i32 + dy))
+            .filter(move |&(x, y)| x >= 0 && y >= 0 && x < width && y < height)
+            .map(|(x, y)| (x as usize, y as usize))
+    }
+
+    /// All cells, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    fn offset(&self, (x, y): (usize, usize)) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "Position outside grid: {:?}",
+            (x, y)
+        );
+        y * self.width + x
+    }
+}
+
+impl<T> std::ops::Index<(usize, usize)> for Grid2D<T> {
+    type Output = T;
+
+    fn index(&self, position: (usize, usize)) -> &T {
+        &self.cells[self.offset(position)]
+    }
+}
+
+impl<T> std::ops::IndexMut<(usize, usize)> for Grid2D<T> {
+    fn index_mut(&mut self, position: (usize, usize)) -> &mut T {
+        let offset = self.offset(position);
+        &mut self.cells[offset]
+    }
+}
+
+#[test]
+fn tests_parse() {
+    let grid = Grid::parse("#.#\n..\n");
+    assert_eq!((grid.width(), grid.height()), (3, 2));
+    assert_eq!(grid.get((0, 0)), Some(&'#'));
+    assert_eq!(grid.get((1, 1)), Some(&'.'));
+    assert_eq!(grid[(2, 0)], '#');
+    assert_eq!(grid.get((2, 1)), Some(&' '));
+    assert_eq!(grid.get((3, 0)), None);
+    assert_eq!(grid.get((0, -1)), None);
+    assert!(grid.in_bounds((2, 1)));
+    assert!(!grid.in_bounds((-1, 0)));
+    assert_eq!(
+        grid.iter_coords().collect::<Vec<_>>(),
+        vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+    );
+
+    // Trailing blank lines and CRLF line endings do not give extra rows or columns:
+    assert_eq!(Grid::parse("#.#\n..\n\n  \n"), grid);
+    assert_eq!(Grid::parse("#.#\r\n..\r\n"), grid);
+}
+
+#[test]
+fn tests_neighbors() {
+    let grid = Grid::parse("...\n...\n...");
+    let sorted = |neighbors: Vec<(i32, i32)>| {
+        let mut neighbors = neighbors;
+        neighbors.sort();
+        neighbors
+    };
+
+    // Corner:
+    assert_eq!(
+        sorted(grid.neighbors4((0, 0)).collect()),
+        vec![(0, 1), (1, 0)]
+    );
+    assert_eq!(
+        sorted(grid.neighbors8((0, 0)).collect()),
+        vec![(0, 1), (1, 0), (1, 1)]
+    );
+    assert_eq!(
+        sorted(grid.neighbors8((2, 2)).collect()),
+        vec![(1, 1), (1, 2), (2, 1)]
+    );
+
+    // Edge:
+    assert_eq!(
+        sorted(grid.neighbors4((1, 0)).collect()),
+        vec![(0, 0), (1, 1), (2, 0)]
+    );
+    assert_eq!(grid.neighbors8((2, 1)).count(), 5);
+
+    // Center:
+    assert_eq!(grid.neighbors4((1, 1)).count(), 4);
+    assert_eq!(grid.neighbors8((1, 1)).count(), 8);
+}
+
+#[test]
+fn tests_grid_2d_indexing() {
+    let mut grid = Grid2D::new(4, 3, 0);
+    assert_eq!((grid.width(), grid.height()), (4, 3));
+    for y in 0..3 {
+        for x in 0..4 {
+            grid[(x, y)] = x * 10 + y;
+        }
+    }
+    for y in 0..3 {
+        for x in 0..4 {
+            assert_eq!(grid[(x, y)], x * 10 + y);
+            assert_eq!(grid.get((x, y)), Some(&(x * 10 + y)));
+        }
+    }
+    assert_eq!(grid.get((4, 0)), None);
+    assert_eq!(grid.get((0, 3)), None);
+    assert_eq!(
+        grid.iter().take(5).cloned().collect::<Vec<_>>(),
+        vec![0, 10, 20, 30, 1]
+    );
+
+    grid.swap((0, 0), (3, 2));
+    assert_eq!((grid[(0, 0)], grid[(3, 2)]), (32, 0));
+}
+
+#[test]
+#[should_panic]
+fn tests_grid_2d_index_out_of_bounds() {
+    let grid = Grid2D::new(2, 2, '.');
+    let _ = grid[(2, 0)];
+}
+
+#[test]
+fn tests_grid_2d_neighbors() {
+    let grid = Grid2D::new(3, 2, false);
+    let sorted = |neighbors: Vec<(usize, usize)>| {
+        let mut neighbors = neighbors;
+        neighbors.sort();
+        neighbors
+    };
+
+    assert_eq!(
+        sorted(grid.neighbors((0, 0)).collect()),
+        vec![(0, 1), (1, 0)]
+    );
+    assert_eq!(
+        sorted(grid.neighbors((2, 1)).collect()),
+        vec![(1, 1), (2, 0)]
+    );
+    assert_eq!(
+        sorted(grid.neighbors((1, 0)).collect()),
+        vec![(0, 0), (1, 1), (2, 0)]
+    );
+    assert_eq!(Grid2D::new(1, 1, 0).neighbors((0, 0)).count(), 0);
+}
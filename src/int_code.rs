@@ -1,14 +1,101 @@
-use std::collections::{HashMap, VecDeque};
+use crate::parse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::str::FromStr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::JoinHandle;
+
+/// Callback invoked with the address and opcode (including parameter modes) of each
+/// instruction before it is executed.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+struct TraceHook(Arc<Mutex<dyn FnMut(usize, i64) + Send>>);
+
+impl fmt::Debug for TraceHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TraceHook")
+    }
+}
+
+/// Source of input values used once the input queue is empty.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+struct InputIter(Arc<Mutex<dyn Iterator<Item = i64> + Send>>);
+
+impl fmt::Debug for InputIter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InputIter")
+    }
+}
+
+/// The state of a program before executing an instruction, for stepping back.
+#[derive(Clone, Debug)]
+struct Snapshot {
+    memory: HashMap<usize, i64>,
+    instruction_pointer: usize,
+    output_values: VecDeque<i64>,
+    input_values: VecDeque<i64>,
+    halted: bool,
+    requires_input_to: Option<usize>,
+    relative_base: i64,
+}
+
+/// The snapshots before the most recently executed instructions, oldest first.
+#[derive(Clone, Debug)]
+struct History {
+    capacity: usize,
+    snapshots: VecDeque<Snapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExecutionError {
+    /// An add or multiply instruction overflowed while checked arithmetic was enabled.
+    ArithmeticOverflow { ip: usize },
+    /// The instruction at the instruction pointer has an unknown opcode.
+    InvalidOpcode { ip: usize, opcode: i64 },
+    /// The program needs more input than what has been supplied.
+    NeedsInput,
+    /// All connected programs which have not halted need input which none of them will produce.
+    Deadlock,
+}
 
 #[derive(Clone, Debug)]
 pub struct Program {
     memory: HashMap<usize, i64>,
     instruction_pointer: usize,
-    output_values: Vec<i64>,
+    output_values: VecDeque<i64>,
     input_values: VecDeque<i64>,
     halted: bool,
     requires_input_to: Option<usize>,
     relative_base: i64,
+    trace_hook: Option<TraceHook>,
+    input_iter: Option<InputIter>,
+    history: Option<History>,
+    checked_arithmetic: bool,
+}
+
+impl FromStr for Program {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Program, String> {
+        Program::try_parse(input)
+    }
+}
+
+/// Options for `Program::with_options`, where the defaults match `Program::parse`.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    /// The relative base the program starts with.
+    pub initial_relative_base: i64,
+    /// The number of memory cells to reserve room for up front, with room always
+    /// being reserved for the program itself.
+    pub memory_reserve: usize,
 }
 
 enum Parameter {
@@ -18,26 +105,98 @@ enum Parameter {
 
 impl Program {
     pub fn parse(input: &str) -> Program {
-        Program {
-            memory: input
-                .split(',')
-                .map(|s| s.parse::<i64>().unwrap())
-                .enumerate()
-                .collect(),
+        Program::try_parse(input).unwrap()
+    }
+
+    pub fn try_parse(input: &str) -> Result<Program, String> {
+        Program::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &str, options: ParseOptions) -> Result<Program, String> {
+        let values = parse::ints(&parse::normalize(input))
+            .map_err(|error| format!("Invalid program value '{}': {}", error.token, error.error))?;
+
+        let mut memory =
+            HashMap::with_capacity(std::cmp::max(values.len(), options.memory_reserve));
+        memory.extend(values.into_iter().enumerate());
+
+        Ok(Program {
+            memory,
             instruction_pointer: 0,
-            output_values: Vec::new(),
+            output_values: VecDeque::new(),
             input_values: VecDeque::new(),
             halted: false,
             requires_input_to: None,
-            relative_base: 0,
-        }
+            relative_base: options.initial_relative_base,
+            trace_hook: None,
+            input_iter: None,
+            history: None,
+            checked_arithmetic: false,
+        })
     }
 
     pub fn is_halted(&self) -> bool {
         self.halted
     }
 
+    /// If the program is waiting for input and there is no queued input to continue with.
+    pub fn blocked_on_input(&self) -> bool {
+        !self.can_run() && !self.halted
+    }
+
+    /// Make add and multiply instructions fail with `ExecutionError::ArithmeticOverflow` on
+    /// overflow instead of wrapping around.
+    pub fn set_checked_arithmetic(&mut self, checked: bool) {
+        self.checked_arithmetic = checked;
+    }
+
     pub fn run_for_register0(&mut self) -> i64 {
+        self.run().unwrap();
+        self.read_memory(0)
+    }
+
+    pub fn run_for_output(&mut self) -> Vec<i64> {
+        self.try_run_for_output().unwrap()
+    }
+
+    pub fn try_run_for_output(&mut self) -> Result<Vec<i64>, ExecutionError> {
+        self.run()?;
+        Ok(Vec::from(std::mem::take(&mut self.output_values)))
+    }
+
+    /// Run until `n` values have been output and return them, or fewer if the program
+    /// halts or needs input which is missing before that. Output is returned in the
+    /// order it was produced.
+    pub fn run_for_n_outputs(&mut self, n: usize) -> Vec<i64> {
+        self.try_run_for_n_outputs(n).unwrap()
+    }
+
+    pub fn try_run_for_n_outputs(&mut self, n: usize) -> Result<Vec<i64>, ExecutionError> {
+        self.consume_queued_input();
+        while self.output_values.len() < n && !self.halted && self.requires_input_to.is_none() {
+            self.evaluate()?;
+        }
+        let num_outputs = std::cmp::min(n, self.output_values.len());
+        Ok(self.output_values.drain(..num_outputs).collect())
+    }
+
+    /// Run until the program halts, feeding it queued input and returning all output.
+    pub fn run_to_halt(&mut self) -> Vec<i64> {
+        self.try_run_to_halt().unwrap()
+    }
+
+    /// Run until the program halts, failing with `ExecutionError::NeedsInput` if
+    /// the queued input runs out before that.
+    pub fn try_run_to_halt(&mut self) -> Result<Vec<i64>, ExecutionError> {
+        self.run_until_blocked()?;
+        if self.halted {
+            Ok(Vec::from(std::mem::take(&mut self.output_values)))
+        } else {
+            Err(ExecutionError::NeedsInput)
+        }
+    }
+
+    fn run(&mut self) -> Result<(), ExecutionError> {
         if self.requires_input_to != None {
             panic!("Cannot run program requiring input");
         } else if self.halted {
@@ -45,14 +204,118 @@ impl Program {
         }
 
         while !self.halted && self.requires_input_to == None {
-            self.evaluate();
+            self.evaluate()?;
         }
-        self.read_memory(0)
+        Ok(())
     }
 
-    pub fn run_for_output(&mut self) -> Vec<i64> {
-        self.run_for_register0();
-        std::mem::replace(&mut self.output_values, Vec::new())
+    /// If the program is not halted and not waiting on input which is missing.
+    fn can_run(&self) -> bool {
+        !self.halted && (self.requires_input_to.is_none() || !self.input_values.is_empty())
+    }
+
+    /// Run until halted or until input is required but not available in the input queue.
+    fn run_until_blocked(&mut self) -> Result<(), ExecutionError> {
+        self.consume_queued_input();
+        while !self.halted && self.requires_input_to.is_none() {
+            self.evaluate()?;
+        }
+        Ok(())
+    }
+
+    /// Satisfy a pending input request from the input queue, if possible.
+    fn consume_queued_input(&mut self) {
+        if let Some(save_address) = self.requires_input_to {
+            if let Some(input_value) = self.next_input() {
+                self.write_memory(save_address, input_value);
+                self.requires_input_to = None;
+            }
+        }
+    }
+
+    /// Compute a hash of the first outputs produced by (a copy of) this program when fed
+    /// a fixed probe input.
+    ///
+    /// This is a heuristic for telling programs apart, e.g. to guess which puzzle a program
+    /// is for: different programs may produce the same fingerprint, and a program which does
+    /// not produce output from the probe within a bounded number of steps, or which fails
+    /// with an execution error such as an invalid opcode, is only identified by the outputs
+    /// (if any) it did produce.
+    pub fn fingerprint(&self) -> u64 {
+        const PROBE_INPUT: [i64; 4] = [0, 1, 0, 1];
+        const MAX_OUTPUTS: usize = 16;
+        const MAX_STEPS: usize = 100_000;
+
+        let mut program = self.clone();
+        program.trace_hook = None;
+        program.input_iter = None;
+        program.history = None;
+        for &value in PROBE_INPUT.iter() {
+            program.input(value);
+        }
+
+        for _ in 0..MAX_STEPS {
+            program.consume_queued_input();
+            if !program.can_run()
+                || program.output_values.len() >= MAX_OUTPUTS
+                || program.evaluate().is_err()
+            {
+                break;
+            }
+        }
+
+        let mut hasher = DefaultHasher::new();
+        program
+            .output_values
+            .iter()
+            .take(MAX_OUTPUTS)
+            .for_each(|value| value.hash(&mut hasher));
+        hasher.finish()
+    }
+
+    pub fn set_trace_hook<F: FnMut(usize, i64) + Send + 'static>(&mut self, hook: F) {
+        self.trace_hook = Some(TraceHook(Arc::new(Mutex::new(hook))));
+    }
+
+    pub fn clear_trace_hook(&mut self) {
+        self.trace_hook = None;
+    }
+
+    /// Run until halted or blocked on input, returning the addresses of all executed instructions.
+    ///
+    /// Any trace hook set on the program is kept, also if running it fails.
+    pub fn coverage(&mut self) -> Result<HashSet<usize>, ExecutionError> {
+        let covered = Arc::new(Mutex::new(HashSet::new()));
+        let covered_by_hook = covered.clone();
+
+        let previous_hook = self.trace_hook.take();
+        self.set_trace_hook(move |address, _| {
+            covered_by_hook.lock().unwrap().insert(address);
+        });
+        let result = self.run_until_blocked();
+        self.trace_hook = previous_hook;
+        result?;
+
+        let covered = std::mem::take(&mut *covered.lock().unwrap());
+        Ok(covered)
+    }
+
+    /// Take input from the given iterator whenever the input queue is empty. Once the
+    /// iterator is exhausted, reading input blocks as with an empty queue, e.g. failing
+    /// `try_run_to_halt` with `ExecutionError::NeedsInput`.
+    ///
+    /// Clones of the program share the iterator.
+    pub fn set_input_iter(&mut self, it: impl Iterator<Item = i64> + Send + 'static) {
+        self.input_iter = Some(InputIter(Arc::new(Mutex::new(it))));
+    }
+
+    /// The next value from the input queue, or from the input iterator if the queue is empty.
+    fn next_input(&mut self) -> Option<i64> {
+        match (self.input_values.pop_front(), &self.input_iter) {
+            (Some(input_value), _) => Some(input_value),
+            (None, Some(InputIter(it))) => it.lock().unwrap().next(),
+            (None, None) => None,
+        }
     }
 
     pub fn input(&mut self, input_value: i64) {
@@ -101,28 +364,104 @@ impl Program {
         }
     }
 
-    fn evaluate(&mut self) {
+    /// Remember the state before each executed instruction, keeping the `capacity` most
+    /// recent ones so that `step_back` can undo up to that many instructions.
+    ///
+    /// This copies the whole memory for every instruction, so is only meant for debugging.
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.history = Some(History {
+            capacity,
+            snapshots: VecDeque::with_capacity(capacity),
+        });
+    }
+
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Execute a single instruction, after first satisfying a pending input request from
+    /// the input queue. Does nothing if the program has halted.
+    pub fn step(&mut self) -> Result<(), ExecutionError> {
+        self.consume_queued_input();
+        if self.halted {
+            Ok(())
+        } else if self.requires_input_to.is_some() {
+            Err(ExecutionError::NeedsInput)
+        } else {
+            self.evaluate()
+        }
+    }
+
+    /// Undo the most recently executed instruction, returning false if there is no history
+    /// left to step back to. Values taken from an input iterator are not given back.
+    pub fn step_back(&mut self) -> bool {
+        let snapshot = match self.history.as_mut().and_then(|h| h.snapshots.pop_back()) {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+        self.memory = snapshot.memory;
+        self.instruction_pointer = snapshot.instruction_pointer;
+        self.output_values = snapshot.output_values;
+        self.input_values = snapshot.input_values;
+        self.halted = snapshot.halted;
+        self.requires_input_to = snapshot.requires_input_to;
+        self.relative_base = snapshot.relative_base;
+        true
+    }
+
+    fn record_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            if history.capacity == 0 {
+                return;
+            }
+            if history.snapshots.len() == history.capacity {
+                history.snapshots.pop_front();
+            }
+            history.snapshots.push_back(Snapshot {
+                memory: self.memory.clone(),
+                instruction_pointer: self.instruction_pointer,
+                output_values: self.output_values.clone(),
+                input_values: self.input_values.clone(),
+                halted: self.halted,
+                requires_input_to: self.requires_input_to,
+                relative_base: self.relative_base,
+            });
+        }
+    }
+
+    fn evaluate(&mut self) -> Result<(), ExecutionError> {
+        self.record_history();
         let opcode_and_parameter_modes = self.read_memory(self.instruction_pointer);
+        if let Some(TraceHook(hook)) = &self.trace_hook {
+            (hook.lock().unwrap())(self.instruction_pointer, opcode_and_parameter_modes);
+        }
         let opcode = opcode_and_parameter_modes % 100;
         match opcode {
             1 | 2 => {
                 let parameter1 = self.parameter_value(opcode_and_parameter_modes, 1);
                 let parameter2 = self.parameter_value(opcode_and_parameter_modes, 2);
                 let output_location = self.output_location(opcode_and_parameter_modes, 3);
-                self.write_memory(
-                    output_location as usize,
-                    if opcode == 1 {
-                        parameter1 + parameter2
-                    } else {
-                        parameter1 * parameter2
-                    },
-                );
+                let output_value = match (opcode == 1, self.checked_arithmetic) {
+                    (true, false) => parameter1.wrapping_add(parameter2),
+                    (false, false) => parameter1.wrapping_mul(parameter2),
+                    (true, true) => parameter1.checked_add(parameter2).ok_or(
+                        ExecutionError::ArithmeticOverflow {
+                            ip: self.instruction_pointer,
+                        },
+                    )?,
+                    (false, true) => parameter1.checked_mul(parameter2).ok_or(
+                        ExecutionError::ArithmeticOverflow {
+                            ip: self.instruction_pointer,
+                        },
+                    )?,
+                };
+                self.write_memory(output_location, output_value);
                 self.instruction_pointer += 4;
             }
             3 => {
                 // Takes a single integer as input and saves it to the address given by its only parameter.
                 let output_location = self.output_location(opcode_and_parameter_modes, 1);
-                if let Some(input_value) = self.input_values.pop_front() {
+                if let Some(input_value) = self.next_input() {
                     self.write_memory(output_location as usize, input_value);
                 } else {
                     self.requires_input_to = Some(output_location as usize);
@@ -132,7 +471,7 @@ impl Program {
             4 => {
                 // Opcode 4 outputs the value of its only parameter.
                 self.output_values
-                    .push(self.parameter_value(opcode_and_parameter_modes, 1));
+                    .push_back(self.parameter_value(opcode_and_parameter_modes, 1));
                 self.instruction_pointer += 2;
             }
             5 | 6 => {
@@ -176,9 +515,13 @@ impl Program {
                 self.halted = true;
             }
             _ => {
-                panic!("Invalid opcode: {}", opcode);
+                return Err(ExecutionError::InvalidOpcode {
+                    ip: self.instruction_pointer,
+                    opcode,
+                });
             }
         }
+        Ok(())
     }
 
     fn read_memory(&self, address: usize) -> i64 {
@@ -188,4 +531,648 @@ impl Program {
     pub fn write_memory(&mut self, address: usize, value: i64) {
         self.memory.insert(address, value);
     }
+
+    /// Write the memory of the program to a file as comma separated integers, in the same
+    /// format as the program input, with unwritten addresses up to the highest one as zero.
+    pub fn dump_memory(&self, path: &str) -> io::Result<()> {
+        let memory_size = self.memory.keys().max().map_or(0, |&address| address + 1);
+        let values: Vec<String> = (0..memory_size)
+            .map(|address| self.read_memory(address).to_string())
+            .collect();
+        std::fs::write(path, values.join(","))
+    }
+
+    /// Replace the memory of the program with the one dumped to a file by `dump_memory`.
+    ///
+    /// The instruction pointer, relative base and input and output queues are left as is.
+    pub fn load_memory(&mut self, path: &str) -> io::Result<()> {
+        let contents = std::fs::read_to_string(path)?;
+        let memory = parse::ints(&contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+        self.memory = memory.into_iter().enumerate().collect();
+        Ok(())
+    }
+
+    /// A listing of the memory of the program, decoding instructions one after another
+    /// from address zero and showing words which are not valid instructions as data.
+    ///
+    /// Addresses which are the immediate target of a jump are labeled (such as `L0012:`)
+    /// and the jump instructions refer to these labels.
+    pub fn disassemble(&self) -> String {
+        let memory_size = self.memory.keys().max().map_or(0, |&address| address + 1);
+        let memory: Vec<i64> = (0..memory_size)
+            .map(|address| self.read_memory(address))
+            .collect();
+
+        let mut instructions = Vec::new();
+        let mut address = 0;
+        while address < memory.len() {
+            let instruction = decode_instruction(&memory, address);
+            let length = instruction
+                .as_ref()
+                .map_or(1, |(_, operands)| operands.len() + 1);
+            instructions.push((address, instruction));
+            address += length;
+        }
+
+        let jump_targets: HashSet<i64> = instructions
+            .iter()
+            .filter_map(|(_, instruction)| match instruction {
+                Some(("JT", operands)) | Some(("JF", operands)) => match operands[1] {
+                    (1, target) => Some(target),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+
+        let mut listing = String::new();
+        for (address, instruction) in instructions {
+            if jump_targets.contains(&(address as i64)) {
+                listing.push_str(&format!("L{:04}:\n", address));
+            }
+            let text = match instruction {
+                Some((mnemonic, operands)) => {
+                    let is_jump = mnemonic == "JT" || mnemonic == "JF";
+                    let operands: Vec<String> = operands
+                        .iter()
+                        .enumerate()
+                        .map(|(index, &(mode, value))| match mode {
+                            1 if is_jump && index == 1 && jump_targets.contains(&value) => {
+                                format!("L{:04}", value)
+                            }
+                            1 => format!("#{}", value),
+                            2 if value < 0 => format!("[rb{}]", value),
+                            2 => format!("[rb+{}]", value),
+                            _ => format!("[{}]", value),
+                        })
+                        .collect();
+                    if operands.is_empty() {
+                        mnemonic.to_string()
+                    } else {
+                        format!("{} {}", mnemonic, operands.join(", "))
+                    }
+                }
+                None => format!("DATA {}", memory[address]),
+            };
+            listing.push_str(&format!("{:04}: {}\n", address, text));
+        }
+        listing
+    }
+}
+
+/// The mnemonic and the (mode, value) operands of the instruction at the given address,
+/// or None if the word there is not a valid instruction or its operands are out of memory.
+#[allow(clippy::type_complexity)]
+fn decode_instruction(memory: &[i64], address: usize) -> Option<(&'static str, Vec<(u8, i64)>)> {
+    let opcode_and_parameter_modes = memory[address];
+    let (mnemonic, parameter_count) = match opcode_and_parameter_modes % 100 {
+        1 => ("ADD", 3),
+        2 => ("MUL", 3),
+        3 => ("IN", 1),
+        4 => ("OUT", 1),
+        5 => ("JT", 2),
+        6 => ("JF", 2),
+        7 => ("LT", 3),
+        8 => ("EQ", 3),
+        9 => ("ARB", 1),
+        99 => ("HLT", 0),
+        _ => return None,
+    };
+    if opcode_and_parameter_modes < 0
+        || opcode_and_parameter_modes >= 100 * 10_i64.pow(parameter_count)
+        || address + parameter_count as usize >= memory.len()
+    {
+        return None;
+    }
+
+    let mut operands = Vec::new();
+    for position in 1..=parameter_count {
+        let mode = ((opcode_and_parameter_modes / 10_i64.pow(position + 1)) % 10) as u8;
+        if mode > 2 {
+            return None;
+        }
+        operands.push((mode, memory[address + position as usize]));
+    }
+    Some((mnemonic, operands))
+}
+
+/// Connect two programs into a ring where the output of each program is the input of the other.
+///
+/// The output queue of one program is swapped in as the input queue of the other while it runs,
+/// so values are never copied between separate buffers. Runs until both programs have halted
+/// (or neither can continue due to missing input) and returns the last value output by `b`.
+///
+/// Fails with the execution error of the first program which fails to run.
+pub fn couple(a: &mut Program, b: &mut Program) -> Result<Option<i64>, ExecutionError> {
+    let mut last_output = None;
+
+    loop {
+        std::mem::swap(&mut a.output_values, &mut b.input_values);
+        let result = a.run_until_blocked();
+        std::mem::swap(&mut a.output_values, &mut b.input_values);
+        result?;
+
+        std::mem::swap(&mut b.output_values, &mut a.input_values);
+        let length_before = b.output_values.len();
+        let result = b.run_until_blocked();
+        if b.output_values.len() > length_before {
+            last_output = b.output_values.back().copied();
+        }
+        std::mem::swap(&mut b.output_values, &mut a.input_values);
+        result?;
+
+        if !a.can_run() && !b.can_run() {
+            return Ok(last_output);
+        }
+    }
+}
+
+/// A program running on its own thread, receiving input and sending output over channels.
+///
+/// The thread runs the program whenever input arrives and exits when the program halts
+/// or fails to run, or when the `ThreadedProgram` is joined or dropped while the program
+/// waits for input.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ThreadedProgram {
+    input: Sender<i64>,
+    output: Receiver<i64>,
+    handle: JoinHandle<Result<Program, ExecutionError>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ThreadedProgram {
+    pub fn spawn(mut program: Program) -> ThreadedProgram {
+        let (input, input_receiver) = channel();
+        let (output_sender, output) = channel();
+
+        let handle = std::thread::spawn(move || {
+            loop {
+                let result = program.run_until_blocked();
+                for value in program.output_values.drain(..) {
+                    // The receiving end may have been dropped if no one cares about output.
+                    let _ = output_sender.send(value);
+                }
+                result?;
+                if program.halted {
+                    break;
+                }
+                match input_receiver.recv() {
+                    Ok(value) => program.input(value),
+                    Err(_) => break,
+                }
+            }
+            Ok(program)
+        });
+
+        ThreadedProgram {
+            input,
+            output,
+            handle,
+        }
+    }
+
+    /// Send an input value to the program, returning false if its thread has exited.
+    pub fn send_input(&self, value: i64) -> bool {
+        self.input.send(value).is_ok()
+    }
+
+    /// Wait for the next output value, or None if the program has exited without more output.
+    pub fn recv_output(&self) -> Option<i64> {
+        self.output.recv().ok()
+    }
+
+    /// Stop sending input and wait for the program to either halt or use up the input
+    /// already sent, returning it or the execution error it failed with.
+    pub fn join(self) -> Result<Program, ExecutionError> {
+        let ThreadedProgram { input, handle, .. } = self;
+        drop(input);
+        handle.join().expect("Program thread panicked")
+    }
+}
+
+/// Connect programs into a ring where the output of each program is the input of the next,
+/// running them in turn until the last program halts and returning the last value it output.
+///
+/// Fails with `ExecutionError::Deadlock` if no program is able to continue, since each one
+/// which has not halted is waiting for input which is not queued.
+pub fn run_ring(programs: &mut [Program]) -> Result<Option<i64>, ExecutionError> {
+    let mut last_output = None;
+
+    loop {
+        let mut made_progress = false;
+        for i in 0..programs.len() {
+            if !programs[i].can_run() {
+                continue;
+            }
+            made_progress = true;
+
+            programs[i].run_until_blocked()?;
+            let output = std::mem::take(&mut programs[i].output_values);
+            if i == programs.len() - 1 {
+                if let Some(&value) = output.back() {
+                    last_output = Some(value);
+                }
+                if programs[i].is_halted() {
+                    return Ok(last_output);
+                }
+            }
+
+            let next = &mut programs[(i + 1) % programs.len()];
+            for value in output {
+                next.input(value);
+            }
+        }
+
+        if !made_progress {
+            return Err(ExecutionError::Deadlock);
+        }
+    }
+}
+
+#[test]
+fn tests_couple() {
+    let source =
+        "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+
+    // Same as the day 7 part 2 feedback loop, but with two amplifiers:
+    let mut expected = 0;
+    let mut amplifiers = [Program::parse(source), Program::parse(source)];
+    amplifiers[0].input(9);
+    amplifiers[1].input(8);
+    amplifiers[0].input(0);
+    'outer: loop {
+        for i in 0..2 {
+            let output = amplifiers[i].run_for_output();
+            if i == 1 {
+                if let Some(&value) = output.last() {
+                    expected = value;
+                }
+                if amplifiers[i].is_halted() {
+                    break 'outer;
+                }
+            }
+            for value in output {
+                amplifiers[(i + 1) % 2].input(value);
+            }
+        }
+    }
+
+    let mut a = Program::parse(source);
+    let mut b = Program::parse(source);
+    a.input(9);
+    b.input(8);
+    a.input(0);
+    assert_eq!(couple(&mut a, &mut b), Ok(Some(expected)));
+    assert_eq!(expected, 4_774);
+    assert!(a.is_halted());
+    assert!(b.is_halted());
+
+    // A program with an invalid opcode fails instead of panicking, after passing on its output:
+    let mut a = Program::parse("4,0,42");
+    let mut b = Program::parse("3,100,4,100,99");
+    assert_eq!(
+        couple(&mut a, &mut b),
+        Err(ExecutionError::InvalidOpcode { ip: 2, opcode: 42 })
+    );
+    assert_eq!(b.input_values, VecDeque::from(vec![4]));
+}
+
+#[test]
+fn tests_from_str() {
+    let mut program = "104,42,99".parse::<Program>().unwrap();
+    assert_eq!(program.run_for_output(), vec![42]);
+
+    assert!("104,x,99".parse::<Program>().is_err());
+
+    fn output_of(source: &str) -> Result<Vec<i64>, String> {
+        let mut program: Program = source.parse()?;
+        Ok(program.run_for_output())
+    }
+    assert_eq!(output_of("1101,2,3,5,104,0,99"), Ok(vec![5]));
+    assert_eq!(output_of("1101,2,3,5,\n104,0,99\n"), Ok(vec![5]));
+    assert_eq!(output_of("1101,2,3,5,\r\n104,0,99\r\n\r\n"), Ok(vec![5]));
+    assert_eq!(
+        output_of("1101,2,,5,104,0,99"),
+        Err("Invalid program value '': cannot parse integer from empty string".to_string())
+    );
+}
+
+#[test]
+fn tests_coverage() {
+    // Jumps directly from address 0 to 7, skipping the output instructions at 3 and 5:
+    let mut program = Program::parse("1105,1,7,104,1,104,2,104,3,99");
+    let coverage = program.coverage().unwrap();
+    assert_eq!(coverage, [0, 7, 9].iter().copied().collect());
+    assert!(!coverage.contains(&3));
+    assert!(!coverage.contains(&5));
+    assert!(program.is_halted());
+
+    // An invalid opcode fails the coverage run, while keeping the previous trace hook:
+    let traced = Arc::new(Mutex::new(Vec::new()));
+    let traced_by_hook = traced.clone();
+    let mut program = Program::parse("104,1,42");
+    program.set_trace_hook(move |address, _| traced_by_hook.lock().unwrap().push(address));
+    assert_eq!(
+        program.coverage(),
+        Err(ExecutionError::InvalidOpcode { ip: 2, opcode: 42 })
+    );
+    assert!(traced.lock().unwrap().is_empty());
+    assert_eq!(
+        program.step(),
+        Err(ExecutionError::InvalidOpcode { ip: 2, opcode: 42 })
+    );
+    assert_eq!(*traced.lock().unwrap(), vec![2]);
+}
+
+#[test]
+fn tests_checked_arithmetic() {
+    let source = "1101,9223372036854775807,1,0,4,0,99";
+    assert_eq!(Program::parse(source).run_for_output(), vec![i64::MIN]);
+
+    let mut program = Program::parse(source);
+    program.set_checked_arithmetic(true);
+    assert_eq!(
+        program.try_run_for_output(),
+        Err(ExecutionError::ArithmeticOverflow { ip: 0 })
+    );
+
+    let mut program = Program::parse("1102,3037000500,3037000500,0,4,0,99");
+    program.set_checked_arithmetic(true);
+    assert_eq!(
+        program.try_run_for_output(),
+        Err(ExecutionError::ArithmeticOverflow { ip: 0 })
+    );
+
+    let mut program = Program::parse(include_str!("day09_input.txt"));
+    program.set_checked_arithmetic(true);
+    program.input(1);
+    assert_eq!(program.try_run_for_output(), Ok(vec![3_601_950_151]));
+}
+
+#[test]
+fn tests_run_to_halt() {
+    let mut program = Program::parse(include_str!("day09_input.txt"));
+    program.input(1);
+    assert_eq!(program.run_to_halt(), vec![3_601_950_151]);
+    assert!(program.is_halted());
+
+    // Output each of two inputs:
+    let source = "3,0,4,0,3,0,4,0,99";
+    let mut program = Program::parse(source);
+    program.input(1);
+    program.input(2);
+    assert_eq!(program.run_to_halt(), vec![1, 2]);
+
+    let mut program = Program::parse(source);
+    program.input(1);
+    assert_eq!(program.try_run_to_halt(), Err(ExecutionError::NeedsInput));
+    program.input(2);
+    assert_eq!(program.try_run_to_halt(), Ok(vec![1, 2]));
+}
+
+#[test]
+fn tests_fingerprint() {
+    let day11_program = Program::parse(include_str!("day11_input.txt"));
+    let day23_program = Program::parse(include_str!("day23_input.txt"));
+    assert_ne!(day11_program.fingerprint(), day23_program.fingerprint());
+    assert_eq!(
+        day11_program.fingerprint(),
+        day11_program.clone().fingerprint()
+    );
+
+    // An invalid opcode ends the probe instead of panicking, so only earlier outputs count:
+    let invalid_opcode_program = Program::parse("104,1,42");
+    assert_eq!(
+        invalid_opcode_program.fingerprint(),
+        Program::parse("104,1,99").fingerprint()
+    );
+    assert_eq!(
+        invalid_opcode_program.clone().try_run_to_halt(),
+        Err(ExecutionError::InvalidOpcode { ip: 2, opcode: 42 })
+    );
+}
+
+#[test]
+fn tests_run_for_n_outputs() {
+    // Output 1, 2 and 3, then read input and output it:
+    let mut program = Program::parse("104,1,104,2,104,3,3,20,4,20,99");
+    assert_eq!(program.run_for_n_outputs(2), vec![1, 2]);
+    assert_eq!(program.run_for_n_outputs(2), vec![3]);
+    assert!(!program.is_halted());
+    assert_eq!(program.run_for_n_outputs(2), vec![]);
+
+    program.input(42);
+    assert_eq!(program.run_for_n_outputs(1), vec![42]);
+    assert_eq!(program.run_for_n_outputs(1), vec![]);
+    assert!(program.is_halted());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn tests_threaded_program() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Program>();
+
+    // Read a value, output it plus one and halt:
+    let increment = "3,0,1001,0,1,0,4,0,99";
+    let first = ThreadedProgram::spawn(Program::parse(increment));
+    let second = ThreadedProgram::spawn(Program::parse(increment));
+
+    assert!(first.send_input(40));
+    let value = first.recv_output().unwrap();
+    assert!(second.send_input(value));
+    assert_eq!(second.recv_output(), Some(42));
+
+    // Both programs have halted and so produce no more output:
+    assert_eq!(first.recv_output(), None);
+    assert_eq!(second.recv_output(), None);
+    assert!(!first.send_input(1));
+    assert!(first.join().unwrap().is_halted());
+    assert!(second.join().unwrap().is_halted());
+
+    // Dropping a program waiting for input ends its thread:
+    drop(ThreadedProgram::spawn(Program::parse(increment)));
+
+    // Joining a program waiting for input returns it after using the input already sent:
+    let echo = ThreadedProgram::spawn(Program::parse("3,100,4,100,1105,1,0"));
+    assert!(echo.send_input(5));
+    assert!(echo.send_input(6));
+    assert_eq!(echo.recv_output(), Some(5));
+    let program = echo.join().unwrap();
+    assert!(program.blocked_on_input());
+    assert_eq!(program.output_values, VecDeque::new());
+
+    // A program with an invalid opcode ends its thread after sending the output it produced:
+    let invalid = ThreadedProgram::spawn(Program::parse("104,7,42"));
+    assert_eq!(invalid.recv_output(), Some(7));
+    assert_eq!(invalid.recv_output(), None);
+    assert_eq!(
+        invalid.join().err(),
+        Some(ExecutionError::InvalidOpcode { ip: 2, opcode: 42 })
+    );
+}
+
+#[test]
+fn tests_set_input_iter() {
+    // Echo three input values:
+    let mut program = Program::parse("3,0,4,0,3,0,4,0,3,0,4,0,99");
+    program.set_input_iter(vec![7, 8].into_iter());
+    assert_eq!(program.try_run_to_halt(), Err(ExecutionError::NeedsInput));
+
+    // Queued input is used once the iterator is exhausted:
+    program.input(9);
+    assert_eq!(program.run_to_halt(), vec![7, 8, 9]);
+
+    let mut program = Program::parse("3,0,4,0,3,0,4,0,3,0,4,0,99");
+    program.set_input_iter("abc".bytes().map(i64::from));
+    assert_eq!(program.run_to_halt(), vec![97, 98, 99]);
+}
+
+#[test]
+fn tests_dump_memory() {
+    let path = std::env::temp_dir().join("advent_of_code_int_code_dump.txt");
+    let path = path.to_str().unwrap();
+
+    // Store the input at address 10, far beyond the program itself:
+    let mut program = Program::parse("3,10,4,10,99");
+    program.input(42);
+    assert_eq!(program.run_to_halt(), vec![42]);
+    program.dump_memory(path).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(path).unwrap(),
+        "3,10,4,10,99,0,0,0,0,0,42"
+    );
+
+    let mut loaded = Program::parse("99");
+    loaded.load_memory(path).unwrap();
+    assert_eq!(
+        (0..20)
+            .map(|address| loaded.read_memory(address))
+            .collect::<Vec<_>>(),
+        (0..20)
+            .map(|address| program.read_memory(address))
+            .collect::<Vec<_>>()
+    );
+
+    // A dump is itself a runnable program:
+    let mut dumped = Program::parse(&std::fs::read_to_string(path).unwrap());
+    dumped.input(7);
+    assert_eq!(dumped.run_to_halt(), vec![7]);
+
+    std::fs::write(path, "1,2,x").unwrap();
+    let error = loaded.load_memory(path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn tests_step_back() {
+    // Add 1 to address 9 three times, outputting the result each time:
+    let mut program = Program::parse("1001,9,1,9,4,9,1105,1,0,10");
+    program.enable_history(2);
+
+    program.step().unwrap();
+    assert_eq!(
+        (program.instruction_pointer, program.read_memory(9)),
+        (4, 11)
+    );
+    let after_first_step = (program.memory.clone(), program.instruction_pointer);
+    program.step().unwrap();
+    program.step().unwrap();
+    assert_eq!(program.instruction_pointer, 0);
+    assert_eq!(program.output_values, vec![11]);
+
+    assert!(program.step_back());
+    assert_eq!(program.instruction_pointer, 6);
+    assert_eq!(program.output_values, vec![11]);
+    assert!(program.step_back());
+    assert_eq!(
+        (program.memory.clone(), program.instruction_pointer),
+        after_first_step
+    );
+    assert!(program.output_values.is_empty());
+
+    // Only the two most recent instructions are kept:
+    assert!(!program.step_back());
+
+    // Stepping forward again gives the same result:
+    program.step().unwrap();
+    program.step().unwrap();
+    program.step().unwrap();
+    assert_eq!(program.run_for_n_outputs(1), vec![11]);
+    assert_eq!(program.run_for_n_outputs(1), vec![12]);
+
+    let mut without_history = Program::parse("1001,9,1,9,4,9,1105,1,0,10");
+    without_history.step().unwrap();
+    assert!(!without_history.step_back());
+}
+
+#[test]
+fn tests_disassemble() {
+    // Echo input forever, jumping back to the start:
+    let program = Program::parse("3,0,4,0,1105,1,0,99");
+    assert_eq!(
+        program.disassemble(),
+        "L0000:\n0000: IN [0]\n0002: OUT [0]\n0004: JT #1, L0000\n0007: HLT\n"
+    );
+
+    // Jump targets are labeled even when they do not hold an instruction:
+    let program = Program::parse("1106,0,4,99,1234,21101,-1,2,-3");
+    assert_eq!(
+        program.disassemble(),
+        "0000: JF #0, L0004\n0003: HLT\nL0004:\n0004: DATA 1234\n0005: ADD #-1, #2, [rb-3]\n"
+    );
+
+    // Jumps through memory have no label:
+    let program = Program::parse("5,0,3,99");
+    assert_eq!(program.disassemble(), "0000: JT [0], [3]\n0003: HLT\n");
+}
+
+#[test]
+fn tests_run_ring() {
+    // Echo a single value:
+    let echo = Program::parse("3,0,4,0,99");
+
+    // Both programs wait for input from each other:
+    let mut programs = vec![echo.clone(), echo.clone()];
+    assert_eq!(run_ring(&mut programs), Err(ExecutionError::Deadlock));
+    assert!(programs.iter().all(Program::blocked_on_input));
+
+    let mut programs = vec![echo.clone(), echo.clone(), echo];
+    programs[0].input(5);
+    assert_eq!(run_ring(&mut programs), Ok(Some(5)));
+    assert!(programs.iter().all(Program::is_halted));
+    assert!(!programs[0].blocked_on_input());
+}
+
+#[test]
+fn tests_with_options() {
+    let source = include_str!("day09_input.txt");
+    let mut program = Program::with_options(
+        source,
+        ParseOptions {
+            memory_reserve: 100_000,
+            ..ParseOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(program.memory.capacity() >= 100_000);
+    program.input(1);
+    assert_eq!(program.run_for_output(), vec![3_601_950_151]);
+
+    // Output the value at relative address 1:
+    let relative_output = "204,1,99";
+    let mut program = Program::with_options(relative_output, ParseOptions::default()).unwrap();
+    assert_eq!(program.run_for_output(), vec![1]);
+    let mut program = Program::with_options(
+        relative_output,
+        ParseOptions {
+            initial_relative_base: 1,
+            ..ParseOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(program.run_for_output(), vec![99]);
+
+    assert!(Program::with_options("1,x", ParseOptions::default()).is_err());
 }
@@ -3,83 +3,265 @@ extern crate wasm_bindgen;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-mod day01;
+pub mod day01;
 mod day02;
-mod day03;
-mod day04;
+pub mod day03;
+pub mod day04;
 mod day05;
-mod day06;
+pub mod day06;
 mod day07;
-mod day08;
-mod day09;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day20;
+pub mod day08;
+pub mod day09;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
+pub mod day20;
 mod day21;
-mod day22;
-mod day23;
-mod day24;
+pub mod day22;
+pub mod day23;
+pub mod day24;
 mod day25;
-mod int_code;
+pub mod direction;
+#[cfg(feature = "image")]
+mod gif;
+pub mod grid;
+pub mod int_code;
+pub mod parse;
+pub mod pathfinding;
 mod permutation;
+#[cfg(feature = "image")]
+mod png;
+pub mod point;
+#[cfg(feature = "image")]
+pub mod render;
+pub mod sequence;
+pub mod util;
 
-pub fn get_problem_set(day: u8, part: u8) -> Option<fn(&str) -> String> {
-    struct Solutions(fn(&str) -> String, fn(&str) -> String);
-
-    let parts: Solutions = match day {
-        1 => Solutions(day01::part1, day01::part2),
-        2 => Solutions(day02::part1, day02::part2),
-        3 => Solutions(day03::part1, day03::part2),
-        4 => Solutions(day04::part1, day04::part2),
-        5 => Solutions(day05::part1, day05::part2),
-        6 => Solutions(day06::part1, day06::part2),
-        7 => Solutions(day07::part1, day07::part2),
-        8 => Solutions(day08::part1, day08::part2),
-        9 => Solutions(day09::part1, day09::part2),
-        10 => Solutions(day10::part1, day10::part2),
-        11 => Solutions(day11::part1, day11::part2),
-        12 => Solutions(day12::part1, day12::part2),
-        13 => Solutions(day13::part1, day13::part2),
-        14 => Solutions(day14::part1, day14::part2),
-        15 => Solutions(day15::part1, day15::part2),
-        16 => Solutions(day16::part1, day16::part2),
-        17 => Solutions(day17::part1, day17::part2),
-        18 => Solutions(day18::part1, day18::part2),
-        19 => Solutions(day19::part1, day19::part2),
-        20 => Solutions(day20::part1, day20::part2),
-        21 => Solutions(day21::part1, day21::part2),
-        22 => Solutions(day22::part1, day22::part2),
-        23 => Solutions(day23::part1, day23::part2),
-        24 => Solutions(day24::part1, day24::part2),
-        25 => Solutions(day25::part1, day25::part2),
-        _ => {
-            return None;
-        }
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The solution to the two parts of a day, which is stateless and so can be shared
+/// between threads.
+pub trait Solution: Send + Sync {
+    fn part1(&self, input: &str) -> String;
+    fn part2(&self, input: &str) -> String;
+}
+
+/// Define unit structs implementing `Solution` by calling the functions of a day module.
+macro_rules! day_solutions {
+    ($($name:ident => $module:ident),* $(,)?) => {
+        $(
+            pub struct $name;
+
+            impl Solution for $name {
+                fn part1(&self, input: &str) -> String {
+                    $module::part1(input)
+                }
+
+                fn part2(&self, input: &str) -> String {
+                    $module::part2(input)
+                }
+            }
+        )*
     };
+}
+
+day_solutions!(
+    Day01 => day01,
+    Day02 => day02,
+    Day03 => day03,
+    Day04 => day04,
+    Day05 => day05,
+    Day06 => day06,
+    Day07 => day07,
+    Day08 => day08,
+    Day09 => day09,
+    Day10 => day10,
+    Day11 => day11,
+    Day12 => day12,
+    Day13 => day13,
+    Day14 => day14,
+    Day15 => day15,
+    Day16 => day16,
+    Day17 => day17,
+    Day18 => day18,
+    Day19 => day19,
+    Day20 => day20,
+    Day21 => day21,
+    Day22 => day22,
+    Day23 => day23,
+    Day24 => day24,
+    Day25 => day25,
+);
+
+/// The solutions of all days, keyed by day number.
+pub fn registry() -> HashMap<u8, Box<dyn Solution>> {
+    let solutions: Vec<Box<dyn Solution>> = vec![
+        Box::new(Day01),
+        Box::new(Day02),
+        Box::new(Day03),
+        Box::new(Day04),
+        Box::new(Day05),
+        Box::new(Day06),
+        Box::new(Day07),
+        Box::new(Day08),
+        Box::new(Day09),
+        Box::new(Day10),
+        Box::new(Day11),
+        Box::new(Day12),
+        Box::new(Day13),
+        Box::new(Day14),
+        Box::new(Day15),
+        Box::new(Day16),
+        Box::new(Day17),
+        Box::new(Day18),
+        Box::new(Day19),
+        Box::new(Day20),
+        Box::new(Day21),
+        Box::new(Day22),
+        Box::new(Day23),
+        Box::new(Day24),
+        Box::new(Day25),
+    ];
+    solutions
+        .into_iter()
+        .enumerate()
+        .map(|(index, solution)| (index as u8 + 1, solution))
+        .collect()
+}
+
+/// The numbers of the days in the registry, in increasing order.
+pub fn registered_days() -> Vec<u8> {
+    let mut days: Vec<u8> = registry().into_keys().collect();
+    days.sort_unstable();
+    days
+}
+
+/// Solve a part of a day, or None if the day or part does not exist.
+pub fn solve(day: u8, part: u8, input: &str) -> Option<String> {
+    let solution = registry().remove(&day)?;
     match part {
-        1 => Some(parts.0),
-        2 => Some(parts.1),
+        1 => Some(solution.part1(input)),
+        2 => Some(solution.part2(input)),
         _ => None,
     }
 }
 
+/// Solve both parts of every registered day with input available, returning the time taken
+/// by each part in order of day.
+pub fn run_all_timed(
+    input_for_day: impl Fn(u8) -> Option<String>,
+) -> Vec<(u8, Duration, Duration)> {
+    let solutions = registry();
+    registered_days()
+        .into_iter()
+        .filter_map(|day| {
+            let solution = &solutions[&day];
+            let input = input_for_day(day)?;
+            let start = Instant::now();
+            solution.part1(&input);
+            let part1_duration = start.elapsed();
+            let start = Instant::now();
+            solution.part2(&input);
+            Some((day, part1_duration, start.elapsed()))
+        })
+        .collect()
+}
+
+/// Solve both parts of every registered day with input available, returning the answers
+/// by day.
+pub fn run_all(input_for_day: impl Fn(u8) -> Option<String>) -> HashMap<u8, (String, String)> {
+    registry()
+        .into_iter()
+        .filter_map(|(day, solution)| {
+            let input = input_for_day(day)?;
+            Some((day, (solution.part1(&input), solution.part2(&input))))
+        })
+        .collect()
+}
+
+/// Same as `run_all`, but solving the days and parts in parallel.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_all_parallel(
+    input_for_day: impl Fn(u8) -> Option<String> + Sync,
+) -> HashMap<u8, (String, String)> {
+    use rayon::prelude::*;
+
+    registry()
+        .into_par_iter()
+        .filter_map(|(day, solution)| {
+            let input = input_for_day(day)?;
+            let answers = rayon::join(|| solution.part1(&input), || solution.part2(&input));
+            Some((day, answers))
+        })
+        .collect()
+}
+
 #[cfg(target_arch = "wasm32")]
-#[wasm_bindgen]
-pub fn solve(day: u8, part: u8, input: String) -> String {
-    if let Some(solver) = get_problem_set(day, part) {
-        solver(&input)
-    } else {
+#[wasm_bindgen(js_name = solve)]
+pub fn solve_wasm(day: u8, part: u8, input: String) -> String {
+    solve(day, part, &input).unwrap_or_else(|| {
         format!(
             "Day ({}) must be between 1 and 25 and part ({}) either 1 or 2",
             day, part
         )
+    })
+}
+
+#[test]
+fn tests_solve() {
+    let sample = "#########
+#b.A.@.a#
+#########";
+    assert_eq!(solve(18, 1, sample), Some("8".to_string()));
+    assert_eq!(solve(18, 3, sample), None);
+    assert_eq!(solve(26, 1, sample), None);
+    assert_eq!(registry().len(), 25);
+}
+
+#[test]
+fn tests_registered_days() {
+    let days = registered_days();
+    assert_eq!(days.len(), 25);
+    assert!(days.windows(2).all(|pair| pair[0] < pair[1]));
+    for day in &[11, 18, 23] {
+        assert!(days.contains(day));
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn tests_run_all_parallel() {
+    // Only the first ten days, to keep the test fast:
+    let input_for_day = |day| {
+        if day > 10 {
+            return None;
+        }
+        let input_path = format!("{}/src/day{:02}_input.txt", env!("CARGO_MANIFEST_DIR"), day);
+        std::fs::read_to_string(input_path).ok()
+    };
+    let answers = run_all_parallel(input_for_day);
+    assert_eq!(answers.len(), 10);
+    assert_eq!(answers, run_all(input_for_day));
+}
+
+#[test]
+fn tests_run_all_timed() {
+    // Only a few quick days, to keep the test fast:
+    let timings = run_all_timed(|day| {
+        if ![1, 2, 4, 6].contains(&day) {
+            return None;
+        }
+        let input_path = format!("{}/src/day{:02}_input.txt", env!("CARGO_MANIFEST_DIR"), day);
+        std::fs::read_to_string(input_path).ok()
+    });
+    let days: Vec<u8> = timings.iter().map(|&(day, _, _)| day).collect();
+    assert_eq!(days, vec![1, 2, 4, 6]);
+}
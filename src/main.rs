@@ -4,7 +4,7 @@ use std::env;
 use std::io::Read;
 
 #[cfg(not(target_arch = "wasm32"))]
-use advent_of_code_rs::get_problem_set;
+use advent_of_code_rs::solve;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
@@ -20,14 +20,15 @@ fn main() {
     } else if let (Ok(day @ 1..=25), Ok(part @ 1..=2)) =
         (args[1].parse::<u8>(), args[2].parse::<u8>())
     {
-        let solver = get_problem_set(day, part).unwrap();
         let mut input = String::new();
         std::io::stdin()
             .read_to_string(&mut input)
             .expect("Error reading input");
 
-        let solution = solver(input.as_ref());
-        println!("{}", solution);
+        match solve(day, part, &input) {
+            Some(solution) => println!("{}", solution),
+            None => usage(),
+        }
     } else {
         usage();
     }
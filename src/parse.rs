@@ -0,0 +1,129 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::num::ParseIntError;
+
+/// An integer in a list which could not be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseIntsError {
+    /// The token which failed to parse, after trimming.
+    pub token: String,
+    pub error: ParseIntError,
+}
+
+impl fmt::Display for ParseIntsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid integer '{}': {}", self.token, self.error)
+    }
+}
+
+/// Puzzle input with CRLF line endings turned into LF and trailing whitespace, such as a final
+/// newline, removed, so that it does not give rise to empty trailing lines.
+pub fn normalize(input: &str) -> Cow<'_, str> {
+    let input = input.trim_end();
+    if input.contains("\r\n") {
+        Cow::Owned(input.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(input)
+    }
+}
+
+/// Parse a list of integers separated by commas, whitespace or both.
+///
+/// An empty token between two commas is reported as an error rather than skipped.
+pub fn ints(input: &str) -> Result<Vec<i64>, ParseIntsError> {
+    input
+        .split(',')
+        .flat_map(|part| {
+            let mut tokens: Vec<&str> = part.split_whitespace().collect();
+            if tokens.is_empty() {
+                tokens.push("");
+            }
+            tokens
+        })
+        .map(|token| {
+            token.parse::<i64>().map_err(|error| ParseIntsError {
+                token: token.to_string(),
+                error,
+            })
+        })
+        .collect()
+}
+
+/// The digits of a string of ASCII digits, most significant first, ignoring surrounding
+/// whitespace.
+///
+/// Panics if there is any other character than a digit.
+pub fn digits(s: &str) -> Vec<u8> {
+    s.trim()
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_digit() {
+                b - b'0'
+            } else {
+                panic!("Invalid digit: '{}'", b as char)
+            }
+        })
+        .collect()
+}
+
+/// The decimal digits of an integer, most significant first.
+pub fn digits_of(n: u64) -> Vec<u8> {
+    let mut result: Vec<u8> = std::iter::successors(Some(n), |&n| Some(n / 10).filter(|&n| n > 0))
+        .map(|n| (n % 10) as u8)
+        .collect();
+    result.reverse();
+    result
+}
+
+#[test]
+fn tests_ints() {
+    assert_eq!(ints("1,-2,3"), Ok(vec![1, -2, 3]));
+    assert_eq!(ints("1,2,3\n"), Ok(vec![1, 2, 3]));
+    assert_eq!(ints(" 4 5\n6\t7 "), Ok(vec![4, 5, 6, 7]));
+    assert_eq!(ints("1, 2,\n3 4"), Ok(vec![1, 2, 3, 4]));
+    assert_eq!(ints("42"), Ok(vec![42]));
+}
+
+#[test]
+fn tests_ints_errors() {
+    let error = ints("1, 2x ,3").unwrap_err();
+    assert_eq!(error.token, "2x");
+    assert_eq!(
+        error.to_string(),
+        "Invalid integer '2x': invalid digit found in string"
+    );
+
+    assert_eq!(ints("1,,3").unwrap_err().token, "");
+    assert_eq!(ints("1,2,").unwrap_err().token, "");
+    assert_eq!(ints("").unwrap_err().token, "");
+}
+
+#[test]
+fn tests_digits() {
+    assert_eq!(digits("12345"), vec![1, 2, 3, 4, 5]);
+    assert_eq!(digits_of(12345), vec![1, 2, 3, 4, 5]);
+    assert_eq!(digits("12345"), digits_of(12345));
+
+    assert_eq!(digits("0907\n"), vec![0, 9, 0, 7]);
+    assert_eq!(digits(""), Vec::<u8>::new());
+    assert_eq!(digits_of(0), vec![0]);
+    assert_eq!(digits_of(u64::MAX).len(), 20);
+}
+
+#[test]
+#[should_panic(expected = "Invalid digit: 'x'")]
+fn tests_digits_invalid() {
+    digits("12x4");
+}
+
+#[test]
+fn tests_normalize() {
+    assert_eq!(normalize("#.#\n..#\n"), "#.#\n..#");
+    assert_eq!(normalize("#.#\r\n..#\r\n"), "#.#\n..#");
+    assert_eq!(normalize("1,2,3  \t\n\n"), "1,2,3");
+    assert_eq!(normalize("  A\n#.#  \n\n"), "  A\n#.#");
+    assert_eq!(normalize("\n\n"), "");
+
+    assert!(matches!(normalize("1,2,3\n"), Cow::Borrowed("1,2,3")));
+    assert!(matches!(normalize("1\r\n2"), Cow::Owned(_)));
+}
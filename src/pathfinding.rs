@@ -0,0 +1,591 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// The shortest path from `start` to a node satisfying `is_goal` in a graph where all
+/// edges have the same length. The path includes both the start and the goal node.
+pub fn bfs<N, F, I, G>(start: N, mut neighbors: F, mut is_goal: G) -> Option<Vec<N>>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+    G: FnMut(&N) -> bool,
+{
+    // From node to the node it was first reached from:
+    let mut previous: HashMap<N, Option<N>> = HashMap::new();
+    let mut to_visit = VecDeque::new();
+    previous.insert(start.clone(), None);
+    to_visit.push_back(start);
+
+    while let Some(current) = to_visit.pop_front() {
+        if is_goal(&current) {
+            return Some(reconstruct_path(current, |node| previous[node].as_ref()));
+        }
+
+        for neighbor in neighbors(&current) {
+            if let Entry::Vacant(entry) = previous.entry(neighbor.clone()) {
+                entry.insert(Some(current.clone()));
+                to_visit.push_back(neighbor);
+            }
+        }
+    }
+    None
+}
+
+/// All nodes reachable from `start`, including the start node itself.
+pub fn flood_fill<N, F, I>(start: N, neighbors: F) -> HashSet<N>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    flood_fill_distances(start, neighbors).into_keys().collect()
+}
+
+/// All nodes reachable from `start` together with the fewest number of edges needed to
+/// reach them, with the start node itself at distance zero.
+pub fn flood_fill_distances<N, F, I>(start: N, mut neighbors: F) -> HashMap<N, usize>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let mut distances = HashMap::new();
+    let mut to_visit = VecDeque::new();
+    distances.insert(start.clone(), 0);
+    to_visit.push_back((start, 0));
+
+    while let Some((current, distance)) = to_visit.pop_front() {
+        for neighbor in neighbors(&current) {
+            if let Entry::Vacant(entry) = distances.entry(neighbor.clone()) {
+                entry.insert(distance + 1);
+                to_visit.push_back((neighbor, distance + 1));
+            }
+        }
+    }
+    distances
+}
+
+/// Partition `nodes` into groups connected through edges given by `neighbors`, ignoring
+/// neighbors which are not among the nodes. Groups are ordered by their first node in
+/// `nodes`, which is also the first node of the group.
+pub fn connected_components<N, F, I>(
+    nodes: impl IntoIterator<Item = N>,
+    mut neighbors: F,
+) -> Vec<Vec<N>>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let nodes: Vec<N> = nodes.into_iter().collect();
+    let node_set: HashSet<N> = nodes.iter().cloned().collect();
+    let mut visited = HashSet::new();
+    let mut components = Vec::new();
+
+    for node in nodes {
+        if !visited.insert(node.clone()) {
+            continue;
+        }
+
+        let mut component = vec![node];
+        let mut index = 0;
+        while index < component.len() {
+            for neighbor in neighbors(&component[index]) {
+                if node_set.contains(&neighbor) && visited.insert(neighbor.clone()) {
+                    component.push(neighbor);
+                }
+            }
+            index += 1;
+        }
+        components.push(component);
+    }
+    components
+}
+
+/// Optional behaviour and instrumentation of `dijkstra_with`.
+pub struct DijkstraOptions<'a, N, C> {
+    /// A lower bound of the remaining cost from a node to the goal, turning the search into A*.
+    pub heuristic: Option<&'a dyn Fn(&N) -> C>,
+    /// Called for each node popped from the priority queue, including nodes already reached
+    /// more cheaply, with the previous node on the cheapest path found to it, the cost it was
+    /// popped with and the number of nodes remaining in the queue.
+    #[allow(clippy::type_complexity)]
+    pub on_pop: Option<&'a mut dyn FnMut(&N, Option<&N>, C, usize)>,
+}
+
+impl<'a, N, C> Default for DijkstraOptions<'a, N, C> {
+    fn default() -> Self {
+        DijkstraOptions {
+            heuristic: None,
+            on_pop: None,
+        }
+    }
+}
+
+/// The lowest cost to get from `start` to a node satisfying `is_goal` together with the path
+/// there, including both the start and the goal node. `edges` returns the neighbors of a node
+/// together with the cost of moving to them, and `C::default()` is used as zero cost.
+pub fn dijkstra<N, C, E, I, G>(start: N, edges: E, is_goal: G) -> Option<(C, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Add<Output = C> + Default,
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    G: FnMut(&N) -> bool,
+{
+    dijkstra_with(start, edges, is_goal, DijkstraOptions::default())
+}
+
+/// Like `dijkstra`, but expanding nodes in order of the cost so far plus `heuristic`, which
+/// must never overestimate the remaining cost to the goal for the result to be the lowest cost.
+pub fn astar<N, C, E, I, G, H>(start: N, edges: E, is_goal: G, heuristic: H) -> Option<(C, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Add<Output = C> + Default,
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    G: FnMut(&N) -> bool,
+    H: Fn(&N) -> C,
+{
+    dijkstra_with(
+        start,
+        edges,
+        is_goal,
+        DijkstraOptions {
+            heuristic: Some(&heuristic),
+            ..DijkstraOptions::default()
+        },
+    )
+}
+
+/// Like `dijkstra`, with an optional heuristic and instrumentation.
+pub fn dijkstra_with<N, C, E, I, G>(
+    start: N,
+    mut edges: E,
+    mut is_goal: G,
+    options: DijkstraOptions<N, C>,
+) -> Option<(C, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Add<Output = C> + Default,
+    E: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+    G: FnMut(&N) -> bool,
+{
+    struct Vertex<N, C> {
+        /// The cost so far plus the heuristic lower bound of the remaining cost.
+        estimated_cost: C,
+        cost: C,
+        node: N,
+    }
+
+    impl<N, C: Ord> Ord for Vertex<N, C> {
+        fn cmp(&self, other: &Vertex<N, C>) -> Ordering {
+            other
+                .estimated_cost
+                .cmp(&self.estimated_cost)
+                .then_with(|| other.cost.cmp(&self.cost))
+        }
+    }
+
+    impl<N, C: Ord> PartialOrd for Vertex<N, C> {
+        fn partial_cmp(&self, other: &Vertex<N, C>) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl<N, C: Ord> PartialEq for Vertex<N, C> {
+        fn eq(&self, other: &Vertex<N, C>) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl<N, C: Ord> Eq for Vertex<N, C> {}
+
+    let DijkstraOptions {
+        heuristic,
+        mut on_pop,
+    } = options;
+
+    // From node to the lowest cost found to reach it and the previous node on that path:
+    let mut lowest_costs: HashMap<N, (C, Option<N>)> = HashMap::new();
+    let mut to_visit = BinaryHeap::new();
+
+    lowest_costs.insert(start.clone(), (C::default(), None));
+    to_visit.push(Vertex {
+        estimated_cost: C::default(),
+        cost: C::default(),
+        node: start,
+    });
+
+    while let Some(Vertex { cost, node, .. }) = to_visit.pop() {
+        if let Some(on_pop) = &mut on_pop {
+            let previous = lowest_costs[&node].1.as_ref();
+            on_pop(&node, previous, cost, to_visit.len());
+        }
+
+        if is_goal(&node) {
+            let path = reconstruct_path(node, |node| lowest_costs[node].1.as_ref());
+            return Some((cost, path));
+        }
+
+        if cost > lowest_costs[&node].0 {
+            // Already reached this node at a lower cost.
+            continue;
+        }
+
+        for (neighbor, edge_cost) in edges(&node) {
+            let new_cost = cost + edge_cost;
+            let improved = match lowest_costs.get(&neighbor) {
+                Some(&(lowest_cost, _)) => new_cost < lowest_cost,
+                None => true,
+            };
+            if improved {
+                let estimated_cost = match heuristic {
+                    Some(heuristic) => new_cost + heuristic(&neighbor),
+                    None => new_cost,
+                };
+                lowest_costs.insert(neighbor.clone(), (new_cost, Some(node.clone())));
+                to_visit.push(Vertex {
+                    estimated_cost,
+                    cost: new_cost,
+                    node: neighbor,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// How to move between the cells of a grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Movement {
+    /// Moving up, down, left or right at a cost of one.
+    Orthogonal,
+    /// Also moving diagonally, at a cost given by the `DiagonalCost`.
+    Diagonal(DiagonalCost),
+}
+
+/// The cost of steps when moving diagonally on a grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiagonalCost {
+    /// All steps, including diagonal ones, cost one.
+    Unit,
+    /// Orthogonal steps cost `scale`, while diagonal steps cost `scale` times the square root
+    /// of two, rounded to the nearest integer.
+    Euclidean { scale: u32 },
+}
+
+impl Movement {
+    /// The cost of an orthogonal and, if allowed, a diagonal step.
+    fn step_costs(self) -> (u32, Option<u32>) {
+        match self {
+            Movement::Orthogonal => (1, None),
+            Movement::Diagonal(DiagonalCost::Unit) => (1, Some(1)),
+            Movement::Diagonal(DiagonalCost::Euclidean { scale }) => (
+                scale,
+                Some((f64::from(scale) * std::f64::consts::SQRT_2).round() as u32),
+            ),
+        }
+    }
+}
+
+/// The cells next to `position` in a grid of the given size, together with the cost of
+/// moving to them, for use as edges in `dijkstra` or `astar`.
+pub fn grid_edges(
+    (x, y): (i32, i32),
+    width: i32,
+    height: i32,
+    movement: Movement,
+) -> Vec<((i32, i32), u32)> {
+    let (orthogonal_cost, diagonal_cost) = movement.step_costs();
+    let mut steps = vec![
+        ((1, 0), orthogonal_cost),
+        ((-1, 0), orthogonal_cost),
+        ((0, 1), orthogonal_cost),
+        ((0, -1), orthogonal_cost),
+    ];
+    if let Some(diagonal_cost) = diagonal_cost {
+        steps.extend(
+            [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+                .iter()
+                .map(|&step| (step, diagonal_cost)),
+        );
+    }
+
+    steps
+        .into_iter()
+        .map(|((dx, dy), cost)| ((x + dx, y + dy), cost))
+        .filter(|&((x, y), _)| x >= 0 && x < width && y >= 0 && y < height)
+        .collect()
+}
+
+/// The cost of moving between two cells on an open grid, which is a lower bound of the cost
+/// with obstacles and so usable as the heuristic of `astar` together with `grid_edges`.
+pub fn grid_distance(from: (i32, i32), to: (i32, i32), movement: Movement) -> u32 {
+    let dx = (from.0 - to.0).unsigned_abs();
+    let dy = (from.1 - to.1).unsigned_abs();
+    match movement.step_costs() {
+        (orthogonal_cost, None) => (dx + dy) * orthogonal_cost,
+        (orthogonal_cost, Some(diagonal_cost)) => {
+            let diagonal_steps = dx.min(dy);
+            diagonal_steps * diagonal_cost + (dx.max(dy) - diagonal_steps) * orthogonal_cost
+        }
+    }
+}
+
+/// Follow the previous nodes back from `goal` to the start, returning the path in forward order.
+fn reconstruct_path<'a, N: Clone + 'a>(goal: N, previous: impl Fn(&N) -> Option<&'a N>) -> Vec<N> {
+    let mut path = vec![goal];
+    while let Some(node) = previous(&path[path.len() - 1]) {
+        path.push(node.clone());
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+fn test_graph(node: &char) -> Vec<(char, u32)> {
+    // a --1-- b --1-- c --1-- e
+    //  \--5-- d --1--/
+    match node {
+        'a' => vec![('b', 1), ('d', 5)],
+        'b' => vec![('a', 1), ('c', 1)],
+        'c' => vec![('b', 1), ('e', 1)],
+        'd' => vec![('a', 5), ('e', 1)],
+        'e' => vec![('c', 1), ('d', 1)],
+        _ => vec![],
+    }
+}
+
+#[test]
+fn tests_bfs() {
+    let neighbors = |node: &char| test_graph(node).into_iter().map(|(neighbor, _)| neighbor);
+    assert_eq!(
+        bfs('a', neighbors, |&node| node == 'e'),
+        Some(vec!['a', 'd', 'e'])
+    );
+    assert_eq!(bfs('a', neighbors, |&node| node == 'a'), Some(vec!['a']));
+    assert_eq!(bfs('a', neighbors, |&node| node == 'z'), None);
+
+    // An unbounded grid, searching for the Manhattan distance:
+    let path = bfs(
+        (0, 0),
+        |&(x, y): &(i32, i32)| vec![(x + 1, y), (x, y + 1)],
+        |&position| position == (3, 2),
+    )
+    .unwrap();
+    assert_eq!(path.len(), 6);
+}
+
+#[cfg(test)]
+const TEST_GRID: &str = "\
+.....#...
+.###.#.#.
+...#.#.#.
+##.#####.
+.......#.";
+
+#[cfg(test)]
+fn test_grid_neighbors(&(x, y): &(i32, i32)) -> Vec<(i32, i32)> {
+    let rows: Vec<&[u8]> = TEST_GRID.lines().map(str::as_bytes).collect();
+    vec![(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+        .into_iter()
+        .filter(|&(x, y)| {
+            y >= 0
+                && (y as usize) < rows.len()
+                && x >= 0
+                && (x as usize) < rows[y as usize].len()
+                && rows[y as usize][x as usize] == b'.'
+        })
+        .collect()
+}
+
+#[test]
+fn tests_flood_fill() {
+    let reachable = flood_fill((0, 0), test_grid_neighbors);
+    assert_eq!(reachable.len(), 19);
+    assert!(reachable.contains(&(0, 4)));
+    // The pocket to the right of the walls is excluded:
+    assert!(!reachable.contains(&(6, 0)));
+    assert!(!reachable.contains(&(8, 4)));
+
+    let pocket = flood_fill((6, 0), test_grid_neighbors);
+    assert_eq!(pocket.len(), 9);
+    assert!(pocket.is_disjoint(&reachable));
+
+    let distances = flood_fill_distances((0, 0), test_grid_neighbors);
+    assert_eq!(distances.keys().cloned().collect::<HashSet<_>>(), reachable);
+    assert_eq!(distances[&(0, 0)], 0);
+    assert_eq!(distances[&(4, 2)], 6);
+    assert_eq!(distances[&(0, 4)], 8);
+    assert_eq!(distances.get(&(6, 0)), None);
+}
+
+#[test]
+fn tests_connected_components() {
+    // Two cliques, {1, 2, 3} and {4, 5}:
+    let neighbors = |&node: &u32| -> Vec<u32> {
+        let clique = if node <= 3 { 1..=3 } else { 4..=5 };
+        clique.filter(|&other| other != node).collect()
+    };
+    let mut components = connected_components(vec![4, 1, 2, 5, 3], neighbors);
+    assert_eq!(components.len(), 2);
+    assert_eq!(components[0][0], 4);
+    assert_eq!(components[1][0], 1);
+    components.iter_mut().for_each(|component| component.sort());
+    assert_eq!(components, vec![vec![4, 5], vec![1, 2, 3]]);
+
+    // Neighbors outside of the given nodes are ignored:
+    assert_eq!(
+        connected_components(vec![1, 4], neighbors),
+        vec![vec![1], vec![4]]
+    );
+    assert!(connected_components(Vec::new(), neighbors).is_empty());
+
+    // The grid with a walled off pocket:
+    let open_cells: Vec<(i32, i32)> = TEST_GRID
+        .lines()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|&(_, c)| c == '.')
+                .map(move |(x, _)| (x as i32, y as i32))
+        })
+        .collect();
+    let components = connected_components(open_cells, test_grid_neighbors);
+    assert_eq!(
+        components.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![19, 9]
+    );
+}
+
+#[test]
+fn tests_dijkstra() {
+    assert_eq!(
+        dijkstra('a', test_graph, |&node| node == 'e'),
+        Some((3, vec!['a', 'b', 'c', 'e']))
+    );
+    assert_eq!(
+        dijkstra('d', test_graph, |&node| node == 'b'),
+        Some((3, vec!['d', 'e', 'c', 'b']))
+    );
+    assert_eq!(
+        dijkstra('a', test_graph, |&node| node == 'a'),
+        Some((0, vec!['a']))
+    );
+    assert_eq!(dijkstra('a', test_graph, |&node| node == 'z'), None);
+}
+
+#[test]
+fn tests_dijkstra_with() {
+    let mut popped = Vec::new();
+    let mut on_pop = |&node: &char, previous: Option<&char>, cost: u32, _: usize| {
+        popped.push((node, previous.cloned(), cost));
+    };
+    let heuristic = |&node: &char| if node == 'e' { 0 } else { 1 };
+    let result = dijkstra_with(
+        'a',
+        test_graph,
+        |&node| node == 'e',
+        DijkstraOptions {
+            heuristic: Some(&heuristic),
+            on_pop: Some(&mut on_pop),
+        },
+    );
+    assert_eq!(result, Some((3, vec!['a', 'b', 'c', 'e'])));
+    assert_eq!(popped[0], ('a', None, 0));
+    assert_eq!(popped.last(), Some(&('e', Some('c'), 3)));
+    assert!(popped.iter().all(|&(node, _, _)| node != 'd'));
+}
+
+#[test]
+fn tests_astar() {
+    assert_eq!(
+        astar('a', test_graph, |&node| node == 'e', |_| 0),
+        dijkstra('a', test_graph, |&node| node == 'e')
+    );
+    assert_eq!(
+        astar('b', test_graph, |&node| node == 'd', |_| 0),
+        dijkstra('b', test_graph, |&node| node == 'd')
+    );
+    assert_eq!(astar('a', test_graph, |&node| node == 'z', |_| 0), None);
+
+    // An open 21x21 grid, going from the center to a corner:
+    let goal = (10, 10);
+    let search = |heuristic: &dyn Fn(&(i32, i32)) -> i32| {
+        let mut expanded_nodes = 0;
+        let grid_edges = |&(x, y): &(i32, i32)| {
+            expanded_nodes += 1;
+            vec![(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+                .into_iter()
+                .filter(|&(x, y)| x.abs() <= 10 && y.abs() <= 10)
+                .map(|position| (position, 1))
+                .collect::<Vec<_>>()
+        };
+        let (cost, path) = astar((0, 0), grid_edges, |&node| node == goal, heuristic).unwrap();
+        (cost, path.len(), expanded_nodes)
+    };
+
+    let (cost, path_length, astar_expanded_nodes) =
+        search(&|&(x, y)| (goal.0 - x).abs() + (goal.1 - y).abs());
+    let (dijkstra_cost, _, dijkstra_expanded_nodes) = search(&|_| 0);
+    assert_eq!((cost, path_length), (20, 21));
+    assert_eq!(dijkstra_cost, cost);
+    assert!(astar_expanded_nodes < dijkstra_expanded_nodes);
+}
+
+#[test]
+fn tests_grid_edges() {
+    let neighbors = |position, movement| {
+        let mut neighbors: Vec<(i32, i32)> = grid_edges(position, 3, 3, movement)
+            .into_iter()
+            .map(|(neighbor, _)| neighbor)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors
+    };
+    let diagonal = Movement::Diagonal(DiagonalCost::Unit);
+
+    assert_eq!(
+        neighbors((0, 0), Movement::Orthogonal),
+        vec![(0, 1), (1, 0)]
+    );
+    assert_eq!(neighbors((0, 0), diagonal), vec![(0, 1), (1, 0), (1, 1)]);
+    assert_eq!(neighbors((1, 1), Movement::Orthogonal).len(), 4);
+    assert_eq!(neighbors((1, 1), diagonal).len(), 8);
+    assert_eq!(neighbors((2, 1), diagonal).len(), 5);
+
+    let euclidean = Movement::Diagonal(DiagonalCost::Euclidean { scale: 100 });
+    let mut edges = grid_edges((0, 0), 3, 3, euclidean);
+    edges.sort_unstable();
+    assert_eq!(edges, vec![((0, 1), 100), ((1, 0), 100), ((1, 1), 141)]);
+}
+
+#[test]
+fn tests_grid_movement() {
+    // An open 5x5 grid, going between opposite corners and to a cell off the diagonal:
+    let shortest_path = |goal: (i32, i32), movement| {
+        let edges = |&position: &(i32, i32)| grid_edges(position, 5, 5, movement);
+        let heuristic = |&position: &(i32, i32)| grid_distance(position, goal, movement);
+        let (cost, path) = astar((0, 0), edges, |&node| node == goal, heuristic).unwrap();
+        let (dijkstra_cost, _) = dijkstra((0, 0), edges, |&node| node == goal).unwrap();
+        assert_eq!(cost, dijkstra_cost);
+        assert_eq!(cost, grid_distance((0, 0), goal, movement));
+        (cost, path.len())
+    };
+    let diagonal = Movement::Diagonal(DiagonalCost::Unit);
+    let euclidean = Movement::Diagonal(DiagonalCost::Euclidean { scale: 100 });
+
+    assert_eq!(shortest_path((4, 4), Movement::Orthogonal), (8, 9));
+    assert_eq!(shortest_path((4, 4), diagonal), (4, 5));
+    assert_eq!(shortest_path((4, 4), euclidean), (564, 5));
+
+    assert_eq!(shortest_path((4, 2), Movement::Orthogonal), (6, 7));
+    assert_eq!(shortest_path((4, 2), diagonal), (4, 5));
+    assert_eq!(shortest_path((4, 2), euclidean), (482, 5));
+}
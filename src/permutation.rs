@@ -32,3 +32,20 @@ fn all_permutations_internal<F, T>(
         }
     }
 }
+
+#[test]
+fn tests_all_permutations() {
+    let mut sequence = vec![0, 1, 2, 3, 4];
+    let mut permutations = std::collections::HashSet::new();
+    all_permutations(&mut sequence, &mut |permutation: &Vec<i32>| {
+        permutations.insert(permutation.clone());
+    });
+
+    assert_eq!(permutations.len(), 120);
+    assert!(permutations.contains(&vec![4, 3, 2, 1, 0]));
+    assert!(permutations.iter().all(|permutation| {
+        let mut sorted = permutation.clone();
+        sorted.sort();
+        sorted == vec![0, 1, 2, 3, 4]
+    }));
+}
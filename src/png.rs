@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::{self, Write};
+
+/// Write an 8-bit RGB PNG image, with `pixels` containing the red, green and blue
+/// bytes of each pixel in row order.
+///
+/// The image data is stored uncompressed, which keeps the encoder small at the cost
+/// of larger files.
+pub fn write_rgb(path: &str, width: u32, height: u32, pixels: &[u8]) -> io::Result<()> {
+    // Color type 2 is truecolor:
+    write_image(path, width, height, 2, 3, pixels)
+}
+
+fn write_image(
+    path: &str,
+    width: u32,
+    height: u32,
+    color_type: u8,
+    bytes_per_pixel: usize,
+    pixels: &[u8],
+) -> io::Result<()> {
+    let pixel_count = (width * height) as usize;
+    if pixels.len() != pixel_count * bytes_per_pixel {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Expected {} bytes for a {}x{} image, got {}",
+                pixel_count * bytes_per_pixel,
+                width,
+                height,
+                pixels.len()
+            ),
+        ));
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, default compression, filtering and no interlacing:
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    // Each row is preceded by a filter type byte, where 0 means no filtering:
+    let mut scanlines = Vec::with_capacity(pixels.len() + height as usize);
+    if width > 0 {
+        for row in pixels.chunks(width as usize * bytes_per_pixel) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+    }
+
+    let mut file = File::create(path)?;
+    file.write_all(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A])?;
+    write_chunk(&mut file, b"IHDR", &header)?;
+    write_chunk(&mut file, b"IDAT", &zlib_stored(&scanlines))?;
+    write_chunk(&mut file, b"IEND", &[])
+}
+
+fn write_chunk(writer: &mut impl Write, chunk_type: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(chunk_type)?;
+    writer.write_all(data)?;
+    let crc = crc32(chunk_type.iter().chain(data.iter()));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+/// Wrap data in a zlib stream using uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK_SIZE: usize = 0xFFFF;
+
+    // Deflate with a 32K window and no preset dictionary, with a header checksum making
+    // the first two bytes divisible by 31:
+    let mut result = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK_SIZE).peekable();
+    if blocks.peek().is_none() {
+        // A single empty final block:
+        result.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let length = block.len() as u16;
+        result.push(if is_final { 1 } else { 0 });
+        result.extend_from_slice(&length.to_le_bytes());
+        result.extend_from_slice(&(!length).to_le_bytes());
+        result.extend_from_slice(block);
+    }
+    result.extend_from_slice(&adler32(data).to_be_bytes());
+    result
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MODULO: u32 = 65521;
+    let (a, b) = data.iter().fold((1, 0), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % MODULO;
+        (a, (b + a) % MODULO)
+    });
+    (b << 16) | a
+}
+
+fn crc32<'a>(data: impl Iterator<Item = &'a u8>) -> u32 {
+    !data.fold(0xFFFF_FFFF, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[test]
+fn tests_checksums() {
+    assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    assert_eq!(crc32(b"IEND".iter()), 0xAE42_6082);
+    assert_eq!(
+        zlib_stored(&[]),
+        vec![0x78, 0x01, 1, 0, 0, 0xFF, 0xFF, 0, 0, 0, 1]
+    );
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+/// A position or offset on a two dimensional integer grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Offsets to the four orthogonal neighbors of a point.
+pub const DIRECTIONS: [Point; 4] = [
+    Point { x: 0, y: 1 },
+    Point { x: 0, y: -1 },
+    Point { x: -1, y: 0 },
+    Point { x: 1, y: 0 },
+];
+
+impl Point {
+    pub const ORIGIN: Point = Point { x: 0, y: 0 };
+
+    pub const fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    pub fn manhattan_distance(self, other: Point) -> u32 {
+        ((self.x - other.x).abs() + (self.y - other.y).abs()) as u32
+    }
+
+    /// The four orthogonal neighbors of this point.
+    pub fn neighbors4(self) -> impl Iterator<Item = Point> {
+        DIRECTIONS.iter().map(move |&direction| self + direction)
+    }
+}
+
+impl From<(i32, i32)> for Point {
+    fn from((x, y): (i32, i32)) -> Point {
+        Point { x, y }
+    }
+}
+
+impl From<Point> for (i32, i32) {
+    fn from(point: Point) -> (i32, i32) {
+        (point.x, point.y)
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, other: Point) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<i32> for Point {
+    type Output = Point;
+
+    fn mul(self, factor: i32) -> Point {
+        Point::new(self.x * factor, self.y * factor)
+    }
+}
+
+#[test]
+fn tests_arithmetic() {
+    let a = Point::new(3, -2);
+    let b = Point::new(-1, 5);
+    assert_eq!(a + b, Point::new(2, 3));
+    assert_eq!(a - b, Point::new(4, -7));
+    assert_eq!(a * 3, Point::new(9, -6));
+    assert_eq!(a * -1 + a, Point::ORIGIN);
+
+    let mut c = a;
+    c += b;
+    assert_eq!(c, Point::new(2, 3));
+
+    assert_eq!(Point::from((7, 8)), Point::new(7, 8));
+    assert_eq!(<(i32, i32)>::from(Point::new(7, 8)), (7, 8));
+    assert_eq!(Point::new(-4, 2).to_string(), "(-4, 2)");
+}
+
+#[test]
+fn tests_manhattan_distance() {
+    assert_eq!(Point::ORIGIN.manhattan_distance(Point::ORIGIN), 0);
+    assert_eq!(Point::new(3, 4).manhattan_distance(Point::ORIGIN), 7);
+    assert_eq!(Point::new(-3, 4).manhattan_distance(Point::new(2, -1)), 10);
+    assert_eq!(
+        Point::new(2, -1).manhattan_distance(Point::new(-3, 4)),
+        Point::new(-3, 4).manhattan_distance(Point::new(2, -1))
+    );
+}
+
+#[test]
+fn tests_neighbors4() {
+    let mut neighbors: Vec<Point> = Point::new(1, 1).neighbors4().collect();
+    neighbors.sort();
+    assert_eq!(
+        neighbors,
+        vec![
+            Point::new(0, 1),
+            Point::new(1, 0),
+            Point::new(1, 2),
+            Point::new(2, 1)
+        ]
+    );
+    assert!(Point::new(5, -5)
+        .neighbors4()
+        .all(|neighbor| neighbor.manhattan_distance(Point::new(5, -5)) == 1));
+}
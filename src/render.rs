@@ -0,0 +1,143 @@
+use crate::grid::Grid2D;
+use std::convert::TryFrom;
+use std::io;
+
+/// A color given by its red, green and blue components.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(0xFF, 0xFF, 0xFF);
+}
+
+/// The cell values of a grid scaled up so that each cell becomes a square of `scale`
+/// by `scale` pixels, in row order.
+fn scaled_pixels(grid: &Grid2D<u8>, scale: usize) -> Vec<u8> {
+    let width = grid.width() * scale;
+    let height = grid.height() * scale;
+    let mut pixels = Vec::with_capacity(width * height);
+    for pixel_y in 0..height {
+        for pixel_x in 0..width {
+            pixels.push(grid[(pixel_x / scale, pixel_y / scale)]);
+        }
+    }
+    pixels
+}
+
+/// Write a grid as a PNG image, where each cell value is an index into `palette` and
+/// each cell is drawn as a square of `scale` by `scale` pixels.
+pub fn to_png(grid: &Grid2D<u8>, palette: &[Rgb], scale: usize, path: &str) -> io::Result<()> {
+    let mut pixels = Vec::with_capacity(grid.width() * grid.height() * scale * scale * 3);
+    for value in scaled_pixels(grid, scale) {
+        let Rgb(red, green, blue) = palette.get(usize::from(value)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("No color in the palette for cell value {}", value),
+            )
+        })?;
+        pixels.extend_from_slice(&[*red, *green, *blue]);
+    }
+
+    let (width, height) = (grid.width() * scale, grid.height() * scale);
+    crate::png::write_rgb(path, width as u32, height as u32, &pixels)
+}
+
+/// Write grids of the same size as the frames of an animated GIF image, drawn as in
+/// `to_png`, with each frame shown for `delay` hundredths of a second.
+pub fn to_gif(
+    frames: &[Grid2D<u8>],
+    palette: &[Rgb],
+    scale: usize,
+    delay: u16,
+    path: &str,
+) -> io::Result<()> {
+    let (width, height) = frames
+        .first()
+        .map_or((0, 0), |frame| (frame.width(), frame.height()));
+    if frames
+        .iter()
+        .any(|frame| (frame.width(), frame.height()) != (width, height))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "All frames must have the same size",
+        ));
+    }
+
+    let gif_dimension = |cells: usize| {
+        cells
+            .checked_mul(scale)
+            .and_then(|pixels| u16::try_from(pixels).ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "GIF images can be at most 65535 pixels wide and high",
+                )
+            })
+    };
+    let (gif_width, gif_height) = (gif_dimension(width)?, gif_dimension(height)?);
+
+    let colors: Vec<[u8; 3]> = palette
+        .iter()
+        .map(|&Rgb(red, green, blue)| [red, green, blue])
+        .collect();
+    let pixels: Vec<Vec<u8>> = frames
+        .iter()
+        .map(|frame| scaled_pixels(frame, scale))
+        .collect();
+    crate::gif::write_animation(path, gif_width, gif_height, &colors, &pixels, delay)
+}
+
+#[test]
+fn tests_to_png() {
+    let mut grid = Grid2D::new(2, 2, 0);
+    grid[(1, 0)] = 1;
+    grid[(0, 1)] = 2;
+    let palette = [Rgb::BLACK, Rgb::WHITE, Rgb(0xFF, 0, 0)];
+
+    let path = std::env::temp_dir().join("advent_of_code_render.png");
+    let path = path.to_str().unwrap();
+    to_png(&grid, &palette, 3, path).unwrap();
+
+    let png = std::fs::read(path).unwrap();
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(u32::from_be_bytes([png[16], png[17], png[18], png[19]]), 6);
+    assert_eq!(u32::from_be_bytes([png[20], png[21], png[22], png[23]]), 6);
+    // Bit depth 8 with color type 2 (RGB):
+    assert_eq!(&png[24..26], &[8, 2]);
+
+    let error = to_png(&grid, &palette[..2], 3, path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn tests_to_gif() {
+    let mut frames = vec![Grid2D::new(2, 2, 0); 3];
+    frames[1][(1, 0)] = 1;
+    frames[2][(1, 1)] = 1;
+    let palette = [Rgb::BLACK, Rgb::WHITE];
+
+    let path = std::env::temp_dir().join("advent_of_code_render.gif");
+    let path = path.to_str().unwrap();
+    to_gif(&frames, &palette, 3, 10, path).unwrap();
+
+    let gif = std::fs::read(path).unwrap();
+    assert_eq!(&gif[0..6], b"GIF89a");
+    assert_eq!(u16::from_le_bytes([gif[6], gif[7]]), 6);
+    assert_eq!(u16::from_le_bytes([gif[8], gif[9]]), 6);
+    assert_eq!(gif.iter().filter(|&&byte| byte == 0x2C).count(), 3);
+    assert_eq!(gif.last(), Some(&0x3B));
+
+    frames.push(Grid2D::new(3, 2, 0));
+    let error = to_gif(&frames, &palette, 3, 10, path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+    // Too wide to be described in a GIF image once scaled:
+    let wide_frames = [Grid2D::new(21_846, 1, 0)];
+    let error = to_gif(&wide_frames, &palette, 3, 10, path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(to_gif(&wide_frames, &palette, 2, 10, path).is_ok());
+    std::fs::remove_file(path).unwrap();
+}
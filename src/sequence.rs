@@ -0,0 +1,134 @@
+use std::fmt::Display;
+
+/// The length of tokens written out comma separated.
+fn written_length<T: Display>(tokens: &[T]) -> usize {
+    let separators = tokens.len().saturating_sub(1);
+    tokens
+        .iter()
+        .map(|token| token.to_string().len())
+        .sum::<usize>()
+        + separators
+}
+
+/// Factor a token sequence into a main routine calling at most `max_functions` functions,
+/// where each function is a subsequence of tokens, so that expanding the calls of the main
+/// routine gives back the original sequence.
+///
+/// Written comma separated, with calls in the main routine written as a single character
+/// (such as `A,B,A`), the main routine and each function must be at most `max_len`
+/// characters long. Returns the main routine as indices into the returned functions.
+pub fn compress<T>(
+    tokens: &[T],
+    max_functions: usize,
+    max_len: usize,
+) -> Option<(Vec<usize>, Vec<Vec<T>>)>
+where
+    T: Clone + Display + PartialEq,
+{
+    fn factorize<'a, T: Display + PartialEq>(
+        remaining: &'a [T],
+        functions: &mut Vec<&'a [T]>,
+        routine: &mut Vec<usize>,
+        max_functions: usize,
+        max_len: usize,
+    ) -> bool {
+        if remaining.is_empty() {
+            return true;
+        } else if routine.len() * 2 + 1 > max_len {
+            // No room for another ",X" in the main routine.
+            return false;
+        }
+
+        for function_index in 0..functions.len() {
+            let function = functions[function_index];
+            if remaining.starts_with(function) {
+                routine.push(function_index);
+                if factorize(
+                    &remaining[function.len()..],
+                    functions,
+                    routine,
+                    max_functions,
+                    max_len,
+                ) {
+                    return true;
+                }
+                routine.pop();
+            }
+        }
+
+        if functions.len() < max_functions {
+            for function_length in 1..=remaining.len() {
+                let function = &remaining[0..function_length];
+                if written_length(function) > max_len {
+                    break;
+                }
+
+                routine.push(functions.len());
+                functions.push(function);
+                if factorize(
+                    &remaining[function_length..],
+                    functions,
+                    routine,
+                    max_functions,
+                    max_len,
+                ) {
+                    return true;
+                }
+                functions.pop();
+                routine.pop();
+            }
+        }
+
+        false
+    }
+
+    let mut functions = Vec::new();
+    let mut routine = Vec::new();
+    if !factorize(tokens, &mut functions, &mut routine, max_functions, max_len) {
+        return None;
+    }
+
+    let functions = functions.into_iter().map(<[T]>::to_vec).collect();
+    Some((routine, functions))
+}
+
+#[test]
+fn tests_compress() {
+    let tokens = vec![1, 2, 3, 1, 2, 3, 1, 2, 3, 1, 2, 3];
+    let (routine, functions) = compress(&tokens, 1, 20).unwrap();
+    assert_eq!(routine, vec![0, 0, 0, 0]);
+    assert_eq!(functions, vec![vec![1, 2, 3]]);
+
+    let tokens = vec!["L", "10", "R", "R", "L", "10", "L", "L", "R", "R"];
+    let (routine, functions) = compress(&tokens, 3, 10).unwrap();
+    let expanded: Vec<&str> = routine
+        .iter()
+        .flat_map(|&call| functions[call].clone())
+        .collect();
+    assert_eq!(expanded, tokens);
+    assert!(functions.len() <= 3);
+    assert!(functions
+        .iter()
+        .all(|function| written_length(function) <= 10));
+
+    assert_eq!(compress::<u8>(&[], 3, 20), Some((vec![], vec![])));
+}
+
+#[test]
+fn tests_compress_impossible() {
+    // Without any repetition, three functions of at most three tokens cover at most nine:
+    let tokens: Vec<u8> = (0..10).collect();
+    assert_eq!(compress(&tokens, 3, 5), None);
+    assert!(compress(&tokens[..9], 3, 5).is_some());
+    assert!(compress(&tokens, 4, 7).is_some());
+
+    // A token longer than a function may be can never be called:
+    assert_eq!(compress(&[123], 3, 2), None);
+
+    // The main routine needs seven characters for four calls:
+    assert_eq!(compress(&[1, 1, 1, 1], 1, 1), None);
+    assert_eq!(
+        compress(&[1, 1, 1, 1], 1, 7),
+        Some((vec![0; 4], vec![vec![1]]))
+    );
+}
@@ -0,0 +1,26 @@
+use crate::day18::KeyBitset;
+
+/// Iterate over all submasks of a key bitset, starting with the bitset itself and ending with zero.
+pub fn subsets(mask: KeyBitset) -> impl Iterator<Item = KeyBitset> {
+    let mut next_subset = Some(mask);
+    std::iter::from_fn(move || {
+        let subset = next_subset?;
+        next_subset = if subset == 0 {
+            None
+        } else {
+            Some((subset - 1) & mask)
+        };
+        Some(subset)
+    })
+}
+
+#[test]
+fn tests_subsets() {
+    let all_subsets: Vec<KeyBitset> = subsets(0b1011).collect();
+    assert_eq!(
+        all_subsets,
+        vec![0b1011, 0b1010, 0b1001, 0b1000, 0b0011, 0b0010, 0b0001, 0b0000]
+    );
+
+    assert_eq!(subsets(0).collect::<Vec<KeyBitset>>(), vec![0]);
+}
@@ -0,0 +1,98 @@
+use advent_of_code_rs::registry;
+use std::path::PathBuf;
+
+/// The expected answers to the real inputs, as (day, part, expected) entries.
+const ANSWERS: &[(u8, u8, &str)] = &[
+    (1, 1, "3262358"),
+    (1, 2, "4890696"),
+    (2, 1, "4570637"),
+    (2, 2, "5485"),
+    (3, 1, "375"),
+    (3, 2, "14746"),
+    (4, 1, "1675"),
+    (4, 2, "1142"),
+    (5, 1, "15097178"),
+    (5, 2, "1558663"),
+    (6, 1, "273985"),
+    (6, 2, "460"),
+    (7, 1, "51679"),
+    (7, 2, "19539216"),
+    (8, 1, "2413"),
+    (8, 2, "███   ██  ███  ████ ███  \n█  █ █  █ █  █    █ █  █ \n███  █    █  █   █  ███  \n█  █ █    ███   █   █  █ \n█  █ █  █ █    █    █  █ \n███   ██  █    ████ ███  "),
+    (9, 1, "3601950151"),
+    (9, 2, "64236"),
+    (10, 1, "319"),
+    (10, 2, "517"),
+    (11, 1, "1686"),
+    (11, 2, include_str!("../src/day11_part2_output.txt")),
+    (12, 1, "6220"),
+    (12, 2, "548525804273976"),
+    (13, 1, "462"),
+    (13, 2, "23981"),
+    (14, 1, "1590844"),
+    (14, 2, "1184209"),
+    (15, 1, "208"),
+    (15, 2, "306"),
+    (16, 1, "37153056"),
+    (16, 2, "60592199"),
+    (17, 1, "11140"),
+    (17, 2, "1113108"),
+    (18, 1, "4248"),
+    (18, 2, "1878"),
+    (19, 1, "112"),
+    (19, 2, "18261982"),
+    (20, 1, "580"),
+    (20, 2, "6362"),
+    (21, 1, "19358688"),
+    (21, 2, "1141236756"),
+    (22, 1, "6526"),
+    (22, 2, "79855812422607"),
+    (23, 1, "16549"),
+    (23, 2, "11462"),
+    (24, 1, "11042850"),
+    (24, 2, "1967"),
+    (25, 1, "319815680"),
+];
+
+fn input_path(day: u8) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join(format!("day{:02}_input.txt", day))
+}
+
+#[test]
+fn tests_answers() {
+    let solutions = registry();
+    let mut failures = Vec::new();
+
+    for &(day, part, expected) in ANSWERS {
+        let path = input_path(day);
+        let input = match std::fs::read_to_string(&path) {
+            Ok(input) => input,
+            Err(error) => {
+                eprintln!(
+                    "Skipping day {} part {}: cannot read {}: {}",
+                    day,
+                    part,
+                    path.display(),
+                    error
+                );
+                continue;
+            }
+        };
+
+        let solution = &solutions[&day];
+        let answer = match part {
+            1 => solution.part1(&input),
+            _ => solution.part2(&input),
+        };
+        if answer != expected.trim_end_matches('\n') {
+            failures.push(format!(
+                "Day {} part {}: expected {:?}, got {:?}",
+                day, part, expected, answer
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,76 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const SAMPLE: &str = "#########
+#b.A.@.a#
+#########";
+
+fn run_aoc(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_aoc"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn tests_stdin_input() {
+    let output = run_aoc(&["18", "1"], SAMPLE);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "8\n");
+}
+
+#[test]
+fn tests_file_input() {
+    let path = std::env::temp_dir().join("aoc_cli_day18_sample.txt");
+    std::fs::write(&path, SAMPLE).unwrap();
+    let output = run_aoc(&["18", "1", "--input", path.to_str().unwrap()], "");
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "8\n");
+}
+
+#[test]
+fn tests_time() {
+    let output = run_aoc(&["--time", "18", "1"], SAMPLE);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "8\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Solved in ") && stderr.ends_with(" ms\n"));
+}
+
+#[test]
+fn tests_unknown_day() {
+    let output = run_aoc(&["26", "1"], SAMPLE);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("No solution for day 26 part 1"));
+
+    let output = run_aoc(&["eighteen"], SAMPLE);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("Usage: aoc"));
+}
+
+#[test]
+fn tests_list() {
+    let output = run_aoc(&["--list"], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 25);
+    assert!(lines[0].starts_with("Day  1: input "));
+    assert!(lines[0].ends_with("day01_input.txt"));
+    assert!(lines[24].starts_with("Day 25: "));
+}